@@ -0,0 +1,101 @@
+//! Wire format of the audsrv service.
+//!
+//! This crate only deals with the layout of requests and sample buffers, and does not touch the
+//! hardware.
+
+#![no_std]
+#![deny(missing_debug_implementations)]
+
+/// Server id of the audio service.
+pub const AUDSRV_SID: u32 = 0x0870_884d;
+
+/// Largest PCM payload of a single play request.
+pub const PLAY_CHUNK_SIZE: usize = 4096 - 4;
+
+/// Highest volume accepted by the service.
+pub const MAX_VOLUME: u32 = 100;
+
+/// Number of SPU2 voices available to ADPCM samples.
+pub const VOICES: u32 = 24;
+
+pub mod fno {
+    pub const INIT: u32 = 0x00;
+    pub const QUIT: u32 = 0x01;
+    pub const FORMAT: u32 = 0x02;
+    pub const PLAY_AUDIO: u32 = 0x03;
+    pub const WAIT_AUDIO: u32 = 0x04;
+    pub const STOP_AUDIO: u32 = 0x05;
+    pub const SET_VOLUME: u32 = 0x06;
+    pub const AVAILABLE: u32 = 0x08;
+    pub const QUEUED: u32 = 0x09;
+    pub const INIT_ADPCM: u32 = 0x17;
+    pub const LOAD_ADPCM: u32 = 0x18;
+    pub const PLAY_ADPCM: u32 = 0x19;
+    pub const SET_ADPCM_VOL: u32 = 0x1a;
+}
+
+pub mod error {
+    pub const NOT_INITIALIZED: i32 = 1;
+    pub const OUT_OF_MEMORY: i32 = 2;
+    pub const ARGS: i32 = 3;
+    pub const FORMAT_NOT_SUPPORTED: i32 = 4;
+}
+
+/// Header of the sample buffers sent to [`fno::LOAD_ADPCM`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AdpcmHeader {
+    pub channels: u8,
+    pub looping: bool,
+    /// Playback rate relative to 48kHz, in 4.12 fixed point.
+    pub pitch: u32,
+    pub samples: u32,
+}
+
+impl AdpcmHeader {
+    pub const SIZE: usize = 16;
+
+    const MAGIC: &'static [u8; 4] = b"APCM";
+    const VERSION: u8 = 1;
+
+    pub fn encode(&self) -> [u8; Self::SIZE] {
+        let mut buf = [0; Self::SIZE];
+        buf[0..4].copy_from_slice(Self::MAGIC);
+        buf[4] = Self::VERSION;
+        buf[5] = self.channels;
+        buf[6] = self.looping as u8;
+        buf[8..12].copy_from_slice(&self.pitch.to_le_bytes());
+        buf[12..16].copy_from_slice(&self.samples.to_le_bytes());
+        buf
+    }
+}
+
+/// Pitch of a sample recorded at `rate` Hz.
+pub fn pitch(rate: u32) -> u32 {
+    ((rate as u64 * 4096) / 48000) as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pitch() {
+        assert_eq!(pitch(48000), 0x1000);
+        assert_eq!(pitch(24000), 0x800);
+        assert_eq!(pitch(44100), 0xeb3);
+    }
+
+    #[test]
+    fn test_adpcm_header() {
+        let header = AdpcmHeader {
+            channels: 1,
+            looping: true,
+            pitch: 0x1000,
+            samples: 280,
+        };
+        let raw = header.encode();
+        assert_eq!(&raw[0..4], b"APCM");
+        assert_eq!(&raw[4..8], &[1, 1, 1, 0]);
+        assert_eq!(&raw[8..16], &[0, 0x10, 0, 0, 0x18, 0x01, 0, 0]);
+    }
+}
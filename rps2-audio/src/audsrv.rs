@@ -0,0 +1,272 @@
+use alloc::vec;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicU32, Ordering};
+
+use rps2_sif::iop;
+use rps2_sif::loadfile;
+use rps2_sif::rpc::{ClientPool, Service};
+
+use crate::adpcm::{self, Vag, BLOCK_SAMPLES, BLOCK_SIZE};
+use crate::proto::{self, error, fno, AdpcmHeader, AUDSRV_SID, MAX_VOLUME, PLAY_CHUNK_SIZE};
+use crate::wav::Wav;
+
+const LIBSD: &str = "rom0:LIBSD";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    /// The driver could not be loaded.
+    Load(loadfile::Error),
+    /// [`init_adpcm`] was not called.
+    NotInitialized,
+    /// The IOP or the SPU2 ran out of memory.
+    NoMemory,
+    /// An argument is out of range, e.g. a voice past [`proto::VOICES`].
+    InvalidArgument,
+    /// The format is not supported by the driver.
+    UnsupportedFormat,
+    /// Any other driver error code.
+    Other(i32),
+}
+
+impl Error {
+    pub fn from_code(code: i32) -> Self {
+        match code {
+            error::NOT_INITIALIZED => Self::NotInitialized,
+            error::OUT_OF_MEMORY => Self::NoMemory,
+            error::ARGS => Self::InvalidArgument,
+            error::FORMAT_NOT_SUPPORTED => Self::UnsupportedFormat,
+            code => Self::Other(code),
+        }
+    }
+}
+
+impl From<loadfile::Error> for Error {
+    fn from(err: loadfile::Error) -> Self {
+        Self::Load(err)
+    }
+}
+
+pub type Result<T> = core::result::Result<T, Error>;
+
+static AUDSRV: ClientPool = ClientPool::new(AUDSRV_SID);
+static SERVICE: Service = Service::new(AUDSRV_SID);
+
+/// Ids handed to the driver for the loaded samples.
+static NEXT_SAMPLE: AtomicU32 = AtomicU32::new(1);
+
+fn call(fno: u32, args: &[u32]) -> i32 {
+    let mut send = [0; 16];
+    for (chunk, arg) in send.chunks_exact_mut(4).zip(args) {
+        chunk.copy_from_slice(&arg.to_le_bytes());
+    }
+
+    let mut recv = [0; 4];
+    AUDSRV.get().call(fno, &send[..args.len() * 4], &mut recv);
+    i32::from_le_bytes(recv)
+}
+
+fn check(code: i32) -> Result<()> {
+    match code {
+        0 => Ok(()),
+        code => Err(Error::from_code(code)),
+    }
+}
+
+/// Load and initialize the audsrv driver, unless it is already running.
+///
+/// audsrv is not part of the boot ROM, `irx` is the driver module (e.g. embedded with
+/// `include_bytes!`), only loaded if the driver is not found. Needed again after an IOP reset.
+pub fn init(irx: &[u8]) -> Result<()> {
+    SERVICE.init(|running| {
+        // The driver might have been loaded by a previous program
+        if !running {
+            loadfile::load_module(LIBSD, &[])?;
+            loadfile::load_module_buffer(irx, &[])?;
+        }
+        check(call(fno::INIT, &[]))
+    })
+}
+
+/// Stop playback and release the SPU2.
+pub fn quit() {
+    call(fno::QUIT, &[]);
+}
+
+/// Format of the PCM stream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Format {
+    /// Sample rate in Hz, resampled to 48kHz by the driver.
+    pub rate: u32,
+    /// Bits per sample, 8 or 16.
+    pub bits: u32,
+    /// 1 for mono, 2 for interleaved stereo.
+    pub channels: u32,
+}
+
+impl Format {
+    /// Bytes of a sample of every channel.
+    pub fn frame_size(&self) -> usize {
+        (self.bits / 8 * self.channels) as usize
+    }
+}
+
+impl Default for Format {
+    fn default() -> Self {
+        Self {
+            rate: 48000,
+            bits: 16,
+            channels: 2,
+        }
+    }
+}
+
+/// Set the format of the PCM stream, dropping what is queued.
+pub fn set_format(format: Format) -> Result<()> {
+    check(call(
+        fno::FORMAT,
+        &[format.rate, format.bits, format.channels],
+    ))
+}
+
+/// Queue PCM data, returns how much was queued before the ring buffer filled up.
+pub fn play(pcm: &[u8]) -> Result<usize> {
+    let mut queued = 0;
+    for chunk in pcm.chunks(PLAY_CHUNK_SIZE) {
+        let mut send = vec![0; 4 + chunk.len()];
+        send[0..4].copy_from_slice(&(chunk.len() as u32).to_le_bytes());
+        send[4..].copy_from_slice(chunk);
+
+        let mut recv = [0; 4];
+        AUDSRV.get().call(fno::PLAY_AUDIO, &send, &mut recv);
+        let res = i32::from_le_bytes(recv);
+        if res < 0 {
+            return Err(Error::from_code(-res));
+        }
+
+        queued += res as usize;
+        if (res as usize) < chunk.len() {
+            break;
+        }
+    }
+    Ok(queued)
+}
+
+/// Queue all of `pcm`, waiting for room in the ring buffer.
+pub fn play_all(mut pcm: &[u8]) -> Result<()> {
+    while !pcm.is_empty() {
+        wait(pcm.len().min(PLAY_CHUNK_SIZE))?;
+        let queued = play(pcm)?;
+        pcm = &pcm[queued..];
+    }
+    Ok(())
+}
+
+/// Block until `bytes` can be queued without overflowing.
+pub fn wait(bytes: usize) -> Result<()> {
+    check(call(fno::WAIT_AUDIO, &[bytes as u32]))
+}
+
+/// Stop playback and drop what is queued.
+pub fn stop() -> Result<()> {
+    check(call(fno::STOP_AUDIO, &[]))
+}
+
+/// Room left in the ring buffer, in bytes.
+pub fn available() -> usize {
+    call(fno::AVAILABLE, &[]).max(0) as usize
+}
+
+/// Bytes queued and not played yet.
+pub fn queued() -> usize {
+    call(fno::QUEUED, &[]).max(0) as usize
+}
+
+/// Set the volume of the PCM stream, from `0` to [`MAX_VOLUME`].
+pub fn set_volume(volume: u32) -> Result<()> {
+    check(call(fno::SET_VOLUME, &[volume.min(MAX_VOLUME)]))
+}
+
+/// Prepare the SPU2 voices for ADPCM samples, freeing the samples loaded before.
+pub fn init_adpcm() -> Result<()> {
+    check(call(fno::INIT_ADPCM, &[]))
+}
+
+/// A mono ADPCM sample in SPU2 memory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sample {
+    id: u32,
+    pitch: u32,
+    looping: bool,
+}
+
+impl Sample {
+    /// Upload ADPCM blocks recorded at `sample_rate` Hz, e.g. from
+    /// [`adpcm::encode`](crate::adpcm::encode).
+    pub fn load(data: &[u8], sample_rate: u32, looping: bool) -> Result<Self> {
+        let header = AdpcmHeader {
+            channels: 1,
+            looping,
+            pitch: proto::pitch(sample_rate),
+            samples: (data.len() / BLOCK_SIZE * BLOCK_SAMPLES) as u32,
+        };
+
+        // Staged in the IOP heap, which receives whole quadwords
+        let len = AdpcmHeader::SIZE + data.len();
+        let mut buf = vec![0; len.next_multiple_of(16)];
+        buf[..AdpcmHeader::SIZE].copy_from_slice(&header.encode());
+        buf[AdpcmHeader::SIZE..len].copy_from_slice(data);
+
+        let addr = iop::alloc_heap(buf.len()).ok_or(Error::NoMemory)?;
+        iop::write(addr, &buf);
+
+        let id = NEXT_SAMPLE.fetch_add(1, Ordering::Relaxed);
+        let mut recv = [0; 16];
+        let send = [addr, len as u32, id];
+        let send: Vec<u8> = send.iter().flat_map(|word| word.to_le_bytes()).collect();
+        AUDSRV.get().call(fno::LOAD_ADPCM, &send, &mut recv);
+        iop::free_heap(addr);
+        check(i32::from_le_bytes(recv[0..4].try_into().unwrap()))?;
+
+        Ok(Self {
+            id,
+            pitch: header.pitch,
+            looping,
+        })
+    }
+
+    /// Decode a VAG file and upload its data.
+    pub fn load_vag(file: &[u8]) -> Result<Self> {
+        let vag = Vag::parse(file).map_err(|_| Error::UnsupportedFormat)?;
+        Self::load(&vag.data, vag.sample_rate, vag.is_looping())
+    }
+
+    /// Encode a WAV file and upload it, channels are mixed down to mono.
+    pub fn load_wav(file: &[u8], looping: bool) -> Result<Self> {
+        let wav = Wav::parse(file).map_err(|_| Error::UnsupportedFormat)?;
+        let data = adpcm::encode(&wav.mono(), looping);
+        Self::load(&data, wav.sample_rate, looping)
+    }
+
+    pub fn pitch(&self) -> u32 {
+        self.pitch
+    }
+
+    pub fn is_looping(&self) -> bool {
+        self.looping
+    }
+
+    /// Play on `voice`, or on any free voice if `None`, returns the voice used.
+    pub fn play(&self, voice: Option<u32>) -> Result<u32> {
+        // Negative voices let the driver pick
+        let voice = voice.map_or(-1, |voice| voice as i32);
+        match call(fno::PLAY_ADPCM, &[voice as u32, self.id]) {
+            res if res < 0 => Err(Error::from_code(-res)),
+            voice => Ok(voice as u32),
+        }
+    }
+}
+
+/// Set the volume of an ADPCM voice, from `0` to [`MAX_VOLUME`].
+pub fn set_voice_volume(voice: u32, volume: u32) -> Result<()> {
+    check(call(fno::SET_ADPCM_VOL, &[voice, volume.min(MAX_VOLUME)]))
+}
@@ -0,0 +1,18 @@
+//! Audio output through the audsrv driver on the IOP.
+//!
+//! PCM is streamed to a ring buffer mixed by the driver, while short sounds are uploaded once as
+//! SPU2 ADPCM [`Sample`]s and played on hardware voices. [`adpcm`] converts PCM to the format of
+//! the SPU2 and back, [`wav`] reads PCM from WAV files.
+#![no_std]
+#![deny(missing_debug_implementations)]
+extern crate alloc;
+
+mod audsrv;
+
+pub use rps2_audio_proto as proto;
+pub use rps2_formats::{adpcm, wav};
+
+pub use audsrv::{
+    available, init, init_adpcm, play, play_all, queued, quit, set_format, set_voice_volume,
+    set_volume, stop, wait, Error, Format, Result, Sample,
+};
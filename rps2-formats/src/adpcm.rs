@@ -0,0 +1,339 @@
+//! SPU2 ADPCM, the sample format of the sound processor, and the VAG files wrapping it.
+//!
+//! Every 16 byte block holds 28 samples, encoded as 4 bit differences from a prediction based on
+//! the two previous samples. Nothing here touches the hardware, so asset tools can share it.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// Size of an encoded block.
+pub const BLOCK_SIZE: usize = 16;
+
+/// Samples in a block.
+pub const BLOCK_SAMPLES: usize = 28;
+
+/// Prediction filters, applied to the previous and second to last samples, over 64.
+const FILTERS: [[i32; 2]; 5] = [[0, 0], [60, 0], [115, -52], [98, -55], [122, -60]];
+
+/// Largest shift the SPU2 decodes correctly.
+const SHIFT_MAX: u8 = 12;
+
+/// Flags of a block.
+pub mod flags {
+    /// Last block of the sample, or of the loop.
+    pub const END: u8 = 0x01;
+    /// Jump back to the loop start after the end, instead of stopping.
+    pub const REPEAT: u8 = 0x02;
+    /// First block of the loop.
+    pub const LOOP_START: u8 = 0x04;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    /// The data is not a whole number of blocks.
+    Truncated,
+    /// A block uses a prediction filter that does not exist.
+    InvalidFilter(u8),
+    /// The VAG header is missing or corrupted.
+    InvalidHeader,
+}
+
+/// Decoder state, the last two samples.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct History(i32, i32);
+
+impl History {
+    fn predict(&self, filter: usize) -> i32 {
+        let [f0, f1] = FILTERS[filter];
+        (self.0 * f0 + self.1 * f1 + 32) >> 6
+    }
+
+    fn push(&mut self, sample: i32) {
+        *self = Self(sample, self.0);
+    }
+}
+
+/// Decode a 4 bit difference, as the SPU2 does.
+fn expand(nibble: i32, shift: u8, prediction: i32) -> i32 {
+    let diff = ((nibble << 28) >> 16) >> shift;
+    (diff + prediction).clamp(i16::MIN as i32, i16::MAX as i32)
+}
+
+/// Encode one block with `filter` and `shift`, returns the nibbles and the squared error.
+fn encode_with(
+    samples: &[i16; BLOCK_SAMPLES],
+    history: &mut History,
+    filter: usize,
+    shift: u8,
+) -> ([u8; BLOCK_SAMPLES], u64) {
+    let mut nibbles = [0; BLOCK_SAMPLES];
+    let mut error = 0;
+
+    for (nibble, &sample) in nibbles.iter_mut().zip(samples) {
+        let prediction = history.predict(filter);
+        let residual = sample as i32 - prediction;
+        // Round to the nearest step
+        let quantized = (((residual << shift) + 0x800) >> 12).clamp(-8, 7);
+        let decoded = expand(quantized, shift, prediction);
+
+        error += (sample as i32 - decoded).pow(2) as u64;
+        *nibble = quantized as u8 & 0xf;
+        history.push(decoded);
+    }
+    (nibbles, error)
+}
+
+/// Encode one block with the filter and shift minimizing the error.
+fn encode_block(
+    samples: &[i16; BLOCK_SAMPLES],
+    history: &mut History,
+    flags: u8,
+) -> [u8; BLOCK_SIZE] {
+    let mut best = None;
+    for filter in 0..FILTERS.len() {
+        for shift in 0..=SHIFT_MAX {
+            let mut state = *history;
+            let (nibbles, error) = encode_with(samples, &mut state, filter, shift);
+            if best.as_ref().map_or(true, |&(_, _, _, best)| error < best) {
+                best = Some((filter, shift, nibbles, error));
+            }
+        }
+    }
+
+    let (filter, shift, nibbles, _) = best.unwrap();
+    // Replay the choice to advance the history
+    encode_with(samples, history, filter, shift);
+
+    let mut block = [0; BLOCK_SIZE];
+    block[0] = (filter as u8) << 4 | shift;
+    block[1] = flags;
+    for (byte, pair) in block[2..].iter_mut().zip(nibbles.chunks_exact(2)) {
+        *byte = pair[0] | pair[1] << 4;
+    }
+    block
+}
+
+/// Encode 16 bit mono PCM, padding the end with silence.
+///
+/// A looping sample repeats from the start, otherwise the voice stops after the last block.
+pub fn encode(samples: &[i16], looping: bool) -> Vec<u8> {
+    let blocks = samples.len().div_ceil(BLOCK_SAMPLES).max(1);
+    let mut data = Vec::with_capacity(blocks * BLOCK_SIZE);
+    let mut history = History::default();
+
+    for index in 0..blocks {
+        let start = (index * BLOCK_SAMPLES).min(samples.len());
+        let chunk = &samples[start..(start + BLOCK_SAMPLES).min(samples.len())];
+        let mut block = [0; BLOCK_SAMPLES];
+        block[..chunk.len()].copy_from_slice(chunk);
+
+        let mut block_flags = 0;
+        if looping && index == 0 {
+            block_flags |= flags::LOOP_START;
+        }
+        if index == blocks - 1 {
+            block_flags |= flags::END;
+            if looping {
+                block_flags |= flags::REPEAT;
+            }
+        }
+        data.extend_from_slice(&encode_block(&block, &mut history, block_flags));
+    }
+    data
+}
+
+/// Decode blocks to 16 bit PCM, up to and including the first one flagged as [`flags::END`].
+pub fn decode(data: &[u8]) -> Result<Vec<i16>, Error> {
+    if data.len() % BLOCK_SIZE != 0 {
+        return Err(Error::Truncated);
+    }
+
+    let mut samples = Vec::with_capacity(data.len() / BLOCK_SIZE * BLOCK_SAMPLES);
+    let mut history = History::default();
+
+    for block in data.chunks_exact(BLOCK_SIZE) {
+        let filter = block[0] >> 4;
+        let shift = block[0] & 0xf;
+        if filter as usize >= FILTERS.len() {
+            return Err(Error::InvalidFilter(filter));
+        }
+
+        for &byte in &block[2..] {
+            for nibble in [byte & 0xf, byte >> 4] {
+                let sample = expand(nibble as i32, shift, history.predict(filter as usize));
+                samples.push(sample as i16);
+                history.push(sample);
+            }
+        }
+
+        if block[1] & flags::END != 0 {
+            break;
+        }
+    }
+    Ok(samples)
+}
+
+/// A VAG file: ADPCM data with a 48 byte big endian header.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Vag {
+    /// Up to 16 ASCII characters.
+    pub name: String,
+    pub sample_rate: u32,
+    /// ADPCM blocks, without the leading silent block.
+    pub data: Vec<u8>,
+}
+
+impl Vag {
+    pub const HEADER_SIZE: usize = 48;
+
+    const MAGIC: &'static [u8; 4] = b"VAGp";
+    const VERSION: u32 = 0x20;
+
+    /// Encode 16 bit mono PCM sampled at `sample_rate` Hz.
+    pub fn from_pcm(name: &str, samples: &[i16], sample_rate: u32, looping: bool) -> Self {
+        Self {
+            name: name.into(),
+            sample_rate,
+            data: encode(samples, looping),
+        }
+    }
+
+    pub fn parse(file: &[u8]) -> Result<Self, Error> {
+        let header = file.get(..Self::HEADER_SIZE).ok_or(Error::InvalidHeader)?;
+        let word =
+            |offset: usize| u32::from_be_bytes(header[offset..offset + 4].try_into().unwrap());
+        if &header[0..4] != Self::MAGIC {
+            return Err(Error::InvalidHeader);
+        }
+
+        let size = word(12) as usize;
+        let data = file[Self::HEADER_SIZE..]
+            .get(..size)
+            .ok_or(Error::Truncated)?;
+        // The first block is conventionally silent, and not part of the sample
+        let data = data.get(BLOCK_SIZE..).unwrap_or(&[]);
+        if data.len() % BLOCK_SIZE != 0 {
+            return Err(Error::Truncated);
+        }
+
+        let name = header[32..48]
+            .iter()
+            .take_while(|&&b| b != 0)
+            .map(|&b| b as char)
+            .collect();
+        Ok(Self {
+            name,
+            sample_rate: word(16),
+            data: data.into(),
+        })
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let size = BLOCK_SIZE + self.data.len();
+        let mut file = Vec::with_capacity(Self::HEADER_SIZE + size);
+        file.extend_from_slice(Self::MAGIC);
+        file.extend_from_slice(&Self::VERSION.to_be_bytes());
+        file.extend_from_slice(&[0; 4]);
+        file.extend_from_slice(&(size as u32).to_be_bytes());
+        file.extend_from_slice(&self.sample_rate.to_be_bytes());
+        file.extend_from_slice(&[0; 12]);
+
+        let mut name = [0; 16];
+        let len = self.name.len().min(name.len());
+        name[..len].copy_from_slice(&self.name.as_bytes()[..len]);
+        file.extend_from_slice(&name);
+
+        file.extend_from_slice(&[0; BLOCK_SIZE]);
+        file.extend_from_slice(&self.data);
+        file
+    }
+
+    /// Whether the sample loops, according to the flags of its last block.
+    pub fn is_looping(&self) -> bool {
+        self.data
+            .chunks_exact(BLOCK_SIZE)
+            .last()
+            .is_some_and(|block| block[1] & flags::REPEAT != 0)
+    }
+
+    pub fn decode(&self) -> Result<Vec<i16>, Error> {
+        decode(&self.data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A triangle wave with a period of 100 samples, starting from silence.
+    fn triangle(len: usize) -> Vec<i16> {
+        (0..len)
+            .map(|i| {
+                let phase = ((i + 25) % 100) as i32;
+                let level = if phase < 50 { phase } else { 100 - phase };
+                ((level - 25) * 1000) as i16
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_adpcm_round_trip() {
+        let pcm = triangle(36 * BLOCK_SAMPLES);
+        let data = encode(&pcm, false);
+        assert_eq!(data.len(), 36 * BLOCK_SIZE);
+
+        let decoded = decode(&data).unwrap();
+        assert_eq!(decoded.len(), pcm.len());
+
+        let max_error = pcm
+            .iter()
+            .zip(&decoded)
+            .map(|(&a, &b)| (a as i32 - b as i32).abs())
+            .max()
+            .unwrap();
+        assert!(max_error < 512, "error {max_error}");
+
+        // Padded with silence up to a whole block
+        let data = encode(&pcm[..30], false);
+        assert_eq!(decode(&data).unwrap().len(), 2 * BLOCK_SAMPLES);
+    }
+
+    #[test]
+    fn test_adpcm_flags() {
+        let one_shot = encode(&[0; 100], false);
+        let blocks: Vec<&[u8]> = one_shot.chunks(BLOCK_SIZE).collect();
+        assert_eq!(blocks.len(), 4);
+        assert_eq!(blocks[0][1], 0);
+        assert_eq!(blocks[3][1], flags::END);
+
+        let looping = encode(&[0; 100], true);
+        assert_eq!(looping[1], flags::LOOP_START);
+        assert_eq!(looping[3 * BLOCK_SIZE + 1], flags::END | flags::REPEAT);
+
+        // Decoding stops at the end flag
+        let mut data = one_shot.clone();
+        data.extend_from_slice(&one_shot);
+        assert_eq!(decode(&data).unwrap().len(), 4 * BLOCK_SAMPLES);
+
+        assert_eq!(decode(&[0; 10]), Err(Error::Truncated));
+        let mut bad = [0; BLOCK_SIZE];
+        bad[0] = 0x50;
+        assert_eq!(decode(&bad), Err(Error::InvalidFilter(5)));
+    }
+
+    #[test]
+    fn test_vag() {
+        let vag = Vag::from_pcm("jump", &triangle(200), 22050, true);
+        let file = vag.encode();
+        assert_eq!(&file[0..4], b"VAGp");
+        assert_eq!(&file[16..20], &22050u32.to_be_bytes());
+        assert_eq!(&file[32..36], b"jump");
+        // The leading silent block is counted in the size
+        assert_eq!(file.len(), Vag::HEADER_SIZE + BLOCK_SIZE + vag.data.len());
+
+        let parsed = Vag::parse(&file).unwrap();
+        assert_eq!(parsed, vag);
+        assert!(parsed.is_looping());
+        assert_eq!(Vag::parse(&file[..20]), Err(Error::InvalidHeader));
+    }
+}
@@ -0,0 +1,429 @@
+//! `icon.sys`, the file describing a save to the memory card browser, and the directory
+//! holding a save.
+
+use alloc::borrow::ToOwned;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// Maximum length of a file name on a memory card, terminator included.
+pub const NAME_MAX: usize = 32;
+
+/// Maximum length of the encoded title, terminator included.
+const TITLE_MAX: usize = 68;
+
+/// Maximum length of an icon file name, terminator included.
+const ICON_NAME_MAX: usize = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IconSysError {
+    /// The encoded title does not fit in 68 bytes, roughly 33 characters.
+    TitleTooLong,
+    /// The title contains a character without a full-width Shift-JIS encoding.
+    UnsupportedChar(char),
+    /// A file name is empty, too long, or not ASCII.
+    InvalidName,
+    /// The save has no icon.
+    MissingIcon,
+}
+
+/// Contents of an `icon.sys` file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IconSys {
+    /// First line of the title.
+    pub title: String,
+    /// Second line of the title, may be empty.
+    pub subtitle: String,
+    /// Opacity of the background, from `0` to `0x80`.
+    pub transparency: u32,
+    /// Colours of the background corners: top left, top right, bottom left, bottom right.
+    pub background: [[u8; 3]; 4],
+    /// Directions of the three lights shining on the icon.
+    pub light_dirs: [[f32; 3]; 3],
+    /// Colours of the three lights, from `0.0` to `1.0`.
+    pub light_colors: [[f32; 3]; 3],
+    pub ambient: [f32; 3],
+    /// Icon shown in the save list.
+    pub list_icon: String,
+    /// Icon shown while copying the save.
+    pub copy_icon: String,
+    /// Icon shown while deleting the save.
+    pub delete_icon: String,
+}
+
+impl Default for IconSys {
+    fn default() -> Self {
+        Self {
+            title: String::new(),
+            subtitle: String::new(),
+            transparency: 0,
+            background: [[0, 0, 0x40], [0, 0, 0x40], [0, 0, 0x20], [0, 0, 0x20]],
+            light_dirs: [[0.5, 0.5, 0.5], [0.0, -0.4, -0.1], [-0.5, -0.5, 0.5]],
+            light_colors: [[0.48, 0.48, 0.48], [0.5, 0.33, 0.2], [0.3, 0.3, 0.3]],
+            ambient: [0.5, 0.5, 0.5],
+            list_icon: "icon.ico".into(),
+            copy_icon: "icon.ico".into(),
+            delete_icon: "icon.ico".into(),
+        }
+    }
+}
+
+impl IconSys {
+    /// Size of the encoded file.
+    pub const SIZE: usize = 964;
+
+    const MAGIC: &'static [u8; 4] = b"PS2D";
+
+    pub fn encode(&self) -> Result<Vec<u8>, IconSysError> {
+        let mut buf = vec![0; Self::SIZE];
+
+        let mut title = to_shift_jis(&self.title)?;
+        let line_break = title.len();
+        title.extend(to_shift_jis(&self.subtitle)?);
+        // Leave room for the terminator
+        if title.len() >= TITLE_MAX {
+            return Err(IconSysError::TitleTooLong);
+        }
+
+        buf[0..4].copy_from_slice(Self::MAGIC);
+        buf[6..8].copy_from_slice(&(line_break as u16).to_le_bytes());
+        buf[12..16].copy_from_slice(&self.transparency.to_le_bytes());
+
+        let mut offset = 0x10;
+        let mut put = |word: [u8; 4]| {
+            buf[offset..offset + 4].copy_from_slice(&word);
+            offset += 4;
+        };
+        for [r, g, b] in self.background {
+            for channel in [r, g, b, 0] {
+                put((channel as u32).to_le_bytes());
+            }
+        }
+        let vectors = self.light_dirs.iter().chain(&self.light_colors);
+        for &[x, y, z] in vectors.chain([&self.ambient]) {
+            for component in [x, y, z, 0.0] {
+                put(component.to_le_bytes());
+            }
+        }
+
+        buf[0xc0..0xc0 + title.len()].copy_from_slice(&title);
+        for (offset, name) in [
+            (0x104, &self.list_icon),
+            (0x144, &self.copy_icon),
+            (0x184, &self.delete_icon),
+        ] {
+            check_name(name, ICON_NAME_MAX)?;
+            buf[offset..offset + name.len()].copy_from_slice(name.as_bytes());
+        }
+
+        Ok(buf)
+    }
+}
+
+fn check_name(name: &str, max: usize) -> Result<(), IconSysError> {
+    // Leave room for the terminator
+    if name.is_empty() || name.len() >= max || !name.is_ascii() || name.contains('/') {
+        return Err(IconSysError::InvalidName);
+    }
+    Ok(())
+}
+
+/// Full-width Shift-JIS encoding of the ASCII punctuation, starting from `!`.
+const PUNCTUATION: [u16; 15] = [
+    0x8149, 0x8168, 0x8194, 0x8190, 0x8193, 0x8195, 0x8166, 0x8169, 0x816a, 0x8196, 0x817b, 0x8143,
+    0x817c, 0x8144, 0x815e,
+];
+
+/// Full-width Shift-JIS encoding of a character.
+fn shift_jis_char(c: char) -> Option<u16> {
+    // Full-width forms map to ASCII
+    let c = match c {
+        '\u{ff01}'..='\u{ff5e}' => char::from_u32(c as u32 - 0xfee0)?,
+        '\u{3000}' => ' ',
+        c => c,
+    };
+
+    let code = match c {
+        ' ' => 0x8140,
+        '!'..='/' => PUNCTUATION[(c as u8 - b'!') as usize],
+        '0'..='9' => 0x824f + (c as u16 - '0' as u16),
+        ':' => 0x8146,
+        ';' => 0x8147,
+        '<' => 0x8183,
+        '=' => 0x8181,
+        '>' => 0x8184,
+        '?' => 0x8148,
+        '@' => 0x8197,
+        'A'..='Z' => 0x8260 + (c as u16 - 'A' as u16),
+        '[' => 0x816d,
+        '\\' => 0x815f,
+        ']' => 0x816e,
+        '^' => 0x814f,
+        '_' => 0x8151,
+        '`' => 0x814d,
+        'a'..='z' => 0x8281 + (c as u16 - 'a' as u16),
+        '{' => 0x816f,
+        '|' => 0x8162,
+        '}' => 0x8170,
+        '~' => 0x8160,
+        '、' => 0x8141,
+        '。' => 0x8142,
+        '・' => 0x8145,
+        'ー' => 0x815b,
+        'ぁ'..='ん' => 0x829f + (c as u32 - 'ぁ' as u32) as u16,
+        // Katakana skip the 0x7f trail byte
+        'ァ'..='ミ' => 0x8340 + (c as u32 - 'ァ' as u32) as u16,
+        'ム'..='ヶ' => 0x8380 + (c as u32 - 'ム' as u32) as u16,
+        _ => return None,
+    };
+    Some(code)
+}
+
+/// Encode `s` in Shift-JIS as the browser expects it, with full-width characters only.
+///
+/// ASCII is widened, kana and common Japanese punctuation are supported, kanji are not.
+pub fn to_shift_jis(s: &str) -> Result<Vec<u8>, IconSysError> {
+    let mut buf = Vec::with_capacity(s.len() * 2);
+    for c in s.chars() {
+        let code = shift_jis_char(c).ok_or(IconSysError::UnsupportedChar(c))?;
+        buf.extend_from_slice(&code.to_be_bytes());
+    }
+    Ok(buf)
+}
+
+/// Builds a save directory: `icon.sys`, its icons and the data files.
+///
+/// ```ignore
+/// let save = SaveDirBuilder::new("BESLES-12345GAME")
+///     .title("My Game", "Slot 1")
+///     .icon(include_bytes!("icon.ico").to_vec())
+///     .file("data.bin", data)
+///     .build()?;
+/// memcard::write_save(0, 0, &save)?;
+/// ```
+#[derive(Debug, Clone)]
+pub struct SaveDirBuilder {
+    name: String,
+    icon_sys: IconSys,
+    icons: Vec<(String, Vec<u8>)>,
+    files: Vec<(String, Vec<u8>)>,
+}
+
+impl SaveDirBuilder {
+    /// Start a save stored in the directory `name`, usually the product code and a suffix.
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.into(),
+            icon_sys: IconSys::default(),
+            icons: Vec::new(),
+            files: Vec::new(),
+        }
+    }
+
+    /// Title shown by the browser, on two lines.
+    pub fn title(mut self, title: &str, subtitle: &str) -> Self {
+        self.icon_sys.title = title.into();
+        self.icon_sys.subtitle = subtitle.into();
+        self
+    }
+
+    /// Use the same icon for the list, copy and delete animations.
+    pub fn icon(mut self, icon: Vec<u8>) -> Self {
+        self.icons = vec![("icon.ico".into(), icon)];
+        self.icon_sys.list_icon = "icon.ico".into();
+        self.icon_sys.copy_icon = "icon.ico".into();
+        self.icon_sys.delete_icon = "icon.ico".into();
+        self
+    }
+
+    /// Use a different icon for the list, copy and delete animations.
+    pub fn icons(mut self, list: Vec<u8>, copy: Vec<u8>, delete: Vec<u8>) -> Self {
+        self.icons = vec![
+            ("list.ico".into(), list),
+            ("copy.ico".into(), copy),
+            ("del.ico".into(), delete),
+        ];
+        self.icon_sys.list_icon = "list.ico".into();
+        self.icon_sys.copy_icon = "copy.ico".into();
+        self.icon_sys.delete_icon = "del.ico".into();
+        self
+    }
+
+    /// Colours of the background corners: top left, top right, bottom left, bottom right.
+    pub fn background(mut self, corners: [[u8; 3]; 4]) -> Self {
+        self.icon_sys.background = corners;
+        self
+    }
+
+    /// Opacity of the background, from `0` to `0x80`.
+    pub fn transparency(mut self, transparency: u32) -> Self {
+        self.icon_sys.transparency = transparency;
+        self
+    }
+
+    /// Set light `index` (`0` to `2`) shining on the icon.
+    pub fn light(mut self, index: usize, direction: [f32; 3], color: [f32; 3]) -> Self {
+        self.icon_sys.light_dirs[index] = direction;
+        self.icon_sys.light_colors[index] = color;
+        self
+    }
+
+    pub fn ambient(mut self, color: [f32; 3]) -> Self {
+        self.icon_sys.ambient = color;
+        self
+    }
+
+    /// Add a data file to the save.
+    pub fn file(mut self, name: &str, data: Vec<u8>) -> Self {
+        self.files.push((name.into(), data));
+        self
+    }
+
+    pub fn build(self) -> Result<SaveDir, IconSysError> {
+        check_name(&self.name, NAME_MAX)?;
+        if self.icons.is_empty() {
+            return Err(IconSysError::MissingIcon);
+        }
+
+        let mut files = vec![("icon.sys".to_owned(), self.icon_sys.encode()?)];
+        files.extend(self.icons);
+        for (name, data) in self.files {
+            check_name(&name, NAME_MAX)?;
+            files.push((name, data));
+        }
+
+        Ok(SaveDir {
+            name: self.name,
+            files,
+        })
+    }
+}
+
+/// A save directory, as produced by [`SaveDirBuilder`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SaveDir {
+    name: String,
+    files: Vec<(String, Vec<u8>)>,
+}
+
+impl SaveDir {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Name and contents of every file, starting with `icon.sys`.
+    pub fn files(&self) -> &[(String, Vec<u8>)] {
+        &self.files
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shift_jis() {
+        assert_eq!(
+            to_shift_jis("Az0 ").unwrap(),
+            [0x82, 0x60, 0x82, 0x9a, 0x82, 0x4f, 0x81, 0x40]
+        );
+        assert_eq!(to_shift_jis("!~").unwrap(), [0x81, 0x49, 0x81, 0x60]);
+        assert_eq!(
+            to_shift_jis("あアムー").unwrap(),
+            [0x82, 0xa0, 0x83, 0x41, 0x83, 0x80, 0x81, 0x5b]
+        );
+        // Full-width forms encode like their ASCII counterpart
+        assert_eq!(to_shift_jis("Ａ").unwrap(), to_shift_jis("A").unwrap());
+        assert_eq!(to_shift_jis("漢"), Err(IconSysError::UnsupportedChar('漢')));
+    }
+
+    #[test]
+    fn test_icon_sys_layout() {
+        let icon_sys = IconSys {
+            title: "Game".into(),
+            subtitle: "Save".into(),
+            transparency: 0x40,
+            background: [[0x80, 0, 0], [0, 0x80, 0], [0, 0, 0x80], [1, 2, 3]],
+            ..Default::default()
+        };
+        let raw = icon_sys.encode().unwrap();
+        assert_eq!(raw.len(), IconSys::SIZE);
+
+        let word = |offset: usize| u32::from_le_bytes(raw[offset..offset + 4].try_into().unwrap());
+        let float = |offset: usize| f32::from_le_bytes(raw[offset..offset + 4].try_into().unwrap());
+        assert_eq!(&raw[0..4], b"PS2D");
+        assert_eq!(&raw[4..6], &[0, 0]);
+        // The second line starts after the 4 characters of the first one
+        assert_eq!(u16::from_le_bytes([raw[6], raw[7]]), 8);
+        assert_eq!(&raw[8..12], &[0; 4]);
+        assert_eq!(word(0x0c), 0x40);
+
+        // Background corners, as RGBA words
+        assert_eq!(
+            (0x10..0x50).step_by(4).map(word).collect::<Vec<_>>(),
+            [0x80, 0, 0, 0, 0, 0x80, 0, 0, 0, 0, 0x80, 0, 1, 2, 3, 0]
+        );
+        // Light directions, light colours then ambient, as XYZW floats
+        assert_eq!(
+            [float(0x50), float(0x54), float(0x58), float(0x5c)],
+            [0.5, 0.5, 0.5, 0.0]
+        );
+        assert_eq!([float(0x80), float(0x84), float(0x88)], [0.48, 0.48, 0.48]);
+        assert_eq!(
+            [float(0xb0), float(0xb4), float(0xb8), float(0xbc)],
+            [0.5, 0.5, 0.5, 0.0]
+        );
+
+        assert_eq!(
+            &raw[0xc0..0xd1],
+            &[
+                0x82, 0x66, 0x82, 0x81, 0x82, 0x8d, 0x82, 0x85, 0x82, 0x72, 0x82, 0x81, 0x82, 0x96,
+                0x82, 0x85, 0
+            ]
+        );
+        assert_eq!(&raw[0x104..0x10d], b"icon.ico\0");
+        assert_eq!(&raw[0x144..0x14d], b"icon.ico\0");
+        assert_eq!(&raw[0x184..0x18d], b"icon.ico\0");
+        assert!(raw[0x1c4..].iter().all(|&byte| byte == 0));
+
+        let long = IconSys {
+            title: "abcdefghijklmnopqrstuvwxyz".into(),
+            subtitle: "0123456789".into(),
+            ..Default::default()
+        };
+        assert_eq!(long.encode(), Err(IconSysError::TitleTooLong));
+    }
+
+    #[test]
+    fn test_save_dir_builder() {
+        let save = SaveDirBuilder::new("BESLES-12345GAME")
+            .title("My Game", "Slot 1")
+            .icons([1].to_vec(), [2].to_vec(), [3].to_vec())
+            .file("data.bin", [4; 100].to_vec())
+            .build()
+            .unwrap();
+
+        assert_eq!(save.name(), "BESLES-12345GAME");
+        let names: Vec<&str> = save.files().iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(
+            names,
+            ["icon.sys", "list.ico", "copy.ico", "del.ico", "data.bin"]
+        );
+        assert_eq!(&save.files()[0].1[0x144..0x14c], b"copy.ico");
+        assert_eq!(save.files()[2].1, [2]);
+
+        let err = SaveDirBuilder::new("GAME").title("Game", "").build();
+        assert_eq!(err.unwrap_err(), IconSysError::MissingIcon);
+
+        let err = SaveDirBuilder::new("GAME")
+            .icon([0].to_vec())
+            .file("a/b", Vec::new())
+            .build();
+        assert_eq!(err.unwrap_err(), IconSysError::InvalidName);
+
+        let err = SaveDirBuilder::new(&"A".repeat(NAME_MAX))
+            .icon([0].to_vec())
+            .build();
+        assert_eq!(err.unwrap_err(), IconSysError::InvalidName);
+    }
+}
@@ -0,0 +1,485 @@
+//! ISO9660 filesystem reader, independent of the drive.
+//!
+//! Works on anything implementing [`SectorRead`], the drive through `rps2_sif::cdvd::Disc` or
+//! a disc image in memory. Only the primary volume descriptor is used, Joliet and Rock Ridge names
+//! are ignored.
+
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// Size of a logical sector.
+pub const SECTOR_SIZE: usize = 2048;
+
+/// First sector of the volume descriptors, after the system area.
+const DESCRIPTORS_LBN: u32 = 16;
+
+const STANDARD_ID: &[u8; 5] = b"CD001";
+
+mod descriptor {
+    pub const PRIMARY: u8 = 1;
+    pub const TERMINATOR: u8 = 255;
+}
+
+/// Source of sectors, e.g. a disc or an image.
+pub trait SectorRead {
+    type Error;
+
+    /// Read consecutive sectors starting at `lbn`, `buf` has a multiple of [`SECTOR_SIZE`] bytes.
+    fn read_sectors(&mut self, lbn: u32, buf: &mut [u8]) -> core::result::Result<(), Self::Error>;
+}
+
+impl<R: SectorRead + ?Sized> SectorRead for &mut R {
+    type Error = R::Error;
+
+    fn read_sectors(&mut self, lbn: u32, buf: &mut [u8]) -> core::result::Result<(), Self::Error> {
+        (**self).read_sectors(lbn, buf)
+    }
+}
+
+/// A disc image, sectors past its end fail with [`OutOfBounds`].
+impl SectorRead for &[u8] {
+    type Error = OutOfBounds;
+
+    fn read_sectors(&mut self, lbn: u32, buf: &mut [u8]) -> core::result::Result<(), Self::Error> {
+        let start = lbn as usize * SECTOR_SIZE;
+        let src = self.get(start..start + buf.len()).ok_or(OutOfBounds(lbn))?;
+        buf.copy_from_slice(src);
+        Ok(())
+    }
+}
+
+/// A sector past the end of an image was read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OutOfBounds(pub u32);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error<E> {
+    /// Reading a sector failed.
+    Read(E),
+    /// There is no primary volume descriptor.
+    NotIso9660,
+    /// A directory record is truncated or inconsistent.
+    Corrupted,
+    /// A component of the path does not exist.
+    NotFound,
+    /// A component of the path is not a directory.
+    NotADirectory,
+    /// A directory was used as a file.
+    IsADirectory,
+}
+
+pub type Result<T, E> = core::result::Result<T, Error<E>>;
+
+/// Date of a directory record, in the local time of the disc author.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Date {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+    /// Offset from GMT in 15 minutes intervals.
+    pub gmt_offset: i8,
+}
+
+impl Date {
+    fn decode(data: &[u8; 7]) -> Self {
+        Self {
+            year: 1900 + data[0] as u16,
+            month: data[1],
+            day: data[2],
+            hour: data[3],
+            minute: data[4],
+            second: data[5],
+            gmt_offset: data[6] as i8,
+        }
+    }
+}
+
+mod flags {
+    pub const HIDDEN: u8 = 0x01;
+    pub const DIRECTORY: u8 = 0x02;
+}
+
+/// A file or directory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DirEntry {
+    /// Name without the `;1` version suffix, empty for the root.
+    pub name: String,
+    /// First sector of the data.
+    pub lbn: u32,
+    pub size: u32,
+    pub date: Date,
+    pub flags: u8,
+}
+
+impl DirEntry {
+    /// Size of a record without its name.
+    const HEADER_SIZE: usize = 33;
+
+    /// Decode the record at the start of `data`, along with its length.
+    ///
+    /// Returns `Ok(None)` for the padding at the end of a sector.
+    fn decode(data: &[u8]) -> core::result::Result<Option<(Self, usize)>, ()> {
+        let len = match data.first() {
+            None | Some(0) => return Ok(None),
+            Some(&len) => len as usize,
+        };
+        if len < Self::HEADER_SIZE || len > data.len() {
+            return Err(());
+        }
+
+        let name_len = data[32] as usize;
+        let name = data.get(33..33 + name_len).filter(|_| 33 + name_len <= len);
+        let name = match name.ok_or(())? {
+            // The directory itself and its parent
+            [0] => ".".into(),
+            [1] => "..".into(),
+            name => decode_name(name),
+        };
+
+        let word = |offset: usize| u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap());
+        let entry = Self {
+            name,
+            lbn: word(2),
+            size: word(10),
+            date: Date::decode(data[18..25].try_into().unwrap()),
+            flags: data[25],
+        };
+        Ok(Some((entry, len)))
+    }
+
+    pub fn is_dir(&self) -> bool {
+        self.flags & flags::DIRECTORY != 0
+    }
+
+    pub fn is_file(&self) -> bool {
+        !self.is_dir()
+    }
+
+    pub fn is_hidden(&self) -> bool {
+        self.flags & flags::HIDDEN != 0
+    }
+
+    /// Number of sectors spanned by the data.
+    pub fn sectors(&self) -> u32 {
+        self.size.div_ceil(SECTOR_SIZE as u32)
+    }
+}
+
+/// Strip the version and the trailing dot of names without extension, `FILE.;1` is `FILE`.
+fn decode_name(name: &[u8]) -> String {
+    let name = match name.iter().rposition(|&b| b == b';') {
+        Some(pos) => &name[..pos],
+        None => name,
+    };
+    let name = name.strip_suffix(b".").unwrap_or(name);
+    name.iter().map(|&b| b as char).collect()
+}
+
+/// A mounted ISO9660 volume.
+///
+/// ```ignore
+/// let mut fs = Iso9660::new(Disc::default())?;
+/// let entry = fs.lookup("/DATA/LEVEL1.BIN")?;
+/// let data = fs.read_file(&entry)?;
+/// ```
+#[derive(Debug)]
+pub struct Iso9660<R> {
+    reader: R,
+    volume_id: String,
+    volume_sectors: u32,
+    root: DirEntry,
+}
+
+impl<R: SectorRead> Iso9660<R> {
+    /// Read the volume descriptors of `reader`.
+    pub fn new(mut reader: R) -> Result<Self, R::Error> {
+        let mut sector = vec![0; SECTOR_SIZE];
+
+        let mut lbn = DESCRIPTORS_LBN;
+        loop {
+            reader.read_sectors(lbn, &mut sector).map_err(Error::Read)?;
+            if &sector[1..6] != STANDARD_ID {
+                return Err(Error::NotIso9660);
+            }
+
+            match sector[0] {
+                descriptor::PRIMARY => {}
+                descriptor::TERMINATOR => return Err(Error::NotIso9660),
+                // Boot records and supplementary descriptors
+                _ => {
+                    lbn += 1;
+                    continue;
+                }
+            }
+
+            let volume_id = sector[40..72]
+                .iter()
+                .map(|&b| b as char)
+                .collect::<String>();
+            let volume_sectors = u32::from_le_bytes(sector[80..84].try_into().unwrap());
+            // Both endian, the little endian half comes first
+            let block_size = u16::from_le_bytes(sector[128..130].try_into().unwrap());
+            if block_size as usize != SECTOR_SIZE {
+                return Err(Error::NotIso9660);
+            }
+
+            let (mut root, _) = DirEntry::decode(&sector[156..190])
+                .ok()
+                .flatten()
+                .ok_or(Error::Corrupted)?;
+            root.name = String::new();
+
+            return Ok(Self {
+                reader,
+                volume_id: volume_id.trim_end().into(),
+                volume_sectors,
+                root,
+            });
+        }
+    }
+
+    pub fn volume_id(&self) -> &str {
+        &self.volume_id
+    }
+
+    /// Size of the volume in sectors.
+    pub fn volume_sectors(&self) -> u32 {
+        self.volume_sectors
+    }
+
+    pub fn root(&self) -> &DirEntry {
+        &self.root
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    /// Entries of `dir`, without `.` and `..`.
+    pub fn read_dir(&mut self, dir: &DirEntry) -> Result<Vec<DirEntry>, R::Error> {
+        if !dir.is_dir() {
+            return Err(Error::NotADirectory);
+        }
+
+        let mut data = vec![0; dir.sectors() as usize * SECTOR_SIZE];
+        self.reader
+            .read_sectors(dir.lbn, &mut data)
+            .map_err(Error::Read)?;
+        data.truncate(dir.size as usize);
+
+        let mut entries = Vec::new();
+        // Records never cross a sector boundary
+        for sector in data.chunks(SECTOR_SIZE) {
+            let mut offset = 0;
+            while let Some((entry, len)) =
+                DirEntry::decode(&sector[offset..]).map_err(|()| Error::Corrupted)?
+            {
+                offset += len;
+                if entry.name != "." && entry.name != ".." {
+                    entries.push(entry);
+                }
+            }
+        }
+        Ok(entries)
+    }
+
+    /// Find the entry at `path`, separated by `/` and relative to the root.
+    ///
+    /// Names are compared ignoring ASCII case, and the `;1` version can be omitted.
+    pub fn lookup(&mut self, path: &str) -> Result<DirEntry, R::Error> {
+        let mut entry = self.root.clone();
+
+        for name in path.split('/').filter(|name| !name.is_empty()) {
+            let name = decode_name(name.as_bytes());
+            entry = self
+                .read_dir(&entry)?
+                .into_iter()
+                .find(|entry| entry.name.eq_ignore_ascii_case(&name))
+                .ok_or(Error::NotFound)?;
+        }
+        Ok(entry)
+    }
+
+    /// Read from `file` at `offset`, returns the number of bytes read.
+    pub fn read_at(
+        &mut self,
+        file: &DirEntry,
+        offset: u32,
+        buf: &mut [u8],
+    ) -> Result<usize, R::Error> {
+        if file.is_dir() {
+            return Err(Error::IsADirectory);
+        }
+
+        let len = buf.len().min(file.size.saturating_sub(offset) as usize);
+        let buf = &mut buf[..len];
+        let start = offset as usize;
+        let end = start + len;
+
+        let mut sector = [0; SECTOR_SIZE];
+        let mut pos = start;
+        while pos < end {
+            let lbn = file.lbn + (pos / SECTOR_SIZE) as u32;
+            let skip = pos % SECTOR_SIZE;
+            let dest = &mut buf[pos - start..];
+
+            if skip == 0 && dest.len() >= SECTOR_SIZE {
+                // Read whole sectors directly
+                let whole = dest.len() / SECTOR_SIZE * SECTOR_SIZE;
+                self.reader
+                    .read_sectors(lbn, &mut dest[..whole])
+                    .map_err(Error::Read)?;
+                pos += whole;
+            } else {
+                self.reader
+                    .read_sectors(lbn, &mut sector)
+                    .map_err(Error::Read)?;
+                let chunk = dest.len().min(SECTOR_SIZE - skip);
+                dest[..chunk].copy_from_slice(&sector[skip..skip + chunk]);
+                pos += chunk;
+            }
+        }
+        Ok(len)
+    }
+
+    /// Read the whole `file`.
+    pub fn read_file(&mut self, file: &DirEntry) -> Result<Vec<u8>, R::Error> {
+        let mut data = vec![0; file.size as usize];
+        self.read_at(file, 0, &mut data)?;
+        Ok(data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ROOT_LBN: u32 = 18;
+    const DATA_LBN: u32 = 19;
+    const FILE_LBN: u32 = 20;
+    const FILE_SIZE: u32 = 3000;
+
+    fn record(buf: &mut Vec<u8>, name: &[u8], lbn: u32, size: u32, flags: u8) {
+        let len = (33 + name.len()).next_multiple_of(2);
+        let start = buf.len();
+        buf.resize(start + len, 0);
+
+        let record = &mut buf[start..];
+        record[0] = len as u8;
+        record[2..6].copy_from_slice(&lbn.to_le_bytes());
+        record[10..14].copy_from_slice(&size.to_le_bytes());
+        record[18..25].copy_from_slice(&[102, 10, 18, 12, 30, 0, 0]);
+        record[25] = flags;
+        record[32] = name.len() as u8;
+        record[33..33 + name.len()].copy_from_slice(name);
+    }
+
+    /// A volume with `/README.TXT;1` and `/DATA/LEVEL.BIN;1`.
+    fn image() -> Vec<u8> {
+        let len = (FILE_LBN + 3) as usize * SECTOR_SIZE;
+        let mut image = vec![0; len];
+        let sector = |lbn: u32| lbn as usize * SECTOR_SIZE;
+
+        let pvd = &mut image[sector(16)..];
+        pvd[0] = 1;
+        pvd[1..7].copy_from_slice(b"CD001\x01");
+        pvd[40..72].copy_from_slice(b"TEST                            ");
+        pvd[80..84].copy_from_slice(&(FILE_LBN + 3).to_le_bytes());
+        pvd[128..130].copy_from_slice(&2048u16.to_le_bytes());
+        let mut root = Vec::new();
+        record(&mut root, &[0], ROOT_LBN, SECTOR_SIZE as u32, 2);
+        pvd[156..156 + root.len()].copy_from_slice(&root);
+
+        let terminator = &mut image[sector(17)..];
+        terminator[0] = 255;
+        terminator[1..7].copy_from_slice(b"CD001\x01");
+
+        let mut dir = Vec::new();
+        record(&mut dir, &[0], ROOT_LBN, SECTOR_SIZE as u32, 2);
+        record(&mut dir, &[1], ROOT_LBN, SECTOR_SIZE as u32, 2);
+        record(&mut dir, b"DATA", DATA_LBN, SECTOR_SIZE as u32, 2);
+        record(&mut dir, b"README.TXT;1", FILE_LBN + 2, 5, 0);
+        image[sector(ROOT_LBN)..][..dir.len()].copy_from_slice(&dir);
+
+        let mut dir = Vec::new();
+        record(&mut dir, &[0], DATA_LBN, SECTOR_SIZE as u32, 2);
+        record(&mut dir, &[1], ROOT_LBN, SECTOR_SIZE as u32, 2);
+        record(&mut dir, b"LEVEL.BIN;1", FILE_LBN, FILE_SIZE, 0);
+        image[sector(DATA_LBN)..][..dir.len()].copy_from_slice(&dir);
+
+        for (i, byte) in image[sector(FILE_LBN)..][..FILE_SIZE as usize]
+            .iter_mut()
+            .enumerate()
+        {
+            *byte = i as u8;
+        }
+        image[sector(FILE_LBN + 2)..][..5].copy_from_slice(b"hello");
+        image
+    }
+
+    #[test]
+    fn test_iso9660_lookup() {
+        let image = image();
+        let mut fs = Iso9660::new(image.as_slice()).unwrap();
+        assert_eq!(fs.volume_id(), "TEST");
+        assert_eq!(fs.volume_sectors(), FILE_LBN + 3);
+
+        let names: Vec<String> = fs
+            .read_dir(&fs.root().clone())
+            .unwrap()
+            .into_iter()
+            .map(|entry| entry.name)
+            .collect();
+        assert_eq!(names, ["DATA", "README.TXT"]);
+
+        let entry = fs.lookup("/data/level.bin").unwrap();
+        assert_eq!(entry.lbn, FILE_LBN);
+        assert_eq!(entry.size, FILE_SIZE);
+        assert_eq!(entry.date.year, 2002);
+        assert!(entry.is_file());
+        assert_eq!(fs.lookup("DATA/LEVEL.BIN;1").unwrap(), entry);
+
+        assert!(fs.lookup("/DATA").unwrap().is_dir());
+        assert_eq!(fs.lookup("/DATA/MISSING"), Err(Error::NotFound));
+        assert_eq!(fs.lookup("/README.TXT/FILE"), Err(Error::NotADirectory));
+    }
+
+    #[test]
+    fn test_iso9660_read() {
+        let image = image();
+        let mut fs = Iso9660::new(image.as_slice()).unwrap();
+
+        let readme = fs.lookup("README.TXT").unwrap();
+        assert_eq!(fs.read_file(&readme).unwrap(), b"hello");
+
+        let level = fs.lookup("DATA/LEVEL.BIN").unwrap();
+        let data = fs.read_file(&level).unwrap();
+        assert_eq!(data.len(), FILE_SIZE as usize);
+        assert!(data.iter().enumerate().all(|(i, &b)| b == i as u8));
+
+        // Across a sector boundary, and clamped at the end of the file
+        let mut buf = [0; 8];
+        assert_eq!(fs.read_at(&level, 2044, &mut buf).unwrap(), 8);
+        assert_eq!(buf, [252, 253, 254, 255, 0, 1, 2, 3]);
+        assert_eq!(fs.read_at(&level, FILE_SIZE - 2, &mut buf).unwrap(), 2);
+        assert_eq!(fs.read_at(&level, FILE_SIZE, &mut buf).unwrap(), 0);
+
+        let data_dir = fs.lookup("DATA").unwrap();
+        assert_eq!(fs.read_file(&data_dir), Err(Error::IsADirectory));
+    }
+
+    #[test]
+    fn test_iso9660_invalid() {
+        let mut image = image();
+        image[16 * SECTOR_SIZE + 1] = b'X';
+        let err = Iso9660::new(image.as_slice()).unwrap_err();
+        assert_eq!(err, Error::NotIso9660);
+
+        let err = Iso9660::new(&image[..SECTOR_SIZE]).unwrap_err();
+        assert_eq!(err, Error::Read(OutOfBounds(16)));
+    }
+}
@@ -0,0 +1,14 @@
+//! File formats of the PS2 software ecosystem.
+//!
+//! This crate only encodes and decodes data and does not touch the hardware, the drivers
+//! reading and writing it live in the other crates.
+
+#![no_std]
+#![deny(missing_debug_implementations)]
+extern crate alloc;
+
+pub mod adpcm;
+pub mod icon;
+pub mod iso9660;
+pub mod mcfs;
+pub mod wav;
@@ -0,0 +1,117 @@
+//! Directory entries of the memory card filesystem, as listed by mcserv.
+
+use alloc::string::String;
+
+use crate::icon::NAME_MAX;
+
+/// Timestamp of a file, in the local time of the device.
+///
+/// ioman reports the timestamps of every device in this same encoding.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Time {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+}
+
+impl Time {
+    /// Decode the 8 byte encoding, the first byte is unused.
+    pub fn decode(raw: &[u8]) -> Self {
+        Self {
+            second: raw[1],
+            minute: raw[2],
+            hour: raw[3],
+            day: raw[4],
+            month: raw[5],
+            year: u16::from_le_bytes([raw[6], raw[7]]),
+        }
+    }
+}
+
+/// Attribute bits of [`DirEntry::attr`].
+pub mod attr {
+    pub const READABLE: u16 = 0x0001;
+    pub const WRITABLE: u16 = 0x0002;
+    pub const EXECUTABLE: u16 = 0x0004;
+    pub const PROTECTED: u16 = 0x0008;
+    pub const FILE: u16 = 0x0010;
+    pub const SUBDIR: u16 = 0x0020;
+    pub const CLOSED: u16 = 0x0080;
+    pub const PDA_EXEC: u16 = 0x0800;
+    pub const PSX: u16 = 0x1000;
+    pub const HIDDEN: u16 = 0x2000;
+}
+
+/// An entry of a directory listing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DirEntry {
+    pub created: Time,
+    pub modified: Time,
+    /// Size in bytes for files, number of entries for directories.
+    pub size: u32,
+    /// Combination of [`attr`] bits.
+    pub attr: u16,
+    pub name: String,
+}
+
+impl DirEntry {
+    /// Size of the encoded structure.
+    pub const SIZE: usize = 64;
+
+    pub fn decode(raw: &[u8]) -> Self {
+        let name = &raw[32..32 + NAME_MAX];
+        let len = name.iter().position(|&c| c == 0).unwrap_or(name.len());
+        Self {
+            created: Time::decode(&raw[0..8]),
+            modified: Time::decode(&raw[8..16]),
+            size: u32::from_le_bytes(raw[16..20].try_into().unwrap()),
+            attr: u16::from_le_bytes(raw[20..22].try_into().unwrap()),
+            name: String::from_utf8_lossy(&name[..len]).into_owned(),
+        }
+    }
+
+    pub fn is_dir(&self) -> bool {
+        self.attr & attr::SUBDIR != 0
+    }
+
+    pub fn is_file(&self) -> bool {
+        self.attr & attr::FILE != 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dir_entry_decoding() {
+        let mut raw = [0; DirEntry::SIZE];
+        raw[8..16].copy_from_slice(&[0, 30, 20, 10, 24, 12, 0xd2, 0x07]);
+        raw[16..20].copy_from_slice(&964u32.to_le_bytes());
+        raw[20..22].copy_from_slice(&(attr::FILE | attr::READABLE).to_le_bytes());
+        raw[32..40].copy_from_slice(b"icon.sys");
+
+        let entry = DirEntry::decode(&raw);
+        assert_eq!(entry.name, "icon.sys");
+        assert_eq!(entry.size, 964);
+        assert_eq!(entry.modified.year, 2002);
+        assert_eq!(entry.modified.month, 12);
+        assert_eq!(entry.modified.hour, 10);
+        assert!(entry.is_file() && !entry.is_dir());
+    }
+
+    #[test]
+    fn test_dir_entry_long_name() {
+        // Names filling the whole field have no terminator
+        let mut raw = [0; DirEntry::SIZE];
+        raw[32..64].fill(b'A');
+        raw[20..22].copy_from_slice(&attr::SUBDIR.to_le_bytes());
+
+        let entry = DirEntry::decode(&raw);
+        assert_eq!(entry.name.len(), NAME_MAX);
+        assert!(entry.is_dir());
+    }
+}
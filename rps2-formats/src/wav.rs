@@ -0,0 +1,207 @@
+//! WAV files, RIFF containers of PCM audio.
+//!
+//! Only 16 bit PCM is read, the format streamed by audsrv and encoded by
+//! [`adpcm`](crate::adpcm). Chunks other than `fmt ` and `data` are skipped.
+
+use alloc::vec::Vec;
+
+mod format {
+    pub const PCM: u16 = 0x0001;
+    /// The actual format is in the sub format of the extension.
+    pub const EXTENSIBLE: u16 = 0xfffe;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    /// The file is not a RIFF WAVE file, or misses the `fmt ` or `data` chunk.
+    InvalidHeader,
+    /// The samples are not 16 bit PCM.
+    UnsupportedFormat,
+    /// A chunk is cut short by the end of the file.
+    Truncated,
+}
+
+/// A WAV file holding 16 bit PCM.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Wav {
+    pub sample_rate: u32,
+    pub channels: u16,
+    /// Samples of every channel, interleaved.
+    pub samples: Vec<i16>,
+}
+
+impl Wav {
+    pub fn parse(file: &[u8]) -> Result<Self, Error> {
+        let header = file.get(..12).ok_or(Error::InvalidHeader)?;
+        if &header[0..4] != b"RIFF" || &header[8..12] != b"WAVE" {
+            return Err(Error::InvalidHeader);
+        }
+
+        let mut format = None;
+        let mut chunks = &file[12..];
+        while chunks.len() >= 8 {
+            let id = &chunks[0..4];
+            let size = u32::from_le_bytes(chunks[4..8].try_into().unwrap()) as usize;
+            let body = chunks[8..].get(..size).ok_or(Error::Truncated)?;
+
+            match id {
+                b"fmt " => format = Some(Self::parse_format(body)?),
+                b"data" => {
+                    let (sample_rate, channels) = format.ok_or(Error::InvalidHeader)?;
+                    let mut samples: Vec<i16> = body
+                        .chunks_exact(2)
+                        .map(|pair| i16::from_le_bytes([pair[0], pair[1]]))
+                        .collect();
+                    // Drop an incomplete last frame
+                    samples.truncate(samples.len() / channels as usize * channels as usize);
+
+                    return Ok(Self {
+                        sample_rate,
+                        channels,
+                        samples,
+                    });
+                }
+                _ => {}
+            }
+
+            // Chunks are padded to an even size
+            let next = (8 + size + size % 2).min(chunks.len());
+            chunks = &chunks[next..];
+        }
+        Err(Error::InvalidHeader)
+    }
+
+    /// Decode the `fmt ` chunk, returning the sample rate and the number of channels.
+    fn parse_format(body: &[u8]) -> Result<(u32, u16), Error> {
+        if body.len() < 16 {
+            return Err(Error::InvalidHeader);
+        }
+        let half = |offset: usize| u16::from_le_bytes([body[offset], body[offset + 1]]);
+
+        let tag = match half(0) {
+            // The sub format GUID starts with the format tag
+            format::EXTENSIBLE if body.len() >= 26 => half(24),
+            tag => tag,
+        };
+        let channels = half(2);
+        let sample_rate = u32::from_le_bytes(body[4..8].try_into().unwrap());
+        let bits = half(14);
+        if tag != format::PCM || bits != 16 || channels == 0 {
+            return Err(Error::UnsupportedFormat);
+        }
+        Ok((sample_rate, channels))
+    }
+
+    /// Number of samples of each channel.
+    pub fn frames(&self) -> usize {
+        self.samples.len() / self.channels as usize
+    }
+
+    /// Mix the channels down to mono, e.g. to encode them with [`adpcm::encode`](crate::adpcm::encode).
+    pub fn mono(&self) -> Vec<i16> {
+        self.samples
+            .chunks_exact(self.channels as usize)
+            .map(|frame| {
+                let sum = frame.iter().map(|&sample| sample as i32).sum::<i32>();
+                (sum / frame.len() as i32) as i16
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunk(file: &mut Vec<u8>, id: &[u8; 4], body: &[u8]) {
+        file.extend_from_slice(id);
+        file.extend_from_slice(&(body.len() as u32).to_le_bytes());
+        file.extend_from_slice(body);
+        if body.len() % 2 != 0 {
+            file.push(0);
+        }
+    }
+
+    fn fmt(tag: u16, channels: u16, sample_rate: u32, bits: u16) -> Vec<u8> {
+        let block_align = channels * bits / 8;
+        let mut body = Vec::new();
+        body.extend_from_slice(&tag.to_le_bytes());
+        body.extend_from_slice(&channels.to_le_bytes());
+        body.extend_from_slice(&sample_rate.to_le_bytes());
+        body.extend_from_slice(&(sample_rate * block_align as u32).to_le_bytes());
+        body.extend_from_slice(&block_align.to_le_bytes());
+        body.extend_from_slice(&bits.to_le_bytes());
+        body
+    }
+
+    fn wav(chunks: &[(&[u8; 4], &[u8])]) -> Vec<u8> {
+        let mut file = b"RIFF\0\0\0\0WAVE".to_vec();
+        for (id, body) in chunks {
+            chunk(&mut file, id, body);
+        }
+        let size = (file.len() - 8) as u32;
+        file[4..8].copy_from_slice(&size.to_le_bytes());
+        file
+    }
+
+    #[test]
+    fn test_wav_parse() {
+        let data = [
+            0x01, 0x00, 0xff, 0xff, 0x00, 0x80, 0xff, 0x7f, 0x64, 0x00, 0x2c, 0x01,
+        ];
+        let file = wav(&[
+            (b"fmt ", &fmt(format::PCM, 2, 22050, 16)),
+            (b"LIST", b"odd"),
+            (b"data", &data),
+        ]);
+
+        let wav = Wav::parse(&file).unwrap();
+        assert_eq!(wav.sample_rate, 22050);
+        assert_eq!(wav.channels, 2);
+        assert_eq!(wav.samples, [1, -1, i16::MIN, i16::MAX, 100, 300]);
+        assert_eq!(wav.frames(), 3);
+        assert_eq!(wav.mono(), [0, 0, 200]);
+    }
+
+    #[test]
+    fn test_wav_extensible() {
+        let mut format = fmt(format::EXTENSIBLE, 1, 48000, 16);
+        // Extension size, valid bits, channel mask, then the PCM sub format
+        format.extend_from_slice(&[22, 0, 16, 0, 4, 0, 0, 0, 1, 0]);
+        format.extend_from_slice(&[0; 14]);
+        let file = wav(&[(b"fmt ", &format), (b"data", &[0x34, 0x12, 0x00])]);
+
+        let wav = Wav::parse(&file).unwrap();
+        assert_eq!(wav.sample_rate, 48000);
+        assert_eq!(wav.samples, [0x1234]);
+        assert_eq!(wav.mono(), wav.samples);
+    }
+
+    #[test]
+    fn test_wav_invalid() {
+        assert_eq!(Wav::parse(b"RIFF"), Err(Error::InvalidHeader));
+        assert_eq!(
+            Wav::parse(&wav(&[(b"data", &[0; 4])])),
+            Err(Error::InvalidHeader)
+        );
+        assert_eq!(
+            Wav::parse(&wav(&[(b"fmt ", &fmt(format::PCM, 1, 8000, 16))])),
+            Err(Error::InvalidHeader)
+        );
+        assert_eq!(
+            Wav::parse(&wav(&[(b"fmt ", &fmt(format::PCM, 1, 8000, 8))])),
+            Err(Error::UnsupportedFormat)
+        );
+        assert_eq!(
+            Wav::parse(&wav(&[(b"fmt ", &fmt(3, 1, 8000, 16))])),
+            Err(Error::UnsupportedFormat)
+        );
+
+        let mut file = wav(&[
+            (b"fmt ", &fmt(format::PCM, 1, 8000, 16)),
+            (b"data", &[0; 8]),
+        ]);
+        file.truncate(file.len() - 2);
+        assert_eq!(Wav::parse(&file), Err(Error::Truncated));
+    }
+}
@@ -168,6 +168,39 @@ macro_rules! interrupt_disable_guard {
     };
 }
 
+/// Run `f` in kernel mode with interrupts disabled, needed to access the kernel segments.
+pub fn with_kernel_mode<R>(f: impl FnOnce() -> R) -> R {
+    let _guard = interrupt_disable_guard();
+
+    let status: u32;
+    unsafe {
+        asm!(
+            ".set noat",
+            "mfc0 {}, $12",
+            out(reg) status
+        );
+        // Clear KSU
+        asm!(
+            ".set noat",
+            "mtc0 {}, $12",
+            "sync 0x10",
+            in(reg) status & !0x18
+        );
+    }
+
+    let res = f();
+
+    unsafe {
+        asm!(
+            ".set noat",
+            "mtc0 {}, $12",
+            "sync 0x10",
+            in(reg) status
+        );
+    }
+    res
+}
+
 pub mod cop0 {
     use super::*;
 
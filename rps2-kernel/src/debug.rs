@@ -1,4 +1,5 @@
 use core::fmt::{self, Write};
+use core::sync::atomic::{AtomicPtr, Ordering};
 
 #[cfg(feature = "libtest-capture")]
 pub mod capture {
@@ -37,7 +38,106 @@ pub mod capture {
     }
 }
 
-fn kputs(s: &str) {
+pub mod ring {
+    //! A RAM ring buffer sink, keeping the latest output around for a crash screen or a dump.
+
+    use core::cell::UnsafeCell;
+
+    /// Bytes kept, older output is overwritten.
+    pub const SIZE: usize = 16 * 1024;
+
+    struct Ring {
+        buf: [u8; SIZE],
+        /// Index of the oldest byte.
+        head: usize,
+        len: usize,
+    }
+
+    struct RingCell(UnsafeCell<Ring>);
+
+    // SAFETY: Only accessed with interrupts disabled
+    unsafe impl Sync for RingCell {}
+
+    static RING: RingCell = RingCell(UnsafeCell::new(Ring {
+        buf: [0; SIZE],
+        head: 0,
+        len: 0,
+    }));
+
+    fn with<R>(f: impl FnOnce(&mut Ring) -> R) -> R {
+        crate::interrupt_disable_guard!();
+        f(unsafe { &mut *RING.0.get() })
+    }
+
+    /// Append `s`, usable with [`set_sink`](super::set_sink).
+    pub fn sink(s: &str) {
+        with(|ring| {
+            for &byte in s.as_bytes() {
+                let tail = (ring.head + ring.len) % SIZE;
+                ring.buf[tail] = byte;
+                if ring.len == SIZE {
+                    ring.head = (ring.head + 1) % SIZE;
+                } else {
+                    ring.len += 1;
+                }
+            }
+        })
+    }
+
+    /// Move the oldest output into `buf`, returns the number of bytes read.
+    pub fn read(buf: &mut [u8]) -> usize {
+        with(|ring| {
+            let len = buf.len().min(ring.len);
+            for byte in &mut buf[..len] {
+                *byte = ring.buf[ring.head];
+                ring.head = (ring.head + 1) % SIZE;
+            }
+            ring.len -= len;
+            len
+        })
+    }
+
+    /// Bytes waiting to be read.
+    pub fn len() -> usize {
+        with(|ring| ring.len)
+    }
+
+    pub fn is_empty() -> bool {
+        len() == 0
+    }
+
+    pub fn clear() {
+        with(|ring| ring.len = 0)
+    }
+}
+
+/// Destination of [`print`], called with interrupts possibly disabled.
+pub type Sink = fn(&str);
+
+/// The current sink, null until one is set.
+static SINK: AtomicPtr<()> = AtomicPtr::new(core::ptr::null_mut());
+
+/// Route the output of [`print`] to `sink`, returns the previous one.
+///
+/// The default sink is [`kputs`], which goes through DECI2.
+pub fn set_sink(sink: Sink) -> Sink {
+    crate::interrupt_disable_guard!();
+    let prev = self::sink();
+    SINK.store(sink as *mut (), Ordering::Relaxed);
+    prev
+}
+
+pub fn sink() -> Sink {
+    let sink = SINK.load(Ordering::Relaxed);
+    if sink.is_null() {
+        kputs
+    } else {
+        unsafe { core::mem::transmute::<*mut (), Sink>(sink) }
+    }
+}
+
+/// Print through the DECI2 `kputs` call, only useful with a DECI2 host attached.
+pub fn kputs(s: &str) {
     let mut buf = [0u8; 1024];
     for chunk in s.as_bytes().chunks(1023) {
         // Copy the slice into the chunk
@@ -58,16 +158,16 @@ pub fn print(args: fmt::Arguments) {
     }
 
     // If that fails, go through with the normal path
-    struct KPutsWriter;
+    struct SinkWriter(Sink);
 
-    impl fmt::Write for KPutsWriter {
+    impl fmt::Write for SinkWriter {
         fn write_str(&mut self, s: &str) -> fmt::Result {
-            kputs(s);
+            (self.0)(s);
             Ok(())
         }
     }
 
-    KPutsWriter.write_fmt(args).unwrap();
+    SinkWriter(sink()).write_fmt(args).unwrap();
 }
 
 #[macro_export]
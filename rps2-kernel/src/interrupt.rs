@@ -0,0 +1,356 @@
+//! Safe registration of INTC and DMAC interrupt handlers.
+
+use alloc::boxed::Box;
+use core::cell::UnsafeCell;
+use core::ffi::c_void;
+use core::fmt::{self, Debug};
+use core::marker::PhantomData;
+use core::mem;
+
+use crate::os::{self, dmac_channel, intc_cause, SemaParam, ThreadStatus};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(i32)]
+pub enum IntcCause {
+    Gs = intc_cause::GS,
+    Sbus = intc_cause::SBUS,
+    VblankStart = intc_cause::VBLANK_START,
+    VblankEnd = intc_cause::VBLANK_END,
+    Vif0 = intc_cause::VIF0,
+    Vif1 = intc_cause::VIF1,
+    Vu0 = intc_cause::VU0,
+    Vu1 = intc_cause::VU1,
+    Ipu = intc_cause::IPU,
+    Timer0 = intc_cause::TIMER0,
+    Timer1 = intc_cause::TIMER1,
+    Timer2 = intc_cause::TIMER2,
+    Timer3 = intc_cause::TIMER3,
+    Sfifo = intc_cause::SFIFO,
+    Vu0Watchdog = intc_cause::VU0_WATCHDOG,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(i32)]
+pub enum DmacChannel {
+    Vif0 = dmac_channel::VIF0,
+    Vif1 = dmac_channel::VIF1,
+    Gif = dmac_channel::GIF,
+    FromIpu = dmac_channel::FROM_IPU,
+    ToIpu = dmac_channel::TO_IPU,
+    Sif0 = dmac_channel::SIF0,
+    Sif1 = dmac_channel::SIF1,
+    Sif2 = dmac_channel::SIF2,
+    FromSpr = dmac_channel::FROM_SPR,
+    ToSpr = dmac_channel::TO_SPR,
+}
+
+/// Number of live handlers for each cause, along with whether the first of them enabled it. The
+/// cause is disabled when the count drops to zero, unless it was already enabled beforehand.
+struct UserCount<const N: usize>(UnsafeCell<[(u8, bool); N]>);
+
+// SAFETY: Only accessed with interrupts disabled
+unsafe impl<const N: usize> Sync for UserCount<N> {}
+
+impl<const N: usize> UserCount<N> {
+    const fn new() -> Self {
+        Self(UnsafeCell::new([(0, false); N]))
+    }
+
+    /// Calls `enable` for the first user, which returns 1 if it actually changed the state of the
+    /// cause. Interrupts must be disabled.
+    unsafe fn acquire(&self, idx: usize, enable: impl FnOnce() -> i32) {
+        let (count, owned) = &mut (*self.0.get())[idx];
+        *count += 1;
+        if *count == 1 {
+            *owned = enable() == 1;
+        }
+    }
+
+    /// Returns true for the last user, if the cause was enabled by the first one. Interrupts must
+    /// be disabled.
+    unsafe fn release(&self, idx: usize) -> bool {
+        let (count, owned) = &mut (*self.0.get())[idx];
+        *count -= 1;
+        *count == 0 && mem::take(owned)
+    }
+}
+
+static INTC_USERS: UserCount<15> = UserCount::new();
+static DMAC_USERS: UserCount<10> = UserCount::new();
+
+/// Access to the syscalls that are safe to call from an interrupt handler.
+pub struct HandlerContext {
+    _marker: PhantomData<*const ()>,
+}
+
+impl HandlerContext {
+    pub fn signal_sema(&self, sid: i32) -> i32 {
+        unsafe { os::i_signal_sema(sid) }
+    }
+
+    pub fn poll_sema(&self, sid: i32) -> i32 {
+        unsafe { os::i_poll_sema(sid) }
+    }
+
+    pub fn refer_sema_status(&self, sid: i32, status: &mut SemaParam) -> i32 {
+        unsafe { os::i_refer_sema_status(sid, status) }
+    }
+
+    pub fn delete_sema(&self, sid: i32) -> i32 {
+        unsafe { os::i_delete_sema(sid) }
+    }
+
+    pub fn terminate_thread(&self, tid: i32) -> i32 {
+        unsafe { os::i_terminate_thread(tid) }
+    }
+
+    pub fn change_thread_priority(&self, tid: i32, priority: i32) -> i32 {
+        unsafe { os::i_change_thread_priority(tid, priority) }
+    }
+
+    pub fn release_wait_thread(&self, tid: i32) -> i32 {
+        unsafe { os::i_release_wait_thread(tid) }
+    }
+
+    pub fn refer_thread_status(&self, tid: i32, status: &mut ThreadStatus) -> i32 {
+        unsafe { os::i_refer_thread_status(tid, status) }
+    }
+
+    pub fn cancel_wakeup_thread(&self, tid: i32) -> i32 {
+        unsafe { os::i_cancel_wakeup_thread(tid) }
+    }
+
+    pub fn resume_thread(&self, tid: i32) -> i32 {
+        unsafe { os::i_resume_thread(tid) }
+    }
+
+    pub fn flush_cache(&self, op: i32) {
+        unsafe { os::i_flush_cache(op) }
+    }
+
+    pub fn enable_intc(&self, cause: IntcCause) -> i32 {
+        unsafe { os::i_enable_intc(cause as i32) }
+    }
+
+    pub fn disable_intc(&self, cause: IntcCause) -> i32 {
+        unsafe { os::i_disable_intc(cause as i32) }
+    }
+
+    pub fn enable_dmac(&self, channel: DmacChannel) -> i32 {
+        unsafe { os::i_enable_dmac(channel as i32) }
+    }
+
+    pub fn disable_dmac(&self, channel: DmacChannel) -> i32 {
+        unsafe { os::i_disable_dmac(channel as i32) }
+    }
+
+    pub fn sif_dma_stat(&self, id: u32) -> i32 {
+        unsafe { os::i_sif_dma_stat(id) }
+    }
+
+    /// # Safety
+    /// `sdd` must point to `len` valid transfers.
+    pub unsafe fn sif_set_dma(&self, sdd: *const os::SifDmaTransfer, len: i32) -> u32 {
+        os::i_sif_set_dma(sdd, len)
+    }
+
+    pub fn sif_set_d_chain(&self) {
+        unsafe { os::i_sif_set_d_chain() }
+    }
+}
+
+impl Debug for HandlerContext {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("HandlerContext").finish_non_exhaustive()
+    }
+}
+
+extern "C" fn trampoline<F>(_cause: i32, arg: *mut c_void, _addr: *mut c_void) -> i32
+where
+    F: FnMut(&HandlerContext) + Send + 'static,
+{
+    let f = unsafe {
+        // SAFETY: arg is the closure owned by the guard, which removes the handler before
+        // dropping it
+        &mut *(arg as *mut F)
+    };
+
+    f(&HandlerContext {
+        _marker: PhantomData,
+    });
+
+    unsafe {
+        os::exit_handler();
+    }
+    0
+}
+
+unsafe fn drop_closure<F>(data: *mut ()) {
+    drop(Box::from_raw(data as *mut F));
+}
+
+struct Closure {
+    data: *mut (),
+    drop_fn: unsafe fn(*mut ()),
+}
+
+impl Closure {
+    fn new<F: Send + 'static>(f: F) -> Self {
+        Self {
+            data: Box::into_raw(Box::new(f)) as *mut (),
+            drop_fn: drop_closure::<F>,
+        }
+    }
+}
+
+impl Drop for Closure {
+    fn drop(&mut self) {
+        unsafe { (self.drop_fn)(self.data) }
+    }
+}
+
+/// Guard for a registered INTC handler, removing it when dropped.
+pub struct IntcHandler {
+    cause: IntcCause,
+    id: i32,
+    _closure: Closure,
+}
+
+// SAFETY: The closure is Send and never accessed through the guard
+unsafe impl Send for IntcHandler {}
+unsafe impl Sync for IntcHandler {}
+
+impl IntcHandler {
+    /// Register `f` to be called on every `cause` interrupt, and enable it.
+    ///
+    /// Returns `None` if the kernel ran out of handler slots.
+    pub fn register<F>(cause: IntcCause, f: F) -> Option<Self>
+    where
+        F: FnMut(&HandlerContext) + Send + 'static,
+    {
+        let closure = Closure::new(f);
+
+        crate::interrupt_disable_guard!();
+
+        let id = unsafe {
+            os::add_intc_handler2(
+                cause as i32,
+                trampoline::<F>,
+                0,
+                closure.data as *mut c_void,
+            )
+        };
+        if id < 0 {
+            return None;
+        }
+
+        unsafe {
+            INTC_USERS.acquire(cause as usize, || os::enable_intc(cause as i32));
+        }
+
+        Some(Self {
+            cause,
+            id,
+            _closure: closure,
+        })
+    }
+
+    pub fn cause(&self) -> IntcCause {
+        self.cause
+    }
+}
+
+impl Drop for IntcHandler {
+    fn drop(&mut self) {
+        crate::interrupt_disable_guard!();
+
+        unsafe {
+            if INTC_USERS.release(self.cause as usize) {
+                os::disable_intc(self.cause as i32);
+            }
+            os::remove_intc_handler(self.cause as i32, self.id);
+        }
+    }
+}
+
+impl Debug for IntcHandler {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("IntcHandler")
+            .field("cause", &self.cause)
+            .field("id", &self.id)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Guard for a registered DMAC handler, removing it when dropped.
+pub struct DmacHandler {
+    channel: DmacChannel,
+    id: i32,
+    _closure: Closure,
+}
+
+// SAFETY: The closure is Send and never accessed through the guard
+unsafe impl Send for DmacHandler {}
+unsafe impl Sync for DmacHandler {}
+
+impl DmacHandler {
+    /// Register `f` to be called on every `channel` completion interrupt, and enable it.
+    ///
+    /// Returns `None` if the kernel ran out of handler slots.
+    pub fn register<F>(channel: DmacChannel, f: F) -> Option<Self>
+    where
+        F: FnMut(&HandlerContext) + Send + 'static,
+    {
+        let closure = Closure::new(f);
+
+        crate::interrupt_disable_guard!();
+
+        let id = unsafe {
+            os::add_dmac_handler2(
+                channel as i32,
+                trampoline::<F>,
+                0,
+                closure.data as *mut c_void,
+            )
+        };
+        if id < 0 {
+            return None;
+        }
+
+        unsafe {
+            DMAC_USERS.acquire(channel as usize, || os::enable_dmac(channel as i32));
+        }
+
+        Some(Self {
+            channel,
+            id,
+            _closure: closure,
+        })
+    }
+
+    pub fn channel(&self) -> DmacChannel {
+        self.channel
+    }
+}
+
+impl Drop for DmacHandler {
+    fn drop(&mut self) {
+        crate::interrupt_disable_guard!();
+
+        unsafe {
+            if DMAC_USERS.release(self.channel as usize) {
+                os::disable_dmac(self.channel as i32);
+            }
+            os::remove_dmac_handler(self.channel as i32, self.id);
+        }
+    }
+}
+
+impl Debug for DmacHandler {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("DmacHandler")
+            .field("channel", &self.channel)
+            .field("id", &self.id)
+            .finish_non_exhaustive()
+    }
+}
@@ -7,6 +7,9 @@ pub mod arch;
 pub mod debug;
 pub mod deci2;
 pub mod env;
+pub mod interrupt;
+#[cfg(feature = "log")]
+pub mod logger;
 pub mod os;
 
 #[cfg(feature = "atomics")]
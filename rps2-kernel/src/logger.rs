@@ -0,0 +1,179 @@
+//! A [`log`] backend printing through [`debug::print`](crate::debug::print).
+//!
+//! Records are prefixed with the cop0 cycle counter and the id of the current thread:
+//!
+//! ```text
+//! [  12345678 T1  INFO  my_game::net] connected
+//! ```
+//!
+//! Levels are filtered at compile time by the `max_level_*` and `release_max_level_*` features
+//! of the `log` crate, then at runtime by a default level and per-module filters. Filters are
+//! read from a `--log=<spec>` program argument, where `<spec>` is a comma separated list of
+//! `level` or `module::path=level`, for example `--log=warn,my_game::net=trace`.
+//!
+//! Logging is safe from interrupt handlers: records are formatted on the stack and the logger
+//! never blocks.
+
+use core::cell::UnsafeCell;
+use core::fmt::{self, Write};
+use core::str;
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+pub use log::{Level, LevelFilter, SetLoggerError};
+use log::{Log, Metadata, Record};
+pub use rps2_log_filters::{Filters, MAX_FILTERS};
+
+use crate::arch::cop0;
+use crate::{debug, env, os};
+
+/// Prefix of the program argument holding the filters.
+pub const ARG_PREFIX: &str = "--log=";
+
+const LINE_SIZE: usize = 256;
+
+struct Logger {
+    filters: UnsafeCell<Filters>,
+    level: AtomicUsize,
+}
+
+// SAFETY: The filters are only written once, before the logger is installed
+unsafe impl Sync for Logger {}
+
+static LOGGER: Logger = Logger {
+    filters: UnsafeCell::new(Filters::new()),
+    level: AtomicUsize::new(LevelFilter::Info as usize),
+};
+
+static INITIALIZED: AtomicBool = AtomicBool::new(false);
+
+impl Logger {
+    fn filters(&self) -> &Filters {
+        unsafe { &*self.filters.get() }
+    }
+
+    fn level(&self) -> LevelFilter {
+        match self.level.load(Ordering::Relaxed) {
+            0 => LevelFilter::Off,
+            1 => LevelFilter::Error,
+            2 => LevelFilter::Warn,
+            3 => LevelFilter::Info,
+            4 => LevelFilter::Debug,
+            _ => LevelFilter::Trace,
+        }
+    }
+
+    fn update_max_level(&self) {
+        log::set_max_level(self.level().max(self.filters().max_level()));
+    }
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        let level = self
+            .filters()
+            .level_for(metadata.target())
+            .unwrap_or_else(|| self.level());
+        metadata.level() <= level
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        let cycles = cop0::get_count();
+        let tid = {
+            crate::interrupt_disable_guard!();
+            // The interrupt variant works from both threads and handlers
+            unsafe { os::_i_get_thread_id() }
+        };
+
+        let mut line = LineWriter::new();
+        let _ = writeln!(
+            line,
+            "[{cycles:>10} T{tid:<2} {:<5} {}] {}",
+            record.level(),
+            record.target(),
+            record.args()
+        );
+        line.flush();
+    }
+
+    fn flush(&self) {}
+}
+
+/// Formats a record on the stack, so that it is printed in one piece if it fits.
+struct LineWriter {
+    buf: [u8; LINE_SIZE],
+    len: usize,
+}
+
+impl LineWriter {
+    fn new() -> Self {
+        Self {
+            buf: [0; LINE_SIZE],
+            len: 0,
+        }
+    }
+
+    fn flush(&mut self) {
+        if self.len > 0 {
+            // Only whole strs are written to the buffer
+            let s = unsafe { str::from_utf8_unchecked(&self.buf[..self.len]) };
+            debug::print(format_args!("{s}"));
+            self.len = 0;
+        }
+    }
+}
+
+impl fmt::Write for LineWriter {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        if self.len + s.len() > LINE_SIZE {
+            self.flush();
+        }
+        if s.len() > LINE_SIZE {
+            debug::print(format_args!("{s}"));
+        } else {
+            self.buf[self.len..self.len + s.len()].copy_from_slice(s.as_bytes());
+            self.len += s.len();
+        }
+        Ok(())
+    }
+}
+
+/// Install the logger with the filters of the `--log=` program argument, if any.
+///
+/// The default level is [`LevelFilter::Info`] unless the argument sets one.
+pub fn init() -> Result<(), SetLoggerError> {
+    let spec = env::args()
+        .filter_map(|arg| arg.to_str().ok()?.strip_prefix(ARG_PREFIX))
+        .last()
+        .unwrap_or("");
+    init_with(Filters::parse(spec))
+}
+
+/// Install the logger with the given filters, fails if a logger is already installed.
+pub fn init_with(filters: Filters) -> Result<(), SetLoggerError> {
+    if INITIALIZED.swap(true, Ordering::Acquire) {
+        // Let the log crate produce the error
+        return log::set_logger(&LOGGER);
+    }
+
+    unsafe { *LOGGER.filters.get() = filters };
+    if let Some(level) = filters.default_level() {
+        LOGGER.level.store(level as usize, Ordering::Relaxed);
+    }
+    log::set_logger(&LOGGER)?;
+    LOGGER.update_max_level();
+    Ok(())
+}
+
+/// Change the level of modules without a filter.
+pub fn set_level(level: LevelFilter) {
+    LOGGER.level.store(level as usize, Ordering::Relaxed);
+    LOGGER.update_max_level();
+}
+
+pub fn level() -> LevelFilter {
+    LOGGER.level()
+}
@@ -246,6 +246,11 @@ define_syscall! {
     pub fn ps_mode() as 0x7d;
     pub fn machine_type() -> i32 as 0x7e;
     pub fn get_memory_size() -> i32 as 0x7f;
+
+    pub fn set_alarm(time: u16, handler: AlarmHandler, common: *mut c_void) -> i32 as 0xfe;
+    pub fn i_set_alarm(time: u16, handler: AlarmHandler, common: *mut c_void) -> i32 as -0xfe;
+    pub fn release_alarm(alarm_id: i32) -> i32 as 0xff;
+    pub fn i_release_alarm(alarm_id: i32) -> i32 as -0xff;
 }
 
 pub unsafe fn disable_intc(cause: i32) -> i32 {
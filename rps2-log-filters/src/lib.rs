@@ -0,0 +1,135 @@
+//! Per-module level filters of the rps2 logger, parsed from a spec like
+//! `warn,my_game::net=trace`.
+//!
+//! This crate only parses and matches filters, the logger itself lives in rps2-kernel.
+
+#![no_std]
+#![deny(missing_debug_implementations)]
+
+use log::LevelFilter;
+
+/// Maximum number of module filters, extra ones are ignored.
+pub const MAX_FILTERS: usize = 16;
+
+/// Module filters parsed from a spec.
+#[derive(Debug, Clone, Copy)]
+pub struct Filters {
+    default: Option<LevelFilter>,
+    modules: [(&'static str, LevelFilter); MAX_FILTERS],
+    len: usize,
+}
+
+impl Filters {
+    pub const fn new() -> Self {
+        Self {
+            default: None,
+            modules: [("", LevelFilter::Off); MAX_FILTERS],
+            len: 0,
+        }
+    }
+
+    /// Parse a comma separated list of `level` or `module::path=level`, skipping invalid entries.
+    pub fn parse(spec: &'static str) -> Self {
+        let mut filters = Self::new();
+        for entry in spec.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            match entry.split_once('=') {
+                Some((module, level)) => {
+                    let Ok(level) = level.trim().parse() else {
+                        continue;
+                    };
+                    if filters.len < MAX_FILTERS {
+                        filters.modules[filters.len] = (module.trim(), level);
+                        filters.len += 1;
+                    }
+                }
+                None => {
+                    if let Ok(level) = entry.parse() {
+                        filters.default = Some(level);
+                    }
+                }
+            }
+        }
+        filters
+    }
+
+    /// The level set without a module, if any.
+    pub fn default_level(&self) -> Option<LevelFilter> {
+        self.default
+    }
+
+    pub fn modules(&self) -> &[(&'static str, LevelFilter)] {
+        &self.modules[..self.len]
+    }
+
+    /// Level of the longest filter matching `module`, a filter matches its submodules too.
+    pub fn level_for(&self, module: &str) -> Option<LevelFilter> {
+        self.modules()
+            .iter()
+            .filter(|(path, _)| {
+                module
+                    .strip_prefix(path)
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with("::"))
+            })
+            .max_by_key(|(path, _)| path.len())
+            .map(|&(_, level)| level)
+    }
+
+    /// The most verbose level enabled by a module filter.
+    pub fn max_level(&self) -> LevelFilter {
+        self.modules()
+            .iter()
+            .map(|&(_, level)| level)
+            .max()
+            .unwrap_or(LevelFilter::Off)
+    }
+}
+
+impl Default for Filters {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filters() {
+        let filters =
+            Filters::parse("warn, my_game=debug,my_game::net=trace,rps2_sif=off,bad=loud,");
+        assert_eq!(filters.default_level(), Some(LevelFilter::Warn));
+        assert_eq!(filters.modules().len(), 3);
+        assert_eq!(filters.max_level(), LevelFilter::Trace);
+
+        assert_eq!(filters.level_for("my_game"), Some(LevelFilter::Debug));
+        assert_eq!(
+            filters.level_for("my_game::render"),
+            Some(LevelFilter::Debug)
+        );
+        assert_eq!(
+            filters.level_for("my_game::net::tcp"),
+            Some(LevelFilter::Trace)
+        );
+        assert_eq!(filters.level_for("rps2_sif::cdvd"), Some(LevelFilter::Off));
+        // Only whole path segments match
+        assert_eq!(filters.level_for("my_game_tools"), None);
+        assert_eq!(filters.level_for("bad"), None);
+
+        let filters = Filters::parse("");
+        assert_eq!(filters.default_level(), None);
+        assert!(filters.modules().is_empty());
+        assert_eq!(filters.max_level(), LevelFilter::Off);
+    }
+
+    #[test]
+    fn test_filters_overflow() {
+        // Filters past the maximum are dropped
+        let filters = Filters::parse(concat!(
+            "a=info,b=info,c=info,d=info,e=info,f=info,g=info,h=info,",
+            "i=info,j=info,k=info,l=info,m=info,n=info,o=info,p=info,q=info",
+        ));
+        assert_eq!(filters.modules().len(), MAX_FILTERS);
+        assert_eq!(filters.level_for("q"), None);
+    }
+}
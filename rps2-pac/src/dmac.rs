@@ -0,0 +1,378 @@
+//! DMA controller registers and source chain tags.
+
+use crate::reg::{bitfield, field_enum, Reg, R, RW, W};
+
+field_enum! {
+    /// Transfer direction, only meaningful for channels supporting both.
+    pub struct Direction(u8) {
+        TO_MEMORY = 0;
+        FROM_MEMORY = 1;
+    }
+}
+
+field_enum! {
+    /// Logical transfer mode.
+    pub struct Mode(u8) {
+        NORMAL = 0;
+        CHAIN = 1;
+        INTERLEAVE = 2;
+    }
+}
+
+bitfield! {
+    /// Channel control.
+    pub struct Chcr(u32) {
+        /// Transfer direction.
+        dir, with_dir: Direction = 0;
+        /// Logical transfer mode.
+        mode, with_mode: Mode = 2..=3;
+        /// Address stack pointer, used by `call` and `ret` tags.
+        asp, with_asp: u8 = 4..=5;
+        /// Transfer the upper 64 bits of the tags along with the data.
+        tte, with_tte: bool = 6;
+        /// Enable the `IRQ` bit of the tags.
+        tie, with_tie: bool = 7;
+        /// Start the transfer, stays set while the channel is busy.
+        str, with_str: bool = 8;
+        /// Bits 16-31 of the last tag read.
+        tag, with_tag: u16 = 16..=31;
+    }
+}
+
+bitfield! {
+    /// Memory address, used by MADR, TADR, ASR0, ASR1 and the global address registers.
+    pub struct Madr(u32) {
+        /// Physical address, or scratchpad offset if `spr` is set.
+        addr, with_addr: u32 = 0..=30;
+        /// Address refers to the scratchpad.
+        spr, with_spr: bool = 31;
+    }
+}
+
+impl Madr {
+    /// Build an address from a pointer, see [`dma_addr`].
+    pub fn from_ptr<T>(ptr: *const T) -> Self {
+        Self(dma_addr(ptr))
+    }
+}
+
+bitfield! {
+    /// Quadword count.
+    pub struct Qwc(u32) {
+        qwc, with_qwc: u16 = 0..=15;
+    }
+}
+
+bitfield! {
+    /// Scratchpad address, only present on the scratchpad channels.
+    pub struct Sadr(u32) {
+        addr, with_addr: u16 = 0..=13;
+    }
+}
+
+field_enum! {
+    /// Stall control source channel.
+    pub struct StallSource(u8) {
+        NONE = 0;
+        SIF0 = 1;
+        FROM_SPR = 2;
+        FROM_IPU = 3;
+    }
+}
+
+field_enum! {
+    /// Stall control drain channel.
+    pub struct StallDrain(u8) {
+        VIF1 = 0;
+        GIF = 1;
+        SIF1 = 2;
+    }
+}
+
+field_enum! {
+    /// MFIFO drain channel.
+    pub struct MfifoDrain(u8) {
+        NONE = 0;
+        VIF1 = 2;
+        GIF = 3;
+    }
+}
+
+bitfield! {
+    /// DMAC control.
+    pub struct Ctrl(u32) {
+        /// Enable all DMA transfers.
+        dmae, with_dmae: bool = 0;
+        /// Enable cycle stealing.
+        rele, with_rele: bool = 1;
+        mfd, with_mfd: MfifoDrain = 2..=3;
+        sts, with_sts: StallSource = 4..=5;
+        std, with_std: StallDrain = 6..=7;
+        /// Release cycle, 8 << rcyc cycles.
+        rcyc, with_rcyc: u8 = 8..=10;
+    }
+}
+
+bitfield! {
+    /// DMAC interrupt status.
+    ///
+    /// Status bits are cleared by writing 1 to them, mask bits are toggled by writing 1 to them.
+    pub struct Stat(u32) {
+        /// Channel interrupt status, one bit per channel.
+        cis, with_cis: u16 = 0..=9;
+        /// DMA stall interrupt status.
+        sis, with_sis: bool = 13;
+        /// MFIFO empty interrupt status.
+        meis, with_meis: bool = 14;
+        /// Bus error interrupt status.
+        beis, with_beis: bool = 15;
+        /// Channel interrupt mask, one bit per channel.
+        cim, with_cim: u16 = 16..=25;
+        /// DMA stall interrupt mask.
+        sim, with_sim: bool = 29;
+        /// MFIFO empty interrupt mask.
+        meim, with_meim: bool = 30;
+    }
+}
+
+bitfield! {
+    /// DMAC priority control.
+    pub struct Pcr(u32) {
+        /// COP control, one bit per channel.
+        cpc, with_cpc: u16 = 0..=9;
+        /// Channel DMA enable, one bit per channel.
+        cde, with_cde: u16 = 16..=25;
+        /// Enable the priority control, otherwise `cde` is ignored.
+        pce, with_pce: bool = 31;
+    }
+}
+
+bitfield! {
+    /// Interleave mode skip and transfer sizes.
+    pub struct Sqwc(u32) {
+        sqwc, with_sqwc: u8 = 0..=7;
+        tqwc, with_tqwc: u8 = 16..=23;
+    }
+}
+
+bitfield! {
+    /// MFIFO ring buffer size mask.
+    pub struct Rbsr(u32) {
+        rmsk, with_rmsk: u32 = 4..=30;
+    }
+}
+
+bitfield! {
+    /// DMA hold control, used to suspend all transfers.
+    pub struct Enable(u32) {
+        cpnd, with_cpnd: bool = 16;
+    }
+}
+
+/// Register block of a single DMA channel.
+///
+/// Not every channel implements every register: TADR is only present on channels supporting
+/// chain mode, ASR0/ASR1 only on VIF0, VIF1 and GIF, and SADR only on the scratchpad channels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Channel {
+    id: u8,
+    base: usize,
+}
+
+impl Channel {
+    const fn new(id: u8, base: usize) -> Self {
+        Self { id, base }
+    }
+
+    /// Channel number, as used by `add_dmac_handler` and the D_STAT bits.
+    pub const fn id(&self) -> u8 {
+        self.id
+    }
+
+    pub const fn chcr(&self) -> Reg<Chcr, RW> {
+        unsafe { Reg::new(self.base) }
+    }
+
+    pub const fn madr(&self) -> Reg<Madr, RW> {
+        unsafe { Reg::new(self.base + 0x10) }
+    }
+
+    pub const fn qwc(&self) -> Reg<Qwc, RW> {
+        unsafe { Reg::new(self.base + 0x20) }
+    }
+
+    pub const fn tadr(&self) -> Reg<Madr, RW> {
+        unsafe { Reg::new(self.base + 0x30) }
+    }
+
+    pub const fn asr0(&self) -> Reg<Madr, RW> {
+        unsafe { Reg::new(self.base + 0x40) }
+    }
+
+    pub const fn asr1(&self) -> Reg<Madr, RW> {
+        unsafe { Reg::new(self.base + 0x50) }
+    }
+
+    pub const fn sadr(&self) -> Reg<Sadr, RW> {
+        unsafe { Reg::new(self.base + 0x80) }
+    }
+
+    /// Check if the channel is currently transferring.
+    pub fn is_busy(&self) -> bool {
+        self.chcr().read().str()
+    }
+}
+
+pub const VIF0: Channel = Channel::new(0, 0x1000_8000);
+pub const VIF1: Channel = Channel::new(1, 0x1000_9000);
+pub const GIF: Channel = Channel::new(2, 0x1000_a000);
+pub const FROM_IPU: Channel = Channel::new(3, 0x1000_b000);
+pub const TO_IPU: Channel = Channel::new(4, 0x1000_b400);
+pub const SIF0: Channel = Channel::new(5, 0x1000_c000);
+pub const SIF1: Channel = Channel::new(6, 0x1000_c400);
+pub const SIF2: Channel = Channel::new(7, 0x1000_c800);
+pub const FROM_SPR: Channel = Channel::new(8, 0x1000_d000);
+pub const TO_SPR: Channel = Channel::new(9, 0x1000_d400);
+
+/// All channels, indexed by channel number.
+pub const CHANNELS: [Channel; 10] = [
+    VIF0, VIF1, GIF, FROM_IPU, TO_IPU, SIF0, SIF1, SIF2, FROM_SPR, TO_SPR,
+];
+
+pub const D_CTRL: Reg<Ctrl, RW> = unsafe { Reg::new(0x1000_e000) };
+pub const D_STAT: Reg<Stat, RW> = unsafe { Reg::new(0x1000_e010) };
+pub const D_PCR: Reg<Pcr, RW> = unsafe { Reg::new(0x1000_e020) };
+pub const D_SQWC: Reg<Sqwc, RW> = unsafe { Reg::new(0x1000_e030) };
+pub const D_RBSR: Reg<Rbsr, RW> = unsafe { Reg::new(0x1000_e040) };
+pub const D_RBOR: Reg<Madr, RW> = unsafe { Reg::new(0x1000_e050) };
+pub const D_STADR: Reg<Madr, RW> = unsafe { Reg::new(0x1000_e060) };
+pub const D_ENABLER: Reg<Enable, R> = unsafe { Reg::new(0x1000_f520) };
+pub const D_ENABLEW: Reg<Enable, W> = unsafe { Reg::new(0x1000_f590) };
+
+/// Convert a pointer into an address usable by the DMAC.
+///
+/// Main memory addresses have their segment bits stripped (so cached, uncached and uncached
+/// accelerated pointers all work), while scratchpad addresses are converted into an offset with
+/// the SPR bit set.
+pub fn dma_addr<T>(ptr: *const T) -> u32 {
+    let addr = ptr as usize as u32;
+    if addr & 0xf000_0000 == 0x7000_0000 {
+        (addr & 0x3fff) | 0x8000_0000
+    } else {
+        addr & 0x0fff_ffff
+    }
+}
+
+field_enum! {
+    /// Source chain tag id.
+    pub struct TagId(u8) {
+        /// Transfer `qwc` quadwords from `addr`, then end.
+        REFE = 0;
+        /// Transfer `qwc` quadwords following the tag, the next tag follows the data.
+        CNT = 1;
+        /// Transfer `qwc` quadwords following the tag, the next tag is at `addr`.
+        NEXT = 2;
+        /// Transfer `qwc` quadwords from `addr`, the next tag follows this one.
+        REF = 3;
+        /// Same as `REF`, but subject to stall control.
+        REFS = 4;
+        /// Transfer `qwc` quadwords following the tag, push the address after the data and
+        /// continue at `addr`.
+        CALL = 5;
+        /// Transfer `qwc` quadwords following the tag, pop the next tag address.
+        RET = 6;
+        /// Transfer `qwc` quadwords following the tag, then end.
+        END = 7;
+    }
+}
+
+bitfield! {
+    /// Lower 64 bits of a source chain tag.
+    pub struct TagHeader(u64) {
+        qwc, with_qwc: u16 = 0..=15;
+        /// Priority control, 2 to disable and 3 to enable the channel in D_PCR.
+        pce, with_pce: u8 = 26..=27;
+        id, with_id: TagId = 28..=30;
+        /// Raise an interrupt at the end of this tag, if enabled by `CHCR.TIE`.
+        irq, with_irq: bool = 31;
+        /// Address in the format returned by [`dma_addr`].
+        addr, with_addr: u32 = 32..=63;
+    }
+}
+
+/// A source chain DMA tag, a full quadword.
+///
+/// The upper 64 bits are transferred along with the data when `CHCR.TTE` is set, and are
+/// usually used for VIF codes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[repr(C, align(16))]
+pub struct DmaTag {
+    pub header: TagHeader,
+    pub data: [u32; 2],
+}
+
+impl DmaTag {
+    pub fn new(id: TagId, qwc: u16, addr: u32) -> Self {
+        Self {
+            header: TagHeader::new().with_id(id).with_qwc(qwc).with_addr(addr),
+            data: [0; 2],
+        }
+    }
+
+    pub fn refe(addr: u32, qwc: u16) -> Self {
+        Self::new(TagId::REFE, qwc, addr)
+    }
+
+    pub fn cnt(qwc: u16) -> Self {
+        Self::new(TagId::CNT, qwc, 0)
+    }
+
+    pub fn next(addr: u32, qwc: u16) -> Self {
+        Self::new(TagId::NEXT, qwc, addr)
+    }
+
+    pub fn ref_(addr: u32, qwc: u16) -> Self {
+        Self::new(TagId::REF, qwc, addr)
+    }
+
+    pub fn refs(addr: u32, qwc: u16) -> Self {
+        Self::new(TagId::REFS, qwc, addr)
+    }
+
+    pub fn call(addr: u32, qwc: u16) -> Self {
+        Self::new(TagId::CALL, qwc, addr)
+    }
+
+    pub fn ret(qwc: u16) -> Self {
+        Self::new(TagId::RET, qwc, 0)
+    }
+
+    pub fn end(qwc: u16) -> Self {
+        Self::new(TagId::END, qwc, 0)
+    }
+
+    #[must_use]
+    pub fn with_irq(mut self) -> Self {
+        self.header = self.header.with_irq(true);
+        self
+    }
+
+    #[must_use]
+    pub fn with_data(mut self, data: [u32; 2]) -> Self {
+        self.data = data;
+        self
+    }
+
+    pub fn to_qword(self) -> u128 {
+        (self.header.bits() as u128)
+            | ((self.data[0] as u128) << 64)
+            | ((self.data[1] as u128) << 96)
+    }
+
+    pub fn from_qword(qword: u128) -> Self {
+        Self {
+            header: TagHeader::from_bits(qword as u64),
+            data: [(qword >> 64) as u32, (qword >> 96) as u32],
+        }
+    }
+}
@@ -0,0 +1,564 @@
+//! GIF tags and packet building.
+//!
+//! Packets are built in memory as a list of quadwords, ready to be sent through PATH3 with a
+//! normal or chain mode GIF DMA transfer. The encoder does not touch the hardware.
+
+use alloc::vec::Vec;
+
+use crate::gs::general::{
+    Bitbltbuf, Clamp, Context, GsCtxReg, GsReg, Prim, RegAddr, Tex0, TransferDir, Trxdir, Trxpos,
+    Trxreg, Xyz, Xyzf,
+};
+use crate::gs::Psm;
+use crate::reg::{bitfield, field_enum, RegValue};
+
+/// Maximum value of the NLOOP field.
+pub const MAX_NLOOP: usize = 0x7fff;
+
+field_enum! {
+    /// Data format following a GIF tag.
+    pub struct Flg(u8) {
+        PACKED = 0;
+        REGLIST = 1;
+        IMAGE = 2;
+    }
+}
+
+field_enum! {
+    /// Register descriptor, used by the REGS field in PACKED and REGLIST modes.
+    pub struct RegDesc(u8) {
+        PRIM = 0x0;
+        RGBAQ = 0x1;
+        ST = 0x2;
+        UV = 0x3;
+        XYZF2 = 0x4;
+        XYZ2 = 0x5;
+        TEX0_1 = 0x6;
+        TEX0_2 = 0x7;
+        CLAMP_1 = 0x8;
+        CLAMP_2 = 0x9;
+        FOG = 0xa;
+        XYZF3 = 0xc;
+        XYZ3 = 0xd;
+        A_D = 0xe;
+        NOP = 0xf;
+    }
+}
+
+bitfield! {
+    /// GIF tag.
+    pub struct GifTag(u128) {
+        /// Number of loops over the register descriptors.
+        nloop, with_nloop: u16 = 0..=14;
+        /// Last tag of the packet.
+        eop, with_eop: bool = 15;
+        /// Write the `prim` field to the PRIM register.
+        pre, with_pre: bool = 46;
+        prim, with_prim: Prim = 47..=57;
+        flg, with_flg: Flg = 58..=59;
+        /// Number of register descriptors, 0 means 16.
+        nreg, with_nreg: u8 = 60..=63;
+        regs, with_regs: u64 = 64..=127;
+    }
+}
+
+impl GifTag {
+    /// Set both NREG and REGS from a list of up to 16 descriptors.
+    #[must_use]
+    pub fn with_descs(self, descs: &[RegDesc]) -> Self {
+        assert!(
+            !descs.is_empty() && descs.len() <= 16,
+            "invalid number of register descriptors"
+        );
+
+        let regs = descs
+            .iter()
+            .enumerate()
+            .fold(0u64, |acc, (i, desc)| acc | ((desc.0 as u64) << (i * 4)));
+
+        self.with_nreg((descs.len() & 0xf) as u8).with_regs(regs)
+    }
+
+    pub fn desc(self, i: usize) -> RegDesc {
+        RegDesc(((self.regs() >> (i * 4)) & 0xf) as u8)
+    }
+
+    pub fn desc_count(self) -> usize {
+        match self.nreg() {
+            0 => 16,
+            n => n as usize,
+        }
+    }
+}
+
+/// A GIF packet under construction.
+///
+/// The last tag of the packet always has its EOP bit set, so the packet can be sent at any
+/// point.
+#[derive(Debug, Clone, Default)]
+pub struct Packet {
+    data: Vec<u128>,
+    last_tag: Option<usize>,
+}
+
+impl Packet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a packet with space for `qwc` quadwords.
+    pub fn with_capacity(qwc: usize) -> Self {
+        Self {
+            data: Vec::with_capacity(qwc),
+            last_tag: None,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.data.clear();
+        self.last_tag = None;
+    }
+
+    pub fn as_slice(&self) -> &[u128] {
+        &self.data
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        unsafe {
+            // SAFETY: u128 has no padding and any byte is a valid u8
+            core::slice::from_raw_parts(self.data.as_ptr() as *const u8, self.data.len() * 16)
+        }
+    }
+
+    pub fn into_vec(self) -> Vec<u128> {
+        self.data
+    }
+
+    /// Append a raw tag, the caller is responsible for the data following it.
+    pub fn tag(&mut self, tag: GifTag) {
+        if let Some(last) = self.last_tag {
+            let prev = GifTag(self.data[last]).with_eop(false);
+            self.data[last] = prev.bits();
+        }
+
+        self.last_tag = Some(self.data.len());
+        self.data.push(tag.with_eop(true).bits());
+    }
+
+    /// Append a raw quadword.
+    pub fn qword(&mut self, qword: u128) {
+        self.data.push(qword);
+    }
+
+    /// Start a PACKED mode list with the given register descriptors.
+    pub fn packed(&mut self, prim: Option<Prim>, descs: &[RegDesc]) -> PackedList<'_> {
+        let mut tag = GifTag::new().with_flg(Flg::PACKED).with_descs(descs);
+        if let Some(prim) = prim {
+            tag = tag.with_pre(true).with_prim(prim);
+        }
+
+        PackedList::new(self, tag)
+    }
+
+    /// Start a PACKED mode list of A+D register writes.
+    pub fn a_d(&mut self) -> AdList<'_> {
+        AdList(self.packed(None, &[RegDesc::A_D]))
+    }
+
+    /// Start a REGLIST mode list with the given register descriptors.
+    pub fn reglist(&mut self, descs: &[RegDesc]) -> RegList<'_> {
+        let tag = GifTag::new().with_flg(Flg::REGLIST).with_descs(descs);
+        RegList::new(self, tag)
+    }
+
+    /// Append IMAGE mode data, padded with zeroes to a quadword boundary.
+    pub fn image(&mut self, data: &[u8]) {
+        for chunk in data.chunks(MAX_NLOOP * 16) {
+            let qwc = chunk.len().div_ceil(16);
+            self.tag(GifTag::new().with_flg(Flg::IMAGE).with_nloop(qwc as u16));
+
+            for qword in chunk.chunks(16) {
+                let mut bytes = [0u8; 16];
+                bytes[..qword.len()].copy_from_slice(qword);
+                self.data.push(u128::from_le_bytes(bytes));
+            }
+        }
+    }
+
+    /// Append a complete host to local image transfer.
+    pub fn upload_image(&mut self, upload: &ImageUpload, data: &[u8]) {
+        debug_assert_eq!(
+            data.len(),
+            upload.size(),
+            "image data does not match the transfer size"
+        );
+
+        self.a_d()
+            .reg(
+                Bitbltbuf::new()
+                    .with_dbp(upload.dbp)
+                    .with_dbw(upload.dbw)
+                    .with_dpsm(upload.psm),
+            )
+            .reg(Trxpos::new().with_dsax(upload.x).with_dsay(upload.y))
+            .reg(Trxreg::new().with_rrw(upload.width).with_rrh(upload.height))
+            .reg(Trxdir::new().with_xdir(TransferDir::HOST_TO_LOCAL));
+
+        self.image(data);
+    }
+}
+
+/// Parameters of a host to local image transfer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ImageUpload {
+    /// Destination base pointer, in units of 64 words.
+    pub dbp: u16,
+    /// Destination buffer width, in units of 64 pixels.
+    pub dbw: u8,
+    pub psm: Psm,
+    pub x: u16,
+    pub y: u16,
+    pub width: u16,
+    pub height: u16,
+}
+
+impl ImageUpload {
+    /// Size in bytes of the image data.
+    pub fn size(&self) -> usize {
+        // 24 bit formats are transferred packed
+        let bpp = match self.psm {
+            Psm::CT24 | Psm::Z24 => 24,
+            psm => psm.bits_per_pixel() as usize,
+        };
+
+        (self.width as usize * self.height as usize * bpp) / 8
+    }
+}
+
+/// A PACKED mode list, the tag's NLOOP is fixed up on drop.
+#[derive(Debug)]
+pub struct PackedList<'a> {
+    packet: &'a mut Packet,
+    tag: GifTag,
+    start: usize,
+    prev_tag: Option<usize>,
+}
+
+impl<'a> PackedList<'a> {
+    fn new(packet: &'a mut Packet, tag: GifTag) -> Self {
+        let prev_tag = packet.last_tag;
+        packet.tag(tag);
+        let start = packet.data.len();
+
+        Self {
+            packet,
+            tag,
+            start,
+            prev_tag,
+        }
+    }
+
+    fn count(&self) -> usize {
+        self.packet.data.len() - self.start
+    }
+
+    fn push(&mut self, desc: RegDesc, qword: u128) -> &mut Self {
+        debug_assert_eq!(
+            self.tag.desc(self.count() % self.tag.desc_count()),
+            desc,
+            "register written out of descriptor order"
+        );
+
+        self.packet.data.push(qword);
+        self
+    }
+
+    pub fn prim(&mut self, prim: Prim) -> &mut Self {
+        self.push(RegDesc::PRIM, prim.bits() as u128)
+    }
+
+    /// Color, Q is taken from the last ST write.
+    pub fn rgba(&mut self, r: u8, g: u8, b: u8, a: u8) -> &mut Self {
+        let qword = (r as u128) | ((g as u128) << 32) | ((b as u128) << 64) | ((a as u128) << 96);
+        self.push(RegDesc::RGBAQ, qword)
+    }
+
+    pub fn st(&mut self, s: f32, t: f32, q: f32) -> &mut Self {
+        let qword =
+            (s.to_bits() as u128) | ((t.to_bits() as u128) << 32) | ((q.to_bits() as u128) << 64);
+        self.push(RegDesc::ST, qword)
+    }
+
+    pub fn uv(&mut self, u: u16, v: u16) -> &mut Self {
+        let qword = ((u & 0x3fff) as u128) | (((v & 0x3fff) as u128) << 32);
+        self.push(RegDesc::UV, qword)
+    }
+
+    fn xyz_qword(xyz: Xyz, adc: bool) -> u128 {
+        (xyz.x() as u128)
+            | ((xyz.y() as u128) << 32)
+            | ((xyz.z() as u128) << 64)
+            | ((adc as u128) << 111)
+    }
+
+    fn xyzf_qword(xyzf: Xyzf, adc: bool) -> u128 {
+        (xyzf.x() as u128)
+            | ((xyzf.y() as u128) << 32)
+            | ((xyzf.z() as u128) << 68)
+            | ((xyzf.f() as u128) << 100)
+            | ((adc as u128) << 111)
+    }
+
+    /// Vertex with drawing kick, unless `adc` is set.
+    pub fn xyz2(&mut self, xyz: Xyz, adc: bool) -> &mut Self {
+        self.push(RegDesc::XYZ2, Self::xyz_qword(xyz, adc))
+    }
+
+    /// Vertex with fog and drawing kick, unless `adc` is set.
+    pub fn xyzf2(&mut self, xyzf: Xyzf, adc: bool) -> &mut Self {
+        self.push(RegDesc::XYZF2, Self::xyzf_qword(xyzf, adc))
+    }
+
+    /// Vertex without drawing kick.
+    pub fn xyz3(&mut self, xyz: Xyz) -> &mut Self {
+        self.push(RegDesc::XYZ3, Self::xyz_qword(xyz, false))
+    }
+
+    /// Vertex with fog without drawing kick.
+    pub fn xyzf3(&mut self, xyzf: Xyzf) -> &mut Self {
+        self.push(RegDesc::XYZF3, Self::xyzf_qword(xyzf, false))
+    }
+
+    pub fn tex0(&mut self, ctx: Context, tex0: Tex0) -> &mut Self {
+        let desc = match ctx {
+            Context::One => RegDesc::TEX0_1,
+            Context::Two => RegDesc::TEX0_2,
+        };
+        self.push(desc, tex0.bits() as u128)
+    }
+
+    pub fn clamp(&mut self, ctx: Context, clamp: Clamp) -> &mut Self {
+        let desc = match ctx {
+            Context::One => RegDesc::CLAMP_1,
+            Context::Two => RegDesc::CLAMP_2,
+        };
+        self.push(desc, clamp.bits() as u128)
+    }
+
+    pub fn fog(&mut self, f: u8) -> &mut Self {
+        self.push(RegDesc::FOG, (f as u128) << 100)
+    }
+
+    /// Raw write to any general purpose register.
+    pub fn a_d(&mut self, addr: RegAddr, data: u64) -> &mut Self {
+        self.push(RegDesc::A_D, (data as u128) | ((addr.0 as u128) << 64))
+    }
+
+    pub fn nop(&mut self) -> &mut Self {
+        self.push(RegDesc::NOP, 0)
+    }
+}
+
+impl Drop for PackedList<'_> {
+    fn drop(&mut self) {
+        let count = self.count();
+        let tag_idx = self.start - 1;
+
+        if count == 0 {
+            // Nothing was written, remove the tag altogether
+            self.packet.data.truncate(tag_idx);
+            self.packet.last_tag = self.prev_tag;
+            if let Some(prev) = self.prev_tag {
+                let prev_tag = GifTag(self.packet.data[prev]).with_eop(true);
+                self.packet.data[prev] = prev_tag.bits();
+            }
+            return;
+        }
+
+        let nreg = self.tag.desc_count();
+        debug_assert!(
+            count % nreg == 0,
+            "packed list ended in the middle of a loop"
+        );
+
+        let nloop = count / nreg;
+        assert!(nloop <= MAX_NLOOP, "packed list too long");
+
+        let tag = GifTag(self.packet.data[tag_idx]).with_nloop(nloop as u16);
+        self.packet.data[tag_idx] = tag.bits();
+    }
+}
+
+/// A PACKED mode list of A+D register writes.
+#[derive(Debug)]
+pub struct AdList<'a>(PackedList<'a>);
+
+impl AdList<'_> {
+    pub fn reg<R: GsReg>(&mut self, reg: R) -> &mut Self {
+        self.raw(R::ADDR, reg.into_raw())
+    }
+
+    pub fn ctx_reg<R: GsCtxReg>(&mut self, ctx: Context, reg: R) -> &mut Self {
+        self.raw(R::addr(ctx), reg.into_raw())
+    }
+
+    pub fn raw(&mut self, addr: RegAddr, data: u64) -> &mut Self {
+        self.0.a_d(addr, data);
+        self
+    }
+}
+
+/// A REGLIST mode list, the tag's NLOOP is fixed up and the data padded on drop.
+#[derive(Debug)]
+pub struct RegList<'a> {
+    packet: &'a mut Packet,
+    tag: GifTag,
+    tag_idx: usize,
+    count: usize,
+    prev_tag: Option<usize>,
+}
+
+impl<'a> RegList<'a> {
+    fn new(packet: &'a mut Packet, tag: GifTag) -> Self {
+        let prev_tag = packet.last_tag;
+        let tag_idx = packet.data.len();
+        packet.tag(tag);
+
+        Self {
+            packet,
+            tag,
+            tag_idx,
+            count: 0,
+            prev_tag,
+        }
+    }
+
+    /// Write the next register, in descriptor order.
+    pub fn push(&mut self, data: u64) -> &mut Self {
+        if self.count % 2 == 0 {
+            self.packet.data.push(data as u128);
+        } else {
+            let last = self.packet.data.last_mut().unwrap();
+            *last |= (data as u128) << 64;
+        }
+
+        self.count += 1;
+        self
+    }
+
+    pub fn reg<R: RegValue<Raw = u64>>(&mut self, reg: R) -> &mut Self {
+        self.push(reg.into_raw())
+    }
+}
+
+impl Drop for RegList<'_> {
+    fn drop(&mut self) {
+        if self.count == 0 {
+            self.packet.data.truncate(self.tag_idx);
+            self.packet.last_tag = self.prev_tag;
+            if let Some(prev) = self.prev_tag {
+                let prev_tag = GifTag(self.packet.data[prev]).with_eop(true);
+                self.packet.data[prev] = prev_tag.bits();
+            }
+            return;
+        }
+
+        let nreg = self.tag.desc_count();
+        debug_assert!(
+            self.count % nreg == 0,
+            "register list ended in the middle of a loop"
+        );
+
+        // An odd number of registers is padded by the zeroed upper half of the last quadword
+        let nloop = self.count / nreg;
+        assert!(nloop <= MAX_NLOOP, "register list too long");
+
+        let tag = GifTag(self.packet.data[self.tag_idx]).with_nloop(nloop as u16);
+        self.packet.data[self.tag_idx] = tag.bits();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gs::general::{Frame, PrimType};
+
+    #[test]
+    fn test_gif_a_d() {
+        let mut packet = Packet::new();
+        packet
+            .a_d()
+            .ctx_reg(Context::One, Frame::new().with_fbw(10).with_psm(Psm::CT32));
+
+        #[rustfmt::skip]
+        let expected: [u8; 32] = [
+            // GIF tag: NLOOP=1, EOP, PACKED, NREG=1, REGS=A+D
+            0x01, 0x80, 0x00, 0x00, 0x00, 0x00, 0x00, 0x10,
+            0x0e, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            // FRAME_1: FBW=10
+            0x00, 0x00, 0x0a, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x4c, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        ];
+
+        assert_eq!(packet.as_bytes(), &expected);
+    }
+
+    #[test]
+    fn test_gif_packed() {
+        let mut packet = Packet::new();
+        packet
+            .packed(
+                Some(Prim::new().with_prim(PrimType::SPRITE)),
+                &[RegDesc::RGBAQ, RegDesc::XYZ2, RegDesc::XYZ2],
+            )
+            .rgba(0x10, 0x20, 0x30, 0x80)
+            .xyz2(Xyz::new().with_x(0x100).with_y(0x200), false)
+            .xyz2(Xyz::new().with_x(0x300).with_y(0x400), false);
+
+        assert_eq!(
+            packet.as_slice(),
+            &[
+                0x0000_0000_0000_0551_3003_4000_0000_8001,
+                0x0000_0080_0000_0030_0000_0020_0000_0010,
+                0x0000_0000_0000_0000_0000_0200_0000_0100,
+                0x0000_0000_0000_0000_0000_0400_0000_0300,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_gif_reglist_and_image() {
+        let mut packet = Packet::new();
+        packet
+            .reglist(&[RegDesc::PRIM, RegDesc::RGBAQ, RegDesc::XYZ2])
+            .push(0x1)
+            .push(0x2)
+            .push(0x3);
+        packet.image(&[0xff; 20]);
+
+        let data = packet.as_slice();
+        assert_eq!(data.len(), 6);
+
+        // The first tag lost its EOP bit, while the image tag got it
+        assert!(!GifTag(data[0]).eop());
+        assert_eq!(GifTag(data[0]).nloop(), 1);
+        assert!(GifTag(data[3]).eop());
+        assert_eq!(GifTag(data[3]).nloop(), 2);
+
+        // Odd register count is padded with zeroes
+        assert_eq!(data[1], 0x2_0000_0000_0000_0001);
+        assert_eq!(data[2], 0x3);
+
+        // Image data is padded with zeroes
+        assert_eq!(data[4], u128::MAX);
+        assert_eq!(data[5], 0xffff_ffff);
+    }
+}
@@ -0,0 +1,318 @@
+//! GS privileged registers.
+
+use crate::reg::{bitfield, field_enum, Reg, RW, RW1C, W};
+
+pub mod general;
+
+field_enum! {
+    /// Pixel storage format.
+    pub struct Psm(u8) {
+        CT32 = 0x00;
+        CT24 = 0x01;
+        CT16 = 0x02;
+        CT16S = 0x0a;
+        T8 = 0x13;
+        T4 = 0x14;
+        T8H = 0x1b;
+        T4HL = 0x24;
+        T4HH = 0x2c;
+        Z32 = 0x30;
+        Z24 = 0x31;
+        Z16 = 0x32;
+        Z16S = 0x3a;
+    }
+}
+
+impl Psm {
+    /// Size of a single pixel in GS memory, in bits.
+    pub fn bits_per_pixel(self) -> u32 {
+        match self {
+            Self::CT32 | Self::CT24 | Self::Z32 | Self::Z24 => 32,
+            Self::CT16 | Self::CT16S | Self::Z16 | Self::Z16S => 16,
+            Self::T8 | Self::T8H => 8,
+            Self::T4 | Self::T4HL | Self::T4HH => 4,
+            _ => 32,
+        }
+    }
+}
+
+field_enum! {
+    /// Blending value selection for the PCRTC merge circuit.
+    pub struct AlphaSelect(u8) {
+        /// Use the alpha value of read circuit 1.
+        CIRCUIT1 = 0;
+        /// Use the value of `PMODE.ALP`.
+        FIXED = 1;
+    }
+}
+
+bitfield! {
+    /// PCRTC mode setting.
+    pub struct Pmode(u64) {
+        /// Enable read circuit 1.
+        en1, with_en1: bool = 0;
+        /// Enable read circuit 2.
+        en2, with_en2: bool = 1;
+        /// CRT output switching, must always be 1.
+        crtmd, with_crtmd: u8 = 2..=4;
+        /// Alpha value selection.
+        mmod, with_mmod: AlphaSelect = 5;
+        /// Output alpha value selection, `false` for read circuit 1 and `true` for circuit 2.
+        amod, with_amod: bool = 6;
+        /// Blend with the background color instead of read circuit 2.
+        slbg, with_slbg: bool = 7;
+        /// Fixed alpha value (0xff = 1.0).
+        alp, with_alp: u8 = 8..=15;
+    }
+}
+
+bitfield! {
+    /// PCRTC clock and sync settings, usually left to `set_gs_crt`.
+    pub struct Smode1(u64) {
+        rc, with_rc: u8 = 0..=2;
+        lc, with_lc: u8 = 3..=9;
+        t1248, with_t1248: u8 = 10..=11;
+        slck, with_slck: bool = 12;
+        cmod, with_cmod: u8 = 13..=14;
+        ex, with_ex: bool = 15;
+        prst, with_prst: bool = 16;
+        sint, with_sint: bool = 17;
+        xpck, with_xpck: bool = 18;
+        pck2, with_pck2: u8 = 19..=20;
+        spml, with_spml: u8 = 21..=24;
+        gcont, with_gcont: bool = 25;
+        phs, with_phs: bool = 26;
+        pvs, with_pvs: bool = 27;
+        pehs, with_pehs: bool = 28;
+        pevs, with_pevs: bool = 29;
+        clksel, with_clksel: u8 = 30..=31;
+        nvck, with_nvck: bool = 32;
+        slck2, with_slck2: bool = 33;
+        vcksel, with_vcksel: u8 = 34..=35;
+        vhp, with_vhp: bool = 36;
+    }
+}
+
+field_enum! {
+    /// Power saving mode for VESA DPMS.
+    pub struct Dpms(u8) {
+        ON = 0;
+        STANDBY = 1;
+        SUSPEND = 2;
+        OFF = 3;
+    }
+}
+
+bitfield! {
+    /// Video synchronization mode.
+    pub struct Smode2(u64) {
+        /// Enable interlaced mode.
+        int, with_int: bool = 0;
+        /// In interlaced mode, `false` reads every other line (field mode) and `true` reads
+        /// every line (frame mode).
+        ffmd, with_ffmd: bool = 1;
+        /// VESA DPMS mode.
+        dpms, with_dpms: Dpms = 2..=3;
+    }
+}
+
+bitfield! {
+    /// DRAM refresh settings.
+    pub struct Srfsh(u64) {
+        rfsh, with_rfsh: u8 = 0..=3;
+    }
+}
+
+bitfield! {
+    /// Horizontal synchronization timing settings.
+    pub struct Synch1(u64) {
+        hfp, with_hfp: u16 = 0..=10;
+        hbp, with_hbp: u16 = 11..=21;
+        hseq, with_hseq: u16 = 22..=31;
+        hsvs, with_hsvs: u16 = 32..=42;
+        hs, with_hs: u16 = 43..=53;
+    }
+}
+
+bitfield! {
+    /// Horizontal synchronization timing settings.
+    pub struct Synch2(u64) {
+        hf, with_hf: u16 = 0..=10;
+        hb, with_hb: u16 = 11..=21;
+    }
+}
+
+bitfield! {
+    /// Vertical synchronization timing settings.
+    pub struct Syncv(u64) {
+        vfp, with_vfp: u16 = 0..=9;
+        vfpe, with_vfpe: u16 = 10..=19;
+        vbp, with_vbp: u16 = 20..=31;
+        vbpe, with_vbpe: u16 = 32..=41;
+        vdp, with_vdp: u16 = 42..=52;
+        vs, with_vs: u16 = 53..=63;
+    }
+}
+
+bitfield! {
+    /// Frame buffer settings for a read circuit.
+    pub struct Dispfb(u64) {
+        /// Base pointer, in units of 2048 words.
+        fbp, with_fbp: u16 = 0..=8;
+        /// Buffer width, in units of 64 pixels.
+        fbw, with_fbw: u8 = 9..=14;
+        /// Pixel storage format.
+        psm, with_psm: Psm = 15..=19;
+        /// X coordinate of the upper left point of the display area.
+        dbx, with_dbx: u16 = 32..=42;
+        /// Y coordinate of the upper left point of the display area.
+        dby, with_dby: u16 = 43..=53;
+    }
+}
+
+bitfield! {
+    /// Display area settings for a read circuit.
+    pub struct Display(u64) {
+        /// X position of the display area, in VCK units.
+        dx, with_dx: u16 = 0..=11;
+        /// Y position of the display area, in raster units.
+        dy, with_dy: u16 = 12..=22;
+        /// Horizontal magnification minus one.
+        magh, with_magh: u8 = 23..=26;
+        /// Vertical magnification minus one.
+        magv, with_magv: u8 = 27..=28;
+        /// Width of the display area minus one, in VCK units.
+        dw, with_dw: u16 = 32..=43;
+        /// Height of the display area minus one, in pixels.
+        dh, with_dh: u16 = 44..=54;
+    }
+}
+
+bitfield! {
+    /// Feedback write buffer settings.
+    pub struct Extbuf(u64) {
+        exbp, with_exbp: u16 = 0..=13;
+        exbw, with_exbw: u8 = 14..=19;
+        fbin, with_fbin: u8 = 20..=21;
+        wffmd, with_wffmd: bool = 22;
+        emoda, with_emoda: u8 = 23..=24;
+        emodc, with_emodc: u8 = 25..=26;
+        wdx, with_wdx: u16 = 32..=42;
+        wdy, with_wdy: u16 = 43..=53;
+    }
+}
+
+bitfield! {
+    /// Feedback write input data settings.
+    pub struct Extdata(u64) {
+        sx, with_sx: u16 = 0..=11;
+        sy, with_sy: u16 = 12..=22;
+        smph, with_smph: u8 = 23..=26;
+        smpv, with_smpv: u8 = 27..=28;
+        ww, with_ww: u16 = 32..=43;
+        wh, with_wh: u16 = 44..=54;
+    }
+}
+
+bitfield! {
+    /// Feedback write control.
+    pub struct Extwrite(u64) {
+        write, with_write: bool = 0;
+    }
+}
+
+bitfield! {
+    /// Background color of the merge circuit.
+    pub struct Bgcolor(u64) {
+        r, with_r: u8 = 0..=7;
+        g, with_g: u8 = 8..=15;
+        b, with_b: u8 = 16..=23;
+    }
+}
+
+bitfield! {
+    /// System status and reset.
+    ///
+    /// The event bits (`SIGNAL`, `FINISH`, `HSINT`, `VSINT` and `EDWINT`) are cleared by writing
+    /// 1 to them, write a value built from [`Csr::new`] with only the bits to act on.
+    pub struct Csr(u64) {
+        signal, with_signal: bool = 0;
+        finish, with_finish: bool = 1;
+        hsint, with_hsint: bool = 2;
+        vsint, with_vsint: bool = 3;
+        edwint, with_edwint: bool = 4;
+        flush, with_flush: bool = 8;
+        reset, with_reset: bool = 9;
+        /// Set when a new field has started since the last VSync.
+        nfield, with_nfield: bool = 12;
+        /// Currently displayed field, `false` for even and `true` for odd.
+        field, with_field: bool = 13;
+        fifo, with_fifo: u8 = 14..=15;
+        rev, with_rev: u8 = 16..=23;
+        id, with_id: u8 = 24..=31;
+    }
+}
+
+bitfield! {
+    /// Interrupt mask, a set bit masks the interrupt.
+    ///
+    /// The kernel keeps a shadow copy of this register, prefer `gs_put_imr` over direct writes.
+    pub struct Imr(u64) {
+        sigmsk, with_sigmsk: bool = 8;
+        finishmsk, with_finishmsk: bool = 9;
+        hsmsk, with_hsmsk: bool = 10;
+        vsmsk, with_vsmsk: bool = 11;
+        edwmsk, with_edwmsk: bool = 12;
+        /// Reserved bits, must always be set.
+        reserved, with_reserved: u8 = 13..=14;
+    }
+}
+
+impl Imr {
+    /// All interrupts masked.
+    pub fn all_masked() -> Self {
+        Self::new()
+            .with_sigmsk(true)
+            .with_finishmsk(true)
+            .with_hsmsk(true)
+            .with_vsmsk(true)
+            .with_edwmsk(true)
+            .with_reserved(3)
+    }
+}
+
+bitfield! {
+    /// Host interface bus direction.
+    pub struct Busdir(u64) {
+        /// `false` for host to local and `true` for local to host.
+        dir, with_dir: bool = 0;
+    }
+}
+
+bitfield! {
+    /// Signal and label id.
+    pub struct Siglblid(u64) {
+        sigid, with_sigid: u32 = 0..=31;
+        lblid, with_lblid: u32 = 32..=63;
+    }
+}
+
+pub const PMODE: Reg<Pmode, W> = unsafe { Reg::new(0x1200_0000) };
+pub const SMODE1: Reg<Smode1, W> = unsafe { Reg::new(0x1200_0010) };
+pub const SMODE2: Reg<Smode2, W> = unsafe { Reg::new(0x1200_0020) };
+pub const SRFSH: Reg<Srfsh, W> = unsafe { Reg::new(0x1200_0030) };
+pub const SYNCH1: Reg<Synch1, W> = unsafe { Reg::new(0x1200_0040) };
+pub const SYNCH2: Reg<Synch2, W> = unsafe { Reg::new(0x1200_0050) };
+pub const SYNCV: Reg<Syncv, W> = unsafe { Reg::new(0x1200_0060) };
+pub const DISPFB1: Reg<Dispfb, W> = unsafe { Reg::new(0x1200_0070) };
+pub const DISPLAY1: Reg<Display, W> = unsafe { Reg::new(0x1200_0080) };
+pub const DISPFB2: Reg<Dispfb, W> = unsafe { Reg::new(0x1200_0090) };
+pub const DISPLAY2: Reg<Display, W> = unsafe { Reg::new(0x1200_00a0) };
+pub const EXTBUF: Reg<Extbuf, W> = unsafe { Reg::new(0x1200_00b0) };
+pub const EXTDATA: Reg<Extdata, W> = unsafe { Reg::new(0x1200_00c0) };
+pub const EXTWRITE: Reg<Extwrite, W> = unsafe { Reg::new(0x1200_00d0) };
+pub const BGCOLOR: Reg<Bgcolor, W> = unsafe { Reg::new(0x1200_00e0) };
+pub const CSR: Reg<Csr, RW1C> = unsafe { Reg::new(0x1200_1000) };
+pub const IMR: Reg<Imr, W> = unsafe { Reg::new(0x1200_1010) };
+pub const BUSDIR: Reg<Busdir, W> = unsafe { Reg::new(0x1200_1040) };
+pub const SIGLBLID: Reg<Siglblid, RW> = unsafe { Reg::new(0x1200_1080) };
@@ -0,0 +1,589 @@
+//! GS general purpose registers, written through the GIF.
+
+use crate::gs::Psm;
+use crate::reg::{bitfield, field_enum, RegValue};
+
+field_enum! {
+    /// General purpose register address, as used by A+D writes.
+    pub struct RegAddr(u8) {
+        PRIM = 0x00;
+        RGBAQ = 0x01;
+        ST = 0x02;
+        UV = 0x03;
+        XYZF2 = 0x04;
+        XYZ2 = 0x05;
+        TEX0_1 = 0x06;
+        TEX0_2 = 0x07;
+        CLAMP_1 = 0x08;
+        CLAMP_2 = 0x09;
+        FOG = 0x0a;
+        XYZF3 = 0x0c;
+        XYZ3 = 0x0d;
+        TEX1_1 = 0x14;
+        TEX1_2 = 0x15;
+        TEX2_1 = 0x16;
+        TEX2_2 = 0x17;
+        XYOFFSET_1 = 0x18;
+        XYOFFSET_2 = 0x19;
+        PRMODECONT = 0x1a;
+        PRMODE = 0x1b;
+        TEXCLUT = 0x1c;
+        SCANMSK = 0x22;
+        MIPTBP1_1 = 0x34;
+        MIPTBP1_2 = 0x35;
+        MIPTBP2_1 = 0x36;
+        MIPTBP2_2 = 0x37;
+        TEXA = 0x3b;
+        FOGCOL = 0x3d;
+        TEXFLUSH = 0x3f;
+        SCISSOR_1 = 0x40;
+        SCISSOR_2 = 0x41;
+        ALPHA_1 = 0x42;
+        ALPHA_2 = 0x43;
+        DIMX = 0x44;
+        DTHE = 0x45;
+        COLCLAMP = 0x46;
+        TEST_1 = 0x47;
+        TEST_2 = 0x48;
+        PABE = 0x49;
+        FBA_1 = 0x4a;
+        FBA_2 = 0x4b;
+        FRAME_1 = 0x4c;
+        FRAME_2 = 0x4d;
+        ZBUF_1 = 0x4e;
+        ZBUF_2 = 0x4f;
+        BITBLTBUF = 0x50;
+        TRXPOS = 0x51;
+        TRXREG = 0x52;
+        TRXDIR = 0x53;
+        HWREG = 0x54;
+        SIGNAL = 0x60;
+        FINISH = 0x61;
+        LABEL = 0x62;
+    }
+}
+
+/// Drawing environment context.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Context {
+    #[default]
+    One,
+    Two,
+}
+
+/// A general purpose register with a single address.
+pub trait GsReg: RegValue<Raw = u64> {
+    const ADDR: RegAddr;
+}
+
+/// A general purpose register with one address per drawing context.
+pub trait GsCtxReg: RegValue<Raw = u64> {
+    const ADDR_1: RegAddr;
+    const ADDR_2: RegAddr;
+
+    fn addr(ctx: Context) -> RegAddr {
+        match ctx {
+            Context::One => Self::ADDR_1,
+            Context::Two => Self::ADDR_2,
+        }
+    }
+}
+
+macro_rules! impl_gs_reg {
+    ($($ty:ty => $addr:ident;)*) => {$(
+        impl GsReg for $ty {
+            const ADDR: RegAddr = RegAddr::$addr;
+        }
+    )*};
+}
+
+macro_rules! impl_gs_ctx_reg {
+    ($($ty:ty => $addr1:ident, $addr2:ident;)*) => {$(
+        impl GsCtxReg for $ty {
+            const ADDR_1: RegAddr = RegAddr::$addr1;
+            const ADDR_2: RegAddr = RegAddr::$addr2;
+        }
+    )*};
+}
+
+field_enum! {
+    /// Primitive type.
+    pub struct PrimType(u8) {
+        POINT = 0;
+        LINE = 1;
+        LINE_STRIP = 2;
+        TRIANGLE = 3;
+        TRIANGLE_STRIP = 4;
+        TRIANGLE_FAN = 5;
+        SPRITE = 6;
+    }
+}
+
+bitfield! {
+    /// Drawing primitive setting, also used by the PRIM field of GIF tags.
+    pub struct Prim(u64) {
+        prim, with_prim: PrimType = 0..=2;
+        /// Gouraud shading.
+        iip, with_iip: bool = 3;
+        /// Texture mapping.
+        tme, with_tme: bool = 4;
+        /// Fogging.
+        fge, with_fge: bool = 5;
+        /// Alpha blending.
+        abe, with_abe: bool = 6;
+        /// Antialiasing.
+        aa1, with_aa1: bool = 7;
+        /// Use UV instead of STQ for texture coordinates.
+        fst, with_fst: bool = 8;
+        /// Use drawing context 2.
+        ctxt, with_ctxt: bool = 9;
+        /// Fix fragment value control.
+        fix, with_fix: bool = 10;
+    }
+}
+
+bitfield! {
+    /// Primitive attributes, used when `PRMODECONT.AC` is cleared.
+    pub struct Prmode(u64) {
+        iip, with_iip: bool = 3;
+        tme, with_tme: bool = 4;
+        fge, with_fge: bool = 5;
+        abe, with_abe: bool = 6;
+        aa1, with_aa1: bool = 7;
+        fst, with_fst: bool = 8;
+        ctxt, with_ctxt: bool = 9;
+        fix, with_fix: bool = 10;
+    }
+}
+
+bitfield! {
+    /// Primitive attribute source.
+    pub struct Prmodecont(u64) {
+        /// `true` to use the attributes in PRIM, `false` for PRMODE.
+        ac, with_ac: bool = 0;
+    }
+}
+
+bitfield! {
+    /// Vertex color and texture coordinate normalization factor.
+    pub struct Rgbaq(u64) {
+        r, with_r: u8 = 0..=7;
+        g, with_g: u8 = 8..=15;
+        b, with_b: u8 = 16..=23;
+        a, with_a: u8 = 24..=31;
+        q, with_q: f32 = 32..=63;
+    }
+}
+
+impl Rgbaq {
+    pub fn rgba(r: u8, g: u8, b: u8, a: u8) -> Self {
+        Self::new()
+            .with_r(r)
+            .with_g(g)
+            .with_b(b)
+            .with_a(a)
+            .with_q(1.0)
+    }
+}
+
+bitfield! {
+    /// Vertex texture coordinates, as floating point values.
+    pub struct St(u64) {
+        s, with_s: f32 = 0..=31;
+        t, with_t: f32 = 32..=63;
+    }
+}
+
+bitfield! {
+    /// Vertex texel coordinates, as 10.4 fixed point values.
+    pub struct Uv(u64) {
+        u, with_u: u16 = 0..=13;
+        v, with_v: u16 = 16..=29;
+    }
+}
+
+bitfield! {
+    /// Vertex coordinates, used by XYZ2 and XYZ3.
+    ///
+    /// `x` and `y` are 12.4 fixed point values in the primitive coordinate system.
+    pub struct Xyz(u64) {
+        x, with_x: u16 = 0..=15;
+        y, with_y: u16 = 16..=31;
+        z, with_z: u32 = 32..=63;
+    }
+}
+
+bitfield! {
+    /// Vertex coordinates and fog coefficient, used by XYZF2 and XYZF3.
+    pub struct Xyzf(u64) {
+        x, with_x: u16 = 0..=15;
+        y, with_y: u16 = 16..=31;
+        z, with_z: u32 = 32..=55;
+        f, with_f: u8 = 56..=63;
+    }
+}
+
+field_enum! {
+    /// Texture function.
+    pub struct TextureFunction(u8) {
+        MODULATE = 0;
+        DECAL = 1;
+        HIGHLIGHT = 2;
+        HIGHLIGHT2 = 3;
+    }
+}
+
+bitfield! {
+    /// Texture information.
+    pub struct Tex0(u64) {
+        /// Texture base pointer, in units of 64 words.
+        tbp0, with_tbp0: u16 = 0..=13;
+        /// Texture buffer width, in units of 64 texels.
+        tbw, with_tbw: u8 = 14..=19;
+        psm, with_psm: Psm = 20..=25;
+        /// Texture width, as a power of 2.
+        tw, with_tw: u8 = 26..=29;
+        /// Texture height, as a power of 2.
+        th, with_th: u8 = 30..=33;
+        /// Use the texture alpha channel.
+        tcc, with_tcc: bool = 34;
+        tfx, with_tfx: TextureFunction = 35..=36;
+        /// CLUT base pointer, in units of 64 words.
+        cbp, with_cbp: u16 = 37..=50;
+        cpsm, with_cpsm: Psm = 51..=54;
+        /// CLUT storage mode, `false` for CSM1 and `true` for CSM2.
+        csm, with_csm: bool = 55;
+        /// CLUT entry offset.
+        csa, with_csa: u8 = 56..=60;
+        /// CLUT buffer load control.
+        cld, with_cld: u8 = 61..=63;
+    }
+}
+
+bitfield! {
+    /// Texture wrap mode.
+    pub struct Clamp(u64) {
+        wms, with_wms: u8 = 0..=1;
+        wmt, with_wmt: u8 = 2..=3;
+        minu, with_minu: u16 = 4..=13;
+        maxu, with_maxu: u16 = 14..=23;
+        minv, with_minv: u16 = 24..=33;
+        maxv, with_maxv: u16 = 34..=43;
+    }
+}
+
+bitfield! {
+    /// Texture filtering and LOD settings.
+    pub struct Tex1(u64) {
+        lcm, with_lcm: bool = 0;
+        mxl, with_mxl: u8 = 2..=4;
+        mmag, with_mmag: bool = 5;
+        mmin, with_mmin: u8 = 6..=8;
+        mtba, with_mtba: bool = 9;
+        l, with_l: u8 = 19..=20;
+        k, with_k: u16 = 32..=43;
+    }
+}
+
+bitfield! {
+    /// Vertex fog coefficient.
+    pub struct Fog(u64) {
+        f, with_f: u8 = 56..=63;
+    }
+}
+
+bitfield! {
+    /// Distant fog color.
+    pub struct Fogcol(u64) {
+        fcr, with_fcr: u8 = 0..=7;
+        fcg, with_fcg: u8 = 8..=15;
+        fcb, with_fcb: u8 = 16..=23;
+    }
+}
+
+bitfield! {
+    /// Offset from the primitive to the window coordinate system, as 12.4 fixed point values.
+    pub struct Xyoffset(u64) {
+        ofx, with_ofx: u16 = 0..=15;
+        ofy, with_ofy: u16 = 32..=47;
+    }
+}
+
+bitfield! {
+    /// Texture alpha expansion for 24 and 16 bit formats.
+    pub struct Texa(u64) {
+        ta0, with_ta0: u8 = 0..=7;
+        aem, with_aem: bool = 15;
+        ta1, with_ta1: u8 = 32..=39;
+    }
+}
+
+bitfield! {
+    /// Texture page buffer flush, any write flushes the buffer.
+    pub struct Texflush(u64) {}
+}
+
+bitfield! {
+    /// Scissoring area, in window coordinates.
+    pub struct Scissor(u64) {
+        scax0, with_scax0: u16 = 0..=10;
+        scax1, with_scax1: u16 = 16..=26;
+        scay0, with_scay0: u16 = 32..=42;
+        scay1, with_scay1: u16 = 48..=58;
+    }
+}
+
+impl Scissor {
+    pub fn rect(x0: u16, y0: u16, x1: u16, y1: u16) -> Self {
+        Self::new()
+            .with_scax0(x0)
+            .with_scay0(y0)
+            .with_scax1(x1)
+            .with_scay1(y1)
+    }
+}
+
+field_enum! {
+    /// Alpha blending color input, for the `a`, `b` and `d` terms.
+    pub struct BlendColor(u8) {
+        SOURCE = 0;
+        DEST = 1;
+        ZERO = 2;
+    }
+}
+
+field_enum! {
+    /// Alpha blending alpha input, for the `c` term.
+    pub struct BlendAlpha(u8) {
+        SOURCE = 0;
+        DEST = 1;
+        FIX = 2;
+    }
+}
+
+bitfield! {
+    /// Alpha blending, computes `((a - b) * c >> 7) + d`.
+    pub struct Alpha(u64) {
+        a, with_a: BlendColor = 0..=1;
+        b, with_b: BlendColor = 2..=3;
+        c, with_c: BlendAlpha = 4..=5;
+        d, with_d: BlendColor = 6..=7;
+        fix, with_fix: u8 = 32..=39;
+    }
+}
+
+bitfield! {
+    /// Dither control.
+    pub struct Dthe(u64) {
+        dthe, with_dthe: bool = 0;
+    }
+}
+
+bitfield! {
+    /// Color clamp control, `false` masks the lower 8 bits and `true` clamps to 0-255.
+    pub struct Colclamp(u64) {
+        clamp, with_clamp: bool = 0;
+    }
+}
+
+field_enum! {
+    /// Alpha test method.
+    pub struct AlphaTest(u8) {
+        NEVER = 0;
+        ALWAYS = 1;
+        LESS = 2;
+        LEQUAL = 3;
+        EQUAL = 4;
+        GEQUAL = 5;
+        GREATER = 6;
+        NOTEQUAL = 7;
+    }
+}
+
+field_enum! {
+    /// Processing method when the alpha test fails.
+    pub struct AlphaFail(u8) {
+        KEEP = 0;
+        FB_ONLY = 1;
+        ZB_ONLY = 2;
+        RGB_ONLY = 3;
+    }
+}
+
+field_enum! {
+    /// Depth test method.
+    pub struct DepthTest(u8) {
+        NEVER = 0;
+        ALWAYS = 1;
+        GEQUAL = 2;
+        GREATER = 3;
+    }
+}
+
+bitfield! {
+    /// Pixel test control.
+    pub struct Test(u64) {
+        ate, with_ate: bool = 0;
+        atst, with_atst: AlphaTest = 1..=3;
+        aref, with_aref: u8 = 4..=11;
+        afail, with_afail: AlphaFail = 12..=13;
+        date, with_date: bool = 14;
+        datm, with_datm: bool = 15;
+        zte, with_zte: bool = 16;
+        ztst, with_ztst: DepthTest = 17..=18;
+    }
+}
+
+bitfield! {
+    /// Alpha blending per pixel control.
+    pub struct Pabe(u64) {
+        pabe, with_pabe: bool = 0;
+    }
+}
+
+bitfield! {
+    /// Alpha correction value.
+    pub struct Fba(u64) {
+        fba, with_fba: bool = 0;
+    }
+}
+
+bitfield! {
+    /// Frame buffer settings.
+    pub struct Frame(u64) {
+        /// Base pointer, in units of 2048 words.
+        fbp, with_fbp: u16 = 0..=8;
+        /// Buffer width, in units of 64 pixels.
+        fbw, with_fbw: u8 = 16..=21;
+        psm, with_psm: Psm = 24..=29;
+        /// Drawing mask, set bits are not updated.
+        fbmsk, with_fbmsk: u32 = 32..=63;
+    }
+}
+
+bitfield! {
+    /// Z buffer settings.
+    pub struct Zbuf(u64) {
+        /// Base pointer, in units of 2048 words.
+        zbp, with_zbp: u16 = 0..=8;
+        /// Lower 4 bits of the Z storage format, see [`Zbuf::with_zpsm`].
+        psm, with_psm: u8 = 24..=27;
+        /// Disable Z buffer writes.
+        zmsk, with_zmsk: bool = 32;
+    }
+}
+
+impl Zbuf {
+    #[must_use]
+    pub fn with_zpsm(self, psm: Psm) -> Self {
+        self.with_psm(psm.0 & 0xf)
+    }
+}
+
+bitfield! {
+    /// Transmission buffer settings.
+    pub struct Bitbltbuf(u64) {
+        sbp, with_sbp: u16 = 0..=13;
+        sbw, with_sbw: u8 = 16..=21;
+        spsm, with_spsm: Psm = 24..=29;
+        dbp, with_dbp: u16 = 32..=45;
+        dbw, with_dbw: u8 = 48..=53;
+        dpsm, with_dpsm: Psm = 56..=61;
+    }
+}
+
+bitfield! {
+    /// Transmission area position.
+    pub struct Trxpos(u64) {
+        ssax, with_ssax: u16 = 0..=10;
+        ssay, with_ssay: u16 = 16..=26;
+        dsax, with_dsax: u16 = 32..=42;
+        dsay, with_dsay: u16 = 48..=58;
+        /// Pixel transmission order, for local to local transfers.
+        dir, with_dir: u8 = 59..=60;
+    }
+}
+
+bitfield! {
+    /// Transmission area size.
+    pub struct Trxreg(u64) {
+        rrw, with_rrw: u16 = 0..=11;
+        rrh, with_rrh: u16 = 32..=43;
+    }
+}
+
+field_enum! {
+    /// Transmission direction.
+    pub struct TransferDir(u8) {
+        HOST_TO_LOCAL = 0;
+        LOCAL_TO_HOST = 1;
+        LOCAL_TO_LOCAL = 2;
+        DEACTIVATED = 3;
+    }
+}
+
+bitfield! {
+    /// Transmission activation, must be written last.
+    pub struct Trxdir(u64) {
+        xdir, with_xdir: TransferDir = 0..=1;
+    }
+}
+
+bitfield! {
+    /// SIGNAL event.
+    pub struct Signal(u64) {
+        id, with_id: u32 = 0..=31;
+        idmsk, with_idmsk: u32 = 32..=63;
+    }
+}
+
+bitfield! {
+    /// FINISH event, any write raises it once all drawing is done.
+    pub struct Finish(u64) {}
+}
+
+bitfield! {
+    /// LABEL event.
+    pub struct Label(u64) {
+        id, with_id: u32 = 0..=31;
+        idmsk, with_idmsk: u32 = 32..=63;
+    }
+}
+
+impl_gs_reg! {
+    Prim => PRIM;
+    Rgbaq => RGBAQ;
+    St => ST;
+    Uv => UV;
+    Xyzf => XYZF2;
+    Xyz => XYZ2;
+    Fog => FOG;
+    Prmodecont => PRMODECONT;
+    Prmode => PRMODE;
+    Texa => TEXA;
+    Fogcol => FOGCOL;
+    Texflush => TEXFLUSH;
+    Dthe => DTHE;
+    Colclamp => COLCLAMP;
+    Pabe => PABE;
+    Bitbltbuf => BITBLTBUF;
+    Trxpos => TRXPOS;
+    Trxreg => TRXREG;
+    Trxdir => TRXDIR;
+    Signal => SIGNAL;
+    Finish => FINISH;
+    Label => LABEL;
+}
+
+impl_gs_ctx_reg! {
+    Tex0 => TEX0_1, TEX0_2;
+    Clamp => CLAMP_1, CLAMP_2;
+    Tex1 => TEX1_1, TEX1_2;
+    Xyoffset => XYOFFSET_1, XYOFFSET_2;
+    Scissor => SCISSOR_1, SCISSOR_2;
+    Alpha => ALPHA_1, ALPHA_2;
+    Test => TEST_1, TEST_2;
+    Fba => FBA_1, FBA_2;
+    Frame => FRAME_1, FRAME_2;
+    Zbuf => ZBUF_1, ZBUF_2;
+}
@@ -1 +1,10 @@
 #![no_std]
+extern crate alloc;
+
+pub mod reg;
+
+pub mod dmac;
+pub mod gif;
+pub mod gs;
+pub mod sio;
+pub mod timer;
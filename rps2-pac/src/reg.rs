@@ -0,0 +1,331 @@
+use core::fmt::{self, Debug};
+use core::marker::PhantomData;
+use core::ptr;
+
+/// Marker for read-only registers.
+#[derive(Debug, Clone, Copy)]
+pub struct R;
+
+/// Marker for write-only registers.
+#[derive(Debug, Clone, Copy)]
+pub struct W;
+
+/// Marker for read-write registers.
+#[derive(Debug, Clone, Copy)]
+pub struct RW;
+
+/// Marker for read-write registers holding flags cleared by writing 1 to them.
+///
+/// These have no [`Reg::modify`], as writing back the value read would clear every pending
+/// flag.
+#[derive(Debug, Clone, Copy)]
+pub struct RW1C;
+
+pub trait Readable {}
+pub trait Writable {}
+
+impl Readable for R {}
+impl Readable for RW {}
+impl Readable for RW1C {}
+impl Writable for W {}
+impl Writable for RW {}
+impl Writable for RW1C {}
+
+/// A value that can be stored inside of a memory mapped register.
+pub trait RegValue: Copy {
+    type Raw: Copy;
+
+    fn from_raw(raw: Self::Raw) -> Self;
+    fn into_raw(self) -> Self::Raw;
+}
+
+macro_rules! impl_reg_value {
+    ($($ty:ty),*) => {$(
+        impl RegValue for $ty {
+            type Raw = $ty;
+
+            #[inline(always)]
+            fn from_raw(raw: $ty) -> Self {
+                raw
+            }
+
+            #[inline(always)]
+            fn into_raw(self) -> $ty {
+                self
+            }
+        }
+    )*};
+}
+
+impl_reg_value!(u8, u16, u32, u64);
+
+/// A value that can be stored inside of a bitfield.
+pub trait Field: Copy {
+    fn from_field(bits: u64) -> Self;
+    fn into_field(self) -> u64;
+}
+
+impl Field for bool {
+    #[inline(always)]
+    fn from_field(bits: u64) -> Self {
+        bits != 0
+    }
+
+    #[inline(always)]
+    fn into_field(self) -> u64 {
+        self as u64
+    }
+}
+
+macro_rules! impl_field {
+    ($($ty:ty),*) => {$(
+        impl Field for $ty {
+            #[inline(always)]
+            fn from_field(bits: u64) -> Self {
+                bits as $ty
+            }
+
+            #[inline(always)]
+            fn into_field(self) -> u64 {
+                self as u64
+            }
+        }
+    )*};
+}
+
+impl_field!(u8, u16, u32, u64);
+
+impl Field for f32 {
+    #[inline(always)]
+    fn from_field(bits: u64) -> Self {
+        f32::from_bits(bits as u32)
+    }
+
+    #[inline(always)]
+    fn into_field(self) -> u64 {
+        self.to_bits() as u64
+    }
+}
+
+/// Handle to a memory mapped register.
+pub struct Reg<T, A = RW> {
+    addr: usize,
+    _marker: PhantomData<(T, A)>,
+}
+
+impl<T, A> Reg<T, A> {
+    /// Create a new register handle.
+    ///
+    /// # Safety
+    /// `addr` must point to a valid hardware register holding a `T`.
+    pub const unsafe fn new(addr: usize) -> Self {
+        Self {
+            addr,
+            _marker: PhantomData,
+        }
+    }
+
+    pub const fn addr(&self) -> usize {
+        self.addr
+    }
+}
+
+impl<T: RegValue, A: Readable> Reg<T, A> {
+    #[inline(always)]
+    pub fn read(&self) -> T {
+        unsafe { T::from_raw(ptr::read_volatile(self.addr as *const T::Raw)) }
+    }
+}
+
+impl<T: RegValue, A: Writable> Reg<T, A> {
+    /// Write a new value to the register.
+    ///
+    /// # Safety
+    /// Writing to hardware registers can have arbitrary side effects, the caller must make sure
+    /// the written value does not violate any invariant the rest of the program relies on.
+    #[inline(always)]
+    pub unsafe fn write(&self, val: T) {
+        ptr::write_volatile(self.addr as *mut T::Raw, val.into_raw());
+    }
+}
+
+impl<T: RegValue + Default, A: Writable> Reg<T, A> {
+    /// Write the register starting from the default value.
+    ///
+    /// # Safety
+    /// See [`Reg::write`].
+    #[inline(always)]
+    pub unsafe fn write_with<F: FnOnce(T) -> T>(&self, f: F) {
+        self.write(f(T::default()));
+    }
+}
+
+impl<T: RegValue> Reg<T, RW> {
+    /// Read the register, modify its value and write it back.
+    ///
+    /// # Safety
+    /// See [`Reg::write`].
+    #[inline(always)]
+    pub unsafe fn modify<F: FnOnce(T) -> T>(&self, f: F) {
+        self.write(f(self.read()));
+    }
+}
+
+impl<T, A> Clone for Reg<T, A> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T, A> Copy for Reg<T, A> {}
+
+impl<T, A> Debug for Reg<T, A> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("Reg")
+            .field(&format_args!("{:#010x}", self.addr))
+            .finish()
+    }
+}
+
+unsafe impl<T, A> Send for Reg<T, A> {}
+unsafe impl<T, A> Sync for Reg<T, A> {}
+
+/// Defines a bitfield over an integer, with a getter and a builder style setter for each field.
+///
+/// Setting a value that does not fit inside of its field triggers a debug assertion.
+macro_rules! bitfield {
+    (
+        $(#[$attr:meta])*
+        $vis:vis struct $name:ident($ty:ty) {
+            $(
+                $(#[$fattr:meta])*
+                $get:ident, $with:ident: $fty:ty = $lo:literal $(..= $hi:literal)?;
+            )*
+        }
+    ) => {
+        $(#[$attr])*
+        #[derive(Clone, Copy, Default, PartialEq, Eq, Hash)]
+        #[repr(transparent)]
+        $vis struct $name(pub $ty);
+
+        #[allow(clippy::identity_op)]
+        impl $name {
+            pub const fn new() -> Self {
+                Self(0)
+            }
+
+            pub const fn from_bits(bits: $ty) -> Self {
+                Self(bits)
+            }
+
+            pub const fn bits(self) -> $ty {
+                self.0
+            }
+
+            $(
+                $(#[$fattr])*
+                #[inline(always)]
+                pub fn $get(self) -> $fty {
+                    const LO: u32 = $lo;
+                    const HI: u32 = bitfield!(@hi $lo $(, $hi)?);
+                    const MASK: $ty = <$ty>::MAX >> (<$ty>::BITS - (HI - LO + 1));
+
+                    $crate::reg::Field::from_field(((self.0 >> LO) & MASK) as u64)
+                }
+
+                $(#[$fattr])*
+                #[inline(always)]
+                #[must_use]
+                pub fn $with(self, val: $fty) -> Self {
+                    const LO: u32 = $lo;
+                    const HI: u32 = bitfield!(@hi $lo $(, $hi)?);
+                    const MASK: $ty = <$ty>::MAX >> (<$ty>::BITS - (HI - LO + 1));
+
+                    let val = $crate::reg::Field::into_field(val) as $ty;
+                    debug_assert!(
+                        val & !MASK == 0,
+                        concat!("value out of range for field ", stringify!($get))
+                    );
+
+                    Self((self.0 & !(MASK << LO)) | ((val & MASK) << LO))
+                }
+            )*
+        }
+
+        impl $crate::reg::RegValue for $name {
+            type Raw = $ty;
+
+            #[inline(always)]
+            fn from_raw(raw: $ty) -> Self {
+                Self(raw)
+            }
+
+            #[inline(always)]
+            fn into_raw(self) -> $ty {
+                self.0
+            }
+        }
+
+        impl $crate::reg::Field for $name {
+            #[inline(always)]
+            fn from_field(bits: u64) -> Self {
+                Self(bits as $ty)
+            }
+
+            #[inline(always)]
+            fn into_field(self) -> u64 {
+                self.0 as u64
+            }
+        }
+
+        impl ::core::fmt::Debug for $name {
+            fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+                f.debug_struct(stringify!($name))
+                    $(.field(stringify!($get), &self.$get()))*
+                    .finish()
+            }
+        }
+    };
+
+    (@hi $lo:literal) => { $lo };
+    (@hi $lo:literal, $hi:literal) => { $hi };
+}
+
+/// Defines a newtype over an integer with a set of named values, usable as a bitfield field.
+macro_rules! field_enum {
+    (
+        $(#[$attr:meta])*
+        $vis:vis struct $name:ident($ty:ty) {
+            $(
+                $(#[$vattr:meta])*
+                $var:ident = $val:expr;
+            )*
+        }
+    ) => {
+        $(#[$attr])*
+        #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+        #[repr(transparent)]
+        $vis struct $name(pub $ty);
+
+        impl $name {
+            $(
+                $(#[$vattr])*
+                pub const $var: Self = Self($val);
+            )*
+        }
+
+        impl $crate::reg::Field for $name {
+            #[inline(always)]
+            fn from_field(bits: u64) -> Self {
+                Self(bits as $ty)
+            }
+
+            #[inline(always)]
+            fn into_field(self) -> u64 {
+                self.0 as u64
+            }
+        }
+    };
+}
+
+pub(crate) use {bitfield, field_enum};
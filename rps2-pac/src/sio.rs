@@ -0,0 +1,130 @@
+//! EE serial port (SIO) registers.
+
+use crate::reg::{bitfield, field_enum, Reg, R, RW, W};
+use crate::timer::BUSCLK_HZ;
+
+/// Depth of the transmit and receive FIFOs.
+pub const FIFO_SIZE: u8 = 8;
+
+field_enum! {
+    /// Bits per character.
+    pub struct WordLength(u8) {
+        EIGHT = 0;
+        SEVEN = 1;
+    }
+}
+
+bitfield! {
+    /// Line control.
+    pub struct Lcr(u32) {
+        /// Bits per character.
+        umode, with_umode: WordLength = 0..=1;
+        /// Use two stop bits instead of one.
+        usbl, with_usbl: bool = 2;
+        /// Enable parity.
+        upen, with_upen: bool = 3;
+        /// Use even parity instead of odd.
+        ueps, with_ueps: bool = 4;
+    }
+}
+
+bitfield! {
+    /// Line status, write 1 to clear the error flags.
+    pub struct Lsr(u32) {
+        /// Received data is available.
+        dr, with_dr: bool = 0;
+        /// Overrun error.
+        oe, with_oe: bool = 1;
+        /// Parity error.
+        pe, with_pe: bool = 2;
+        /// Framing error.
+        fe, with_fe: bool = 3;
+    }
+}
+
+bitfield! {
+    /// Interrupt enable.
+    pub struct Ier(u32) {
+        /// Received data available.
+        erdai, with_erdai: bool = 0;
+        /// Line status error.
+        elsi, with_elsi: bool = 2;
+    }
+}
+
+bitfield! {
+    /// Interrupt status and FIFO levels, write 1 to clear the interrupt flags.
+    pub struct Isr(u32) {
+        /// Received data available.
+        rx_data, with_rx_data: bool = 0;
+        /// Transmit FIFO empty.
+        tx_empty, with_tx_empty: bool = 1;
+        /// Receive error.
+        rx_error, with_rx_error: bool = 2;
+        /// Characters in the receive FIFO.
+        rx_count, with_rx_count: u8 = 8..=11;
+        /// Characters in the transmit FIFO.
+        tx_count, with_tx_count: u8 = 12..=15;
+    }
+}
+
+bitfield! {
+    /// FIFO control.
+    pub struct Fcr(u32) {
+        /// Enable the FIFO resets below.
+        frste, with_frste: bool = 0;
+        /// Reset the receive FIFO.
+        rfrst, with_rfrst: bool = 1;
+        /// Reset the transmit FIFO.
+        tfrst, with_tfrst: bool = 2;
+    }
+}
+
+bitfield! {
+    /// Baud rate generator, the rate is BUSCLK / (16 * divisor * 4^prescaler).
+    pub struct Bgr(u32) {
+        /// Divisor.
+        brd, with_brd: u8 = 0..=7;
+        /// Prescaler, from 0 to 3.
+        bclk, with_bclk: u8 = 8..=9;
+    }
+}
+
+impl Bgr {
+    /// Setting closest to `baud`, computed like ps2sdk's `sio_init`.
+    pub fn for_baud(baud: u32) -> Self {
+        let mut brd = BUSCLK_HZ / (baud.max(1) * 16);
+        let mut bclk = 0;
+        while bclk < 3 && brd >= 256 {
+            brd /= 4;
+            bclk += 1;
+        }
+        Self::new().with_brd(brd.min(255) as u8).with_bclk(bclk)
+    }
+}
+
+pub const LCR: Reg<Lcr, RW> = unsafe { Reg::new(0x1000_f100) };
+pub const LSR: Reg<Lsr, RW> = unsafe { Reg::new(0x1000_f110) };
+pub const IER: Reg<Ier, RW> = unsafe { Reg::new(0x1000_f120) };
+pub const ISR: Reg<Isr, RW> = unsafe { Reg::new(0x1000_f130) };
+pub const FCR: Reg<Fcr, RW> = unsafe { Reg::new(0x1000_f140) };
+pub const BGR: Reg<Bgr, RW> = unsafe { Reg::new(0x1000_f150) };
+pub const TXFIFO: Reg<u8, W> = unsafe { Reg::new(0x1000_f180) };
+pub const RXFIFO: Reg<u8, R> = unsafe { Reg::new(0x1000_f1c0) };
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bgr_for_baud() {
+        // BGR values written by ps2sdk's sio_init
+        assert_eq!(Bgr::for_baud(115200).bits(), 0x0050);
+        assert_eq!(Bgr::for_baud(57600).bits(), 0x00a0);
+        assert_eq!(Bgr::for_baud(38400).bits(), 0x00f0);
+        // Slow rates need the prescaler
+        assert_eq!(Bgr::for_baud(9600).bits(), 0x01f0);
+        assert_eq!(Bgr::for_baud(2400).bits(), 0x02f0);
+        assert_eq!(Bgr::for_baud(1200).bits(), 0x0378);
+    }
+}
@@ -0,0 +1,120 @@
+//! EE timer registers.
+
+use crate::reg::{bitfield, field_enum, Reg, RW};
+
+/// Frequency of the bus clock, the base of timers T0-T3.
+pub const BUSCLK_HZ: u32 = 147_456_000;
+
+field_enum! {
+    /// Counter clock selection.
+    pub struct ClockSource(u8) {
+        BUSCLK = 0;
+        BUSCLK_16 = 1;
+        BUSCLK_256 = 2;
+        HBLNK = 3;
+    }
+}
+
+field_enum! {
+    /// Gate mode, only meaningful when gating is enabled.
+    pub struct GateMode(u8) {
+        /// Count while the gate signal is low.
+        LOW = 0;
+        /// Reset and start counting on the rising edge.
+        RISING = 1;
+        /// Reset and start counting on the falling edge.
+        FALLING = 2;
+        /// Reset and start counting on both edges.
+        BOTH = 3;
+    }
+}
+
+bitfield! {
+    /// Counter value, only the lower 16 bits are implemented.
+    pub struct Count(u32) {
+        count, with_count: u16 = 0..=15;
+    }
+}
+
+bitfield! {
+    /// Timer mode.
+    pub struct Mode(u32) {
+        /// Counter clock.
+        clks, with_clks: ClockSource = 0..=1;
+        /// Enable gating.
+        gate, with_gate: bool = 2;
+        /// Use VBLNK instead of HBLNK as the gate signal.
+        gats, with_gats: bool = 3;
+        /// Gate mode.
+        gatm, with_gatm: GateMode = 4..=5;
+        /// Reset the counter when it reaches the compare value.
+        zret, with_zret: bool = 6;
+        /// Enable counting.
+        cue, with_cue: bool = 7;
+        /// Raise an interrupt when the counter reaches the compare value.
+        cmpe, with_cmpe: bool = 8;
+        /// Raise an interrupt when the counter overflows.
+        ovfe, with_ovfe: bool = 9;
+        /// Compare interrupt flag, write 1 to clear.
+        equf, with_equf: bool = 10;
+        /// Overflow interrupt flag, write 1 to clear.
+        ovff, with_ovff: bool = 11;
+    }
+}
+
+bitfield! {
+    /// Compare value.
+    pub struct Comp(u32) {
+        comp, with_comp: u16 = 0..=15;
+    }
+}
+
+bitfield! {
+    /// Counter value latched on SBUS interrupts.
+    pub struct Hold(u32) {
+        hold, with_hold: u16 = 0..=15;
+    }
+}
+
+/// Register block of a single timer.
+///
+/// T_HOLD is only present on T0 and T1. T3 is used by the kernel to implement alarms.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Timer {
+    id: u8,
+    base: usize,
+}
+
+impl Timer {
+    const fn new(id: u8, base: usize) -> Self {
+        Self { id, base }
+    }
+
+    pub const fn id(&self) -> u8 {
+        self.id
+    }
+
+    pub const fn count(&self) -> Reg<Count, RW> {
+        unsafe { Reg::new(self.base) }
+    }
+
+    pub const fn mode(&self) -> Reg<Mode, RW> {
+        unsafe { Reg::new(self.base + 0x10) }
+    }
+
+    pub const fn comp(&self) -> Reg<Comp, RW> {
+        unsafe { Reg::new(self.base + 0x20) }
+    }
+
+    pub const fn hold(&self) -> Reg<Hold, RW> {
+        unsafe { Reg::new(self.base + 0x30) }
+    }
+}
+
+pub const T0: Timer = Timer::new(0, 0x1000_0000);
+pub const T1: Timer = Timer::new(1, 0x1000_0800);
+pub const T2: Timer = Timer::new(2, 0x1000_1000);
+pub const T3: Timer = Timer::new(3, 0x1000_1800);
+
+/// All timers, indexed by timer number.
+pub const TIMERS: [Timer; 4] = [T0, T1, T2, T3];
@@ -0,0 +1,173 @@
+//! Wire format of the XPADMAN service, and of the pad frames it streams to the EE.
+//!
+//! This crate only deals with the layout of requests, frames and controller replies, and does
+//! not touch the hardware.
+
+#![no_std]
+#![deny(missing_debug_implementations)]
+
+mod report;
+
+pub use report::{Buttons, Mode, Pressure, State, Stick};
+
+/// Server id of the pad command service.
+pub const PAD_SID: u32 = 0x8000_0100;
+
+/// Every command goes through this function, with the command id in the first word.
+pub const FNO: u32 = 1;
+
+/// Size of both requests and replies.
+pub const PACKET_SIZE: usize = 128;
+
+/// Size of a frame, the IOP alternates between two of them.
+pub const FRAME_SIZE: usize = 128;
+
+/// Size of the area receiving the frames of an open pad.
+pub const AREA_SIZE: usize = 2 * FRAME_SIZE;
+
+pub mod cmd {
+    pub const OPEN: u32 = 0x01;
+    pub const SET_MAIN_MODE: u32 = 0x06;
+    pub const SET_ACT_DIRECT: u32 = 0x07;
+    pub const SET_ACT_ALIGN: u32 = 0x08;
+    pub const GET_BUTTON_MASK: u32 = 0x09;
+    pub const SET_BUTTON_INFO: u32 = 0x0a;
+    pub const GET_PORT_MAX: u32 = 0x0c;
+    pub const GET_SLOT_MAX: u32 = 0x0d;
+    pub const CLOSE: u32 = 0x0e;
+    pub const END: u32 = 0x0f;
+    pub const INIT: u32 = 0x10;
+}
+
+pub mod main_mode {
+    pub const DIGITAL: u32 = 0;
+    pub const DUALSHOCK: u32 = 1;
+    pub const UNLOCK: u32 = 2;
+    pub const LOCK: u32 = 3;
+}
+
+pub mod state {
+    pub const DISCONNECTED: u8 = 0;
+    pub const FIND_PAD: u8 = 1;
+    pub const FIND_CTP1: u8 = 2;
+    pub const EXEC_CMD: u8 = 4;
+    pub const STABLE: u8 = 6;
+    pub const ERROR: u8 = 7;
+}
+
+pub mod req_state {
+    pub const COMPLETE: u8 = 0;
+    pub const FAILED: u8 = 1;
+    pub const BUSY: u8 = 2;
+}
+
+/// Button mask reported by controllers with pressure sensitive buttons.
+pub const PRESSURE_MASK: u32 = 0x3ffff;
+
+/// Button info enabling the pressure values of all buttons.
+pub const PRESSURE_ALL: u32 = 0xfff;
+
+/// Build a request for command `cmd`, the arguments fill the following words.
+pub fn request(cmd: u32, args: &[u32]) -> [u8; PACKET_SIZE] {
+    let mut buf = [0; PACKET_SIZE];
+    buf[0..4].copy_from_slice(&cmd.to_le_bytes());
+    for (i, arg) in args.iter().enumerate() {
+        buf[4 + i * 4..8 + i * 4].copy_from_slice(&arg.to_le_bytes());
+    }
+    buf
+}
+
+/// Build an actuator request, which carries 6 bytes after the port and slot.
+pub fn act_request(cmd: u32, port: u32, slot: u32, data: [u8; 6]) -> [u8; PACKET_SIZE] {
+    let mut buf = request(cmd, &[port, slot]);
+    buf[12..18].copy_from_slice(&data);
+    buf
+}
+
+/// Result of a command, in the fourth word of the reply.
+pub fn result(reply: &[u8; PACKET_SIZE]) -> i32 {
+    i32::from_le_bytes(reply[12..16].try_into().unwrap())
+}
+
+/// A snapshot of a pad, as written by the IOP.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Frame {
+    /// Raw reply of the controller, see [`State::decode`](crate::State::decode).
+    pub data: [u8; 32],
+    /// Incremented by the IOP on every update.
+    pub counter: u32,
+    /// Length of the valid part of `data`.
+    pub length: u32,
+    /// Id of the current mode, e.g. `0x41` for digital and `0x73` for analog.
+    pub mode_id: u8,
+    pub state: u8,
+    pub req_state: u8,
+}
+
+impl Frame {
+    pub fn decode(buf: &[u8; FRAME_SIZE]) -> Self {
+        let word = |offset: usize| u32::from_le_bytes(buf[offset..offset + 4].try_into().unwrap());
+
+        Self {
+            data: buf[0..32].try_into().unwrap(),
+            counter: word(88),
+            length: word(96),
+            mode_id: buf[101],
+            state: buf[112],
+            req_state: buf[113],
+        }
+    }
+
+    /// The most recent of the two frames in a pad area.
+    pub fn latest(area: &[u8; AREA_SIZE]) -> Self {
+        let (first, second) = area.split_at(FRAME_SIZE);
+        let first = Self::decode(first.try_into().unwrap());
+        let second = Self::decode(second.try_into().unwrap());
+
+        if first.counter < second.counter {
+            second
+        } else {
+            first
+        }
+    }
+
+    /// The valid part of the controller reply.
+    pub fn button_data(&self) -> &[u8] {
+        &self.data[..(self.length as usize).min(self.data.len())]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frame_latest() {
+        let mut area = [0; AREA_SIZE];
+        let mut frame = |index: usize, counter: u32, state: u8| {
+            let base = index * FRAME_SIZE;
+            area[base + 88..base + 92].copy_from_slice(&counter.to_le_bytes());
+            area[base + 96..base + 100].copy_from_slice(&4u32.to_le_bytes());
+            area[base + 112] = state;
+        };
+        frame(0, 7, state::FIND_PAD);
+        frame(1, 8, state::STABLE);
+
+        let latest = Frame::latest(&area);
+        assert_eq!(latest.counter, 8);
+        assert_eq!(latest.state, state::STABLE);
+        assert_eq!(latest.button_data().len(), 4);
+    }
+
+    #[test]
+    fn test_request_encoding() {
+        let request = request(cmd::SET_MAIN_MODE, &[1, 0, 1, 3]);
+        assert_eq!(
+            &request[..20],
+            &[6, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 3, 0, 0, 0]
+        );
+
+        let request = act_request(cmd::SET_ACT_DIRECT, 0, 1, [1, 0xff, 0, 0, 0, 0]);
+        assert_eq!(&request[12..18], &[1, 0xff, 0, 0, 0, 0]);
+    }
+}
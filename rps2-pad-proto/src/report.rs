@@ -0,0 +1,302 @@
+use core::fmt;
+use core::ops::{BitAnd, BitAndAssign, BitOr, BitOrAssign, Not};
+
+/// A set of digital buttons.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Buttons(u16);
+
+impl Buttons {
+    pub const SELECT: Self = Self(0x0001);
+    pub const L3: Self = Self(0x0002);
+    pub const R3: Self = Self(0x0004);
+    pub const START: Self = Self(0x0008);
+    pub const UP: Self = Self(0x0010);
+    pub const RIGHT: Self = Self(0x0020);
+    pub const DOWN: Self = Self(0x0040);
+    pub const LEFT: Self = Self(0x0080);
+    pub const L2: Self = Self(0x0100);
+    pub const R2: Self = Self(0x0200);
+    pub const L1: Self = Self(0x0400);
+    pub const R1: Self = Self(0x0800);
+    pub const TRIANGLE: Self = Self(0x1000);
+    pub const CIRCLE: Self = Self(0x2000);
+    pub const CROSS: Self = Self(0x4000);
+    pub const SQUARE: Self = Self(0x8000);
+
+    const NAMES: [(Self, &'static str); 16] = [
+        (Self::SELECT, "SELECT"),
+        (Self::L3, "L3"),
+        (Self::R3, "R3"),
+        (Self::START, "START"),
+        (Self::UP, "UP"),
+        (Self::RIGHT, "RIGHT"),
+        (Self::DOWN, "DOWN"),
+        (Self::LEFT, "LEFT"),
+        (Self::L2, "L2"),
+        (Self::R2, "R2"),
+        (Self::L1, "L1"),
+        (Self::R1, "R1"),
+        (Self::TRIANGLE, "TRIANGLE"),
+        (Self::CIRCLE, "CIRCLE"),
+        (Self::CROSS, "CROSS"),
+        (Self::SQUARE, "SQUARE"),
+    ];
+
+    pub const fn empty() -> Self {
+        Self(0)
+    }
+
+    pub const fn all() -> Self {
+        Self(0xffff)
+    }
+
+    pub const fn from_bits(bits: u16) -> Self {
+        Self(bits)
+    }
+
+    pub const fn bits(&self) -> u16 {
+        self.0
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    /// Whether all buttons of `other` are in the set.
+    pub const fn contains(&self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Whether any button of `other` is in the set.
+    pub const fn intersects(&self, other: Self) -> bool {
+        self.0 & other.0 != 0
+    }
+}
+
+impl BitOr for Buttons {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl BitOrAssign for Buttons {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
+
+impl BitAnd for Buttons {
+    type Output = Self;
+
+    fn bitand(self, rhs: Self) -> Self {
+        Self(self.0 & rhs.0)
+    }
+}
+
+impl BitAndAssign for Buttons {
+    fn bitand_assign(&mut self, rhs: Self) {
+        self.0 &= rhs.0;
+    }
+}
+
+impl Not for Buttons {
+    type Output = Self;
+
+    fn not(self) -> Self {
+        Self(!self.0)
+    }
+}
+
+impl fmt::Debug for Buttons {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut set = f.debug_set();
+        for (button, name) in Self::NAMES {
+            if self.contains(button) {
+                set.entry(&format_args!("{name}"));
+            }
+        }
+        set.finish()
+    }
+}
+
+/// Position of an analog stick, `0x80` is the center on both axes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Stick {
+    /// From `0` (left) to `255` (right).
+    pub x: u8,
+    /// From `0` (up) to `255` (down).
+    pub y: u8,
+}
+
+impl Stick {
+    pub const CENTER: Self = Self { x: 0x80, y: 0x80 };
+}
+
+impl Default for Stick {
+    fn default() -> Self {
+        Self::CENTER
+    }
+}
+
+/// Pressure of the pressure sensitive buttons, from `0` (released) to `255`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Pressure {
+    pub right: u8,
+    pub left: u8,
+    pub up: u8,
+    pub down: u8,
+    pub triangle: u8,
+    pub circle: u8,
+    pub cross: u8,
+    pub square: u8,
+    pub l1: u8,
+    pub r1: u8,
+    pub l2: u8,
+    pub r2: u8,
+}
+
+impl Pressure {
+    /// Pressure of a single button, `0` for buttons which are not pressure sensitive.
+    pub fn get(&self, button: Buttons) -> u8 {
+        match button {
+            Buttons::RIGHT => self.right,
+            Buttons::LEFT => self.left,
+            Buttons::UP => self.up,
+            Buttons::DOWN => self.down,
+            Buttons::TRIANGLE => self.triangle,
+            Buttons::CIRCLE => self.circle,
+            Buttons::CROSS => self.cross,
+            Buttons::SQUARE => self.square,
+            Buttons::L1 => self.l1,
+            Buttons::R1 => self.r1,
+            Buttons::L2 => self.l2,
+            Buttons::R2 => self.r2,
+            _ => 0,
+        }
+    }
+}
+
+/// Kind of report sent by the controller.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Mode {
+    Digital,
+    /// DualShock reports, including sticks and possibly pressure values.
+    Analog,
+    /// Any other controller type, by the upper nibble of its mode id.
+    Other(u8),
+}
+
+/// Input state of a pad.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct State {
+    pub mode: Mode,
+    /// Buttons being held.
+    pub buttons: Buttons,
+    /// Left stick, centered in digital mode.
+    pub left: Stick,
+    /// Right stick, centered in digital mode.
+    pub right: Stick,
+    /// Button pressures, only reported once enabled through `Pad::set_pressure`.
+    pub pressure: Option<Pressure>,
+}
+
+impl State {
+    /// Decode the reply of a controller: status, mode id, buttons, sticks and pressures.
+    pub fn decode(data: &[u8]) -> Option<Self> {
+        // A non zero status means the reply is not valid
+        if data.len() < 4 || data[0] != 0 {
+            return None;
+        }
+
+        let mode = match data[1] >> 4 {
+            0x4 => Mode::Digital,
+            0x7 => Mode::Analog,
+            other => Mode::Other(other),
+        };
+        // Buttons are active low
+        let buttons = Buttons(!u16::from_le_bytes([data[2], data[3]]));
+
+        let (right, left) = match data.get(4..8) {
+            Some(sticks) => (
+                Stick {
+                    x: sticks[0],
+                    y: sticks[1],
+                },
+                Stick {
+                    x: sticks[2],
+                    y: sticks[3],
+                },
+            ),
+            None => (Stick::CENTER, Stick::CENTER),
+        };
+
+        let pressure = data.get(8..20).map(|p| Pressure {
+            right: p[0],
+            left: p[1],
+            up: p[2],
+            down: p[3],
+            triangle: p[4],
+            circle: p[5],
+            cross: p[6],
+            square: p[7],
+            l1: p[8],
+            r1: p[9],
+            l2: p[10],
+            r2: p[11],
+        });
+
+        Some(Self {
+            mode,
+            buttons,
+            left,
+            right,
+            pressure,
+        })
+    }
+
+    pub fn is_pressed(&self, button: Buttons) -> bool {
+        self.buttons.contains(button)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_buttons() {
+        let buttons = Buttons::CROSS | Buttons::L1;
+        assert!(buttons.contains(Buttons::CROSS));
+        assert!(!buttons.contains(Buttons::CROSS | Buttons::START));
+        assert!(buttons.intersects(Buttons::CROSS | Buttons::START));
+        assert_eq!(buttons & !Buttons::L1, Buttons::CROSS);
+        assert!(Buttons::empty().is_empty());
+    }
+
+    #[test]
+    fn test_state_decoding() {
+        // Digital report, with START and CROSS held
+        let state = State::decode(&[0x00, 0x41, 0xf7, 0xbf]).unwrap();
+        assert_eq!(state.mode, Mode::Digital);
+        assert_eq!(state.buttons, Buttons::START | Buttons::CROSS);
+        assert_eq!(state.left, Stick::CENTER);
+        assert_eq!(state.pressure, None);
+
+        // Analog report with pressures, UP held
+        let mut data = [0; 20];
+        data[..8].copy_from_slice(&[0x00, 0x79, 0xef, 0xff, 0x10, 0x20, 0x30, 0x40]);
+        data[10] = 0xc0;
+        let state = State::decode(&data).unwrap();
+        assert_eq!(state.mode, Mode::Analog);
+        assert_eq!(state.buttons, Buttons::UP);
+        assert_eq!(state.right, Stick { x: 0x10, y: 0x20 });
+        assert_eq!(state.left, Stick { x: 0x30, y: 0x40 });
+        assert_eq!(state.pressure.unwrap().get(Buttons::UP), 0xc0);
+
+        // Failed reads are discarded
+        assert_eq!(State::decode(&[0xff, 0x41, 0xff, 0xff]), None);
+        assert_eq!(State::decode(&[0x00, 0x41]), None);
+    }
+}
@@ -0,0 +1,15 @@
+//! DualShock 2 input through the XPADMAN driver on the IOP.
+//!
+//! The driver streams the state of every open pad to EE memory, so reading a [`Pad`] is cheap.
+//! Commands like mode changes and vibration go through SIF RPC.
+#![no_std]
+#![deny(missing_debug_implementations)]
+extern crate alloc;
+
+mod pad;
+mod poll;
+
+pub use pad::{init, port_max, slot_max, Error, Pad, Result, Status};
+pub use poll::Poller;
+pub use rps2_pad_proto as proto;
+pub use rps2_pad_proto::{Buttons, Mode, Pressure, State, Stick};
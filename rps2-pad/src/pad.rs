@@ -0,0 +1,281 @@
+use alloc::boxed::Box;
+use core::cell::UnsafeCell;
+use core::ptr;
+
+use rps2_kernel::arch::{self, CacheAligned};
+use rps2_sif::cmd;
+use rps2_sif::loadfile;
+use rps2_sif::rpc::{ClientPool, Service};
+
+use crate::proto::{self, cmd as pad_cmd, main_mode, req_state, Frame, AREA_SIZE, PACKET_SIZE};
+use crate::proto::{FNO, PAD_SID, PRESSURE_ALL, PRESSURE_MASK};
+use crate::State;
+
+const SIO2MAN: &str = "rom0:XSIO2MAN";
+const PADMAN: &str = "rom0:XPADMAN";
+
+/// Actuator mapping of a DualShock: small motor first, then large motor.
+const DUALSHOCK_ALIGN: [u8; 6] = [0, 1, 0xff, 0xff, 0xff, 0xff];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    /// The pad driver could not be loaded.
+    Load(loadfile::Error),
+    /// The driver refused to open the port.
+    Open,
+    /// The driver refused the command, the pad might be disconnected or busy.
+    Rejected,
+}
+
+impl From<loadfile::Error> for Error {
+    fn from(err: loadfile::Error) -> Self {
+        Self::Load(err)
+    }
+}
+
+pub type Result<T> = core::result::Result<T, Error>;
+
+static PAD: ClientPool = ClientPool::new(PAD_SID);
+static SERVICE: Service = Service::new(PAD_SID);
+
+#[repr(C, align(64))]
+struct SlotBuf(UnsafeCell<[u8; PACKET_SIZE]>);
+
+// SAFETY: Only ever written by the IOP
+unsafe impl Sync for SlotBuf {}
+
+/// Status of the open slots, written by the driver.
+static SLOTS: SlotBuf = SlotBuf(UnsafeCell::new([0; PACKET_SIZE]));
+
+fn call(request: &[u8; PACKET_SIZE]) -> i32 {
+    let mut reply = [0; PACKET_SIZE];
+    PAD.get().call(FNO, request, &mut reply);
+    proto::result(&reply)
+}
+
+/// Load and initialize the pad driver, unless it is already running.
+///
+/// Called by [`Pad::open`], and needed again after an IOP reset.
+pub fn init() -> Result<()> {
+    SERVICE.init(|running| {
+        // The driver might have been loaded by a previous program
+        if !running {
+            loadfile::load_module(SIO2MAN, &[])?;
+            loadfile::load_module(PADMAN, &[])?;
+        }
+
+        call(&proto::request(
+            pad_cmd::INIT,
+            &[0, 0, 0, cmd::phys_addr(SLOTS.0.get())],
+        ));
+        Ok(())
+    })
+}
+
+/// Number of controller ports.
+pub fn port_max() -> Result<u32> {
+    init()?;
+    Ok(call(&proto::request(pad_cmd::GET_PORT_MAX, &[])) as u32)
+}
+
+/// Number of slots of `port`, more than one with a multitap.
+pub fn slot_max(port: u32) -> Result<u32> {
+    init()?;
+    Ok(call(&proto::request(pad_cmd::GET_SLOT_MAX, &[port])) as u32)
+}
+
+/// Connection status of a pad.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Disconnected,
+    /// The driver is looking for a controller.
+    FindPad,
+    FindCtp1,
+    /// A command is being executed, e.g. a mode change.
+    ExecCmd,
+    /// Ready to be read and to receive commands.
+    Stable,
+    Error,
+    Unknown(u8),
+}
+
+impl Status {
+    pub fn from_raw(state: u8) -> Self {
+        match state {
+            proto::state::DISCONNECTED => Self::Disconnected,
+            proto::state::FIND_PAD => Self::FindPad,
+            proto::state::FIND_CTP1 => Self::FindCtp1,
+            proto::state::EXEC_CMD => Self::ExecCmd,
+            proto::state::STABLE => Self::Stable,
+            proto::state::ERROR => Self::Error,
+            other => Self::Unknown(other),
+        }
+    }
+}
+
+/// A controller port, closed when dropped.
+pub struct Pad {
+    port: u32,
+    slot: u32,
+    area: Box<CacheAligned<[u8; AREA_SIZE]>>,
+    /// Set when a command is accepted, until the driver picks it up.
+    busy_since: Option<u32>,
+}
+
+impl Pad {
+    /// Open the pad in `slot` of `port`, loading the driver if needed.
+    pub fn open(port: u32, slot: u32) -> Result<Self> {
+        init()?;
+
+        let area = Box::new(CacheAligned([0; AREA_SIZE]));
+        // The area is only read through the uncached segment from now on
+        unsafe { arch::cache_dhwbin(area.as_ptr() as *const (), AREA_SIZE) };
+
+        let res = call(&proto::request(
+            pad_cmd::OPEN,
+            &[port, slot, 0, cmd::phys_addr(area.as_ptr())],
+        ));
+        if res == 0 {
+            return Err(Error::Open);
+        }
+
+        Ok(Self {
+            port,
+            slot,
+            area,
+            busy_since: None,
+        })
+    }
+
+    pub fn port(&self) -> u32 {
+        self.port
+    }
+
+    pub fn slot(&self) -> u32 {
+        self.slot
+    }
+
+    /// The latest frame written by the driver.
+    pub fn frame(&self) -> Frame {
+        let area = unsafe {
+            ptr::read_volatile(arch::uncached_seg(&self.area.0 as *const [u8; AREA_SIZE]))
+        };
+        Frame::latest(&area)
+    }
+
+    pub fn status(&mut self) -> Status {
+        let frame = self.frame();
+        let status = Status::from_raw(frame.state);
+
+        if let Some(since) = self.busy_since {
+            if frame.counter == since && frame.req_state != req_state::FAILED {
+                // The driver did not see the command yet
+                return match status {
+                    Status::Stable => Status::ExecCmd,
+                    status => status,
+                };
+            }
+            self.busy_since = None;
+        }
+        if status == Status::Stable && frame.req_state == req_state::BUSY {
+            return Status::ExecCmd;
+        }
+        status
+    }
+
+    /// Current input state, `None` unless the pad is connected and stable.
+    pub fn state(&mut self) -> Option<State> {
+        if self.status() != Status::Stable {
+            return None;
+        }
+        State::decode(self.frame().button_data())
+    }
+
+    fn command(&mut self, request: &[u8; PACKET_SIZE]) -> Result<()> {
+        let counter = self.frame().counter;
+        if call(request) != 1 {
+            return Err(Error::Rejected);
+        }
+        self.busy_since = Some(counter);
+        Ok(())
+    }
+
+    /// Switch between digital and analog mode, `lock` prevents the user from switching back.
+    ///
+    /// The pad goes through [`Status::ExecCmd`] while the mode changes.
+    pub fn set_analog(&mut self, analog: bool, lock: bool) -> Result<()> {
+        let mode = if analog {
+            main_mode::DUALSHOCK
+        } else {
+            main_mode::DIGITAL
+        };
+        let lock = if lock {
+            main_mode::LOCK
+        } else {
+            main_mode::UNLOCK
+        };
+        self.command(&proto::request(
+            pad_cmd::SET_MAIN_MODE,
+            &[self.port, self.slot, mode, lock],
+        ))
+    }
+
+    /// Whether the controller has pressure sensitive buttons, i.e. it is a DualShock 2.
+    pub fn has_pressure(&self) -> bool {
+        let mask = call(&proto::request(
+            pad_cmd::GET_BUTTON_MASK,
+            &[self.port, self.slot],
+        ));
+        mask as u32 == PRESSURE_MASK
+    }
+
+    /// Enable or disable the pressure values in [`State::pressure`], requires analog mode.
+    pub fn set_pressure(&mut self, enabled: bool) -> Result<()> {
+        let info = if enabled { PRESSURE_ALL } else { 0 };
+        self.command(&proto::request(
+            pad_cmd::SET_BUTTON_INFO,
+            &[self.port, self.slot, info],
+        ))
+    }
+
+    /// Map the actuators of a DualShock, must be done before [`Pad::set_actuators`].
+    pub fn enable_actuators(&mut self) -> Result<()> {
+        self.command(&proto::act_request(
+            pad_cmd::SET_ACT_ALIGN,
+            self.port,
+            self.slot,
+            DUALSHOCK_ALIGN,
+        ))
+    }
+
+    /// Drive the small motor, which is either on or off, and the large motor with a strength.
+    pub fn set_actuators(&mut self, small: bool, large: u8) -> Result<()> {
+        let res = call(&proto::act_request(
+            pad_cmd::SET_ACT_DIRECT,
+            self.port,
+            self.slot,
+            [small as u8, large, 0, 0, 0, 0],
+        ));
+        // Vibration updates do not go through the command state
+        if res != 1 {
+            return Err(Error::Rejected);
+        }
+        Ok(())
+    }
+}
+
+impl Drop for Pad {
+    fn drop(&mut self) {
+        // The driver stops writing the area before replying
+        call(&proto::request(pad_cmd::CLOSE, &[self.port, self.slot, 1]));
+    }
+}
+
+impl core::fmt::Debug for Pad {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        f.debug_struct("Pad")
+            .field("port", &self.port)
+            .field("slot", &self.slot)
+            .finish_non_exhaustive()
+    }
+}
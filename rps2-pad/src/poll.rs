@@ -0,0 +1,115 @@
+use alloc::sync::Arc;
+use core::cell::Cell;
+use core::sync::atomic::{AtomicBool, Ordering};
+use critical_section::Mutex;
+
+use rps2_thread::ffi;
+use rps2_thread::mutex::{self, MutexGuard};
+use rps2_thread::thread::{Builder, JoinHandle};
+use rps2_thread::vblank::VBlank;
+
+use crate::{Pad, State};
+
+#[derive(Debug, Clone, Copy)]
+struct Published {
+    state: Option<State>,
+    frame: u64,
+}
+
+#[derive(Debug)]
+struct Shared {
+    pad: mutex::Mutex<Pad>,
+    published: Mutex<Cell<Published>>,
+    stop: AtomicBool,
+}
+
+/// Reads a pad from a background thread at every vertical blank.
+///
+/// The thread stops when the poller is dropped, closing the pad.
+#[derive(Debug)]
+pub struct Poller {
+    shared: Arc<Shared>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Poller {
+    pub fn spawn(pad: Pad) -> ffi::Result<Self> {
+        Self::spawn_with(Builder::new(), pad)
+    }
+
+    /// Spawn the polling thread with a custom builder, e.g. to raise its priority.
+    pub fn spawn_with(builder: Builder, pad: Pad) -> ffi::Result<Self> {
+        let shared = Arc::new(Shared {
+            pad: mutex::Mutex::new(pad),
+            published: Mutex::new(Cell::new(Published {
+                state: None,
+                frame: 0,
+            })),
+            stop: AtomicBool::new(false),
+        });
+
+        let handle = builder.spawn({
+            let shared = shared.clone();
+            move || poll(&shared)
+        })?;
+
+        Ok(Self {
+            shared,
+            handle: Some(handle),
+        })
+    }
+
+    /// State published at the last vertical blank.
+    pub fn state(&self) -> Option<State> {
+        critical_section::with(|cs| self.shared.published.borrow(cs).get().state)
+    }
+
+    /// Frame counter of the last vertical blank, see [`rps2_thread::vblank::frame_count`].
+    pub fn frame(&self) -> u64 {
+        critical_section::with(|cs| self.shared.published.borrow(cs).get().frame)
+    }
+
+    /// Access the pad to send commands, polling pauses while it is held.
+    pub fn pad(&self) -> MutexGuard<'_, Pad> {
+        self.shared.pad.lock()
+    }
+
+    /// Stop the polling thread and take the pad back.
+    pub fn into_pad(mut self) -> Pad {
+        self.stop();
+        let shared = self.shared.clone();
+        drop(self);
+
+        match Arc::try_unwrap(shared) {
+            Ok(shared) => shared.pad.into_inner(),
+            Err(_) => unreachable!("polling thread still running"),
+        }
+    }
+
+    fn stop(&mut self) {
+        if let Some(handle) = self.handle.take() {
+            self.shared.stop.store(true, Ordering::Relaxed);
+            // A panic in the polling thread already got reported
+            let _ = handle.join();
+        }
+    }
+}
+
+impl Drop for Poller {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+fn poll(shared: &Shared) {
+    let vblank = VBlank::subscribe();
+
+    while !shared.stop.load(Ordering::Relaxed) {
+        let frame = vblank.wait();
+        let state = shared.pad.lock().state();
+
+        critical_section::with(|cs| {
+            shared.published.borrow(cs).set(Published { state, frame });
+        });
+    }
+}
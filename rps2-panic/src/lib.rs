@@ -35,6 +35,11 @@ pub fn panic_any<M: 'static + Any + Send>(msg: M) -> ! {
     begin_unwind(Box::new(msg));
 }
 
+/// Continue unwinding with a payload taken from [`catch_unwind`], without printing a message.
+pub fn resume_unwind(payload: Box<dyn Any + Send>) -> ! {
+    begin_unwind(payload);
+}
+
 #[panic_handler]
 fn panic_handler(info: &PanicInfo) -> ! {
     rps2_kernel::kprintln!("{}", info);
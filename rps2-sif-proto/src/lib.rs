@@ -0,0 +1,517 @@
+//! Wire format of SIF command and RPC packets.
+//!
+//! Packets are sequences of little endian 32 bit words, this crate only deals with their
+//! layout and does not touch the hardware.
+
+#![no_std]
+#![deny(missing_debug_implementations)]
+
+/// Bit set in the id of commands reserved to the system.
+pub const CMD_SYSTEM: u32 = 0x8000_0000;
+
+pub const CMD_CHANGE_SADDR: u32 = CMD_SYSTEM;
+pub const CMD_SET_SREG: u32 = CMD_SYSTEM | 1;
+pub const CMD_INIT_CMD: u32 = CMD_SYSTEM | 2;
+pub const CMD_RESET_CMD: u32 = CMD_SYSTEM | 3;
+pub const CMD_RPC_END: u32 = CMD_SYSTEM | 8;
+pub const CMD_RPC_BIND: u32 = CMD_SYSTEM | 9;
+pub const CMD_RPC_CALL: u32 = CMD_SYSTEM | 10;
+pub const CMD_RPC_RDATA: u32 = CMD_SYSTEM | 12;
+
+/// Maximum size of a command packet, header included.
+pub const MAX_PACKET_SIZE: usize = 112;
+
+/// Size of every RPC packet.
+pub const RPC_PACKET_SIZE: usize = 64;
+
+/// Maximum length of the argument of a reset command.
+pub const RESET_ARG_MAX: usize = 79;
+
+/// Maximum size of the extra data attached to a command.
+pub const MAX_DATA_SIZE: u32 = 0xff_ffff;
+
+/// A raw command packet, aligned to a cache line so it can be sent with DMA.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(C, align(64))]
+pub struct RawPacket(pub [u32; MAX_PACKET_SIZE / 4]);
+
+impl RawPacket {
+    pub const fn new() -> Self {
+        Self([0; MAX_PACKET_SIZE / 4])
+    }
+
+    pub fn header(&self) -> CmdHeader {
+        CmdHeader::decode(&self.0)
+    }
+
+    /// Size of the packet, as stored in the header.
+    pub fn size(&self) -> usize {
+        (self.0[0] & 0xff) as usize
+    }
+
+    pub fn as_ptr(&self) -> *const u32 {
+        self.0.as_ptr()
+    }
+}
+
+impl Default for RawPacket {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Common header of all command packets.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CmdHeader {
+    /// Size of the packet, header included.
+    pub psize: u8,
+    /// Size of the extra data sent along with the packet, 24 bits.
+    pub dsize: u32,
+    /// Destination of the extra data.
+    pub dest: u32,
+    /// Command id.
+    pub cid: u32,
+    /// Command specific option.
+    pub opt: u32,
+}
+
+impl CmdHeader {
+    pub const WORDS: usize = 4;
+
+    pub fn encode(&self, words: &mut [u32]) {
+        assert!(self.dsize <= MAX_DATA_SIZE, "SIF extra data too big");
+
+        words[0] = self.psize as u32 | (self.dsize << 8);
+        words[1] = self.dest;
+        words[2] = self.cid;
+        words[3] = self.opt;
+    }
+
+    pub fn decode(words: &[u32]) -> Self {
+        Self {
+            psize: words[0] as u8,
+            dsize: words[0] >> 8,
+            dest: words[1],
+            cid: words[2],
+            opt: words[3],
+        }
+    }
+}
+
+/// Body of a command packet, following the header.
+pub trait Body: Sized {
+    /// Command id this body is sent with.
+    const CID: u32;
+    /// Size of the body, in words.
+    const WORDS: usize;
+
+    fn encode(&self, words: &mut [u32]);
+    fn decode(words: &[u32]) -> Self;
+
+    /// Size of the whole packet, in bytes.
+    fn packet_size() -> usize {
+        (CmdHeader::WORDS + Self::WORDS) * 4
+    }
+}
+
+/// Encode `header` and `body` into `packet`, filling in the packet size and command id.
+pub fn encode<B: Body>(packet: &mut RawPacket, mut header: CmdHeader, body: &B) {
+    header.psize = B::packet_size() as u8;
+    header.cid = B::CID;
+    header.encode(&mut packet.0[..CmdHeader::WORDS]);
+    body.encode(&mut packet.0[CmdHeader::WORDS..CmdHeader::WORDS + B::WORDS]);
+}
+
+/// Decode the body of `packet`, returns `None` if the command id does not match.
+pub fn decode<B: Body>(packet: &RawPacket) -> Option<B> {
+    if packet.header().cid != B::CID {
+        return None;
+    }
+    Some(B::decode(
+        &packet.0[CmdHeader::WORDS..CmdHeader::WORDS + B::WORDS],
+    ))
+}
+
+/// Move the IOP side command buffer.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ChangeSaddr {
+    pub addr: u32,
+}
+
+impl Body for ChangeSaddr {
+    const CID: u32 = CMD_CHANGE_SADDR;
+    const WORDS: usize = 1;
+
+    fn encode(&self, words: &mut [u32]) {
+        words[0] = self.addr;
+    }
+
+    fn decode(words: &[u32]) -> Self {
+        Self { addr: words[0] }
+    }
+}
+
+/// Set one of the software registers.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SetSreg {
+    pub index: u32,
+    pub value: i32,
+}
+
+impl Body for SetSreg {
+    const CID: u32 = CMD_SET_SREG;
+    const WORDS: usize = 2;
+
+    fn encode(&self, words: &mut [u32]) {
+        words[0] = self.index;
+        words[1] = self.value as u32;
+    }
+
+    fn decode(words: &[u32]) -> Self {
+        Self {
+            index: words[0],
+            value: words[1] as i32,
+        }
+    }
+}
+
+/// Initialize the command layer (header option 0) or the RPC layer (header option 1).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct InitCmd {
+    /// Physical address of the EE receive buffer.
+    pub buf: u32,
+}
+
+impl Body for InitCmd {
+    const CID: u32 = CMD_INIT_CMD;
+    const WORDS: usize = 1;
+
+    fn encode(&self, words: &mut [u32]) {
+        words[0] = self.buf;
+    }
+
+    fn decode(words: &[u32]) -> Self {
+        Self { buf: words[0] }
+    }
+}
+
+/// Reboot the IOP, loading the modules listed by `arg`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResetCmd {
+    pub arglen: u32,
+    pub mode: u32,
+    /// NUL terminated argument string.
+    pub arg: [u8; RESET_ARG_MAX + 1],
+}
+
+impl Default for ResetCmd {
+    fn default() -> Self {
+        Self {
+            arglen: 0,
+            mode: 0,
+            arg: [0; RESET_ARG_MAX + 1],
+        }
+    }
+}
+
+impl Body for ResetCmd {
+    const CID: u32 = CMD_RESET_CMD;
+    const WORDS: usize = 2 + (RESET_ARG_MAX + 1) / 4;
+
+    fn encode(&self, words: &mut [u32]) {
+        words[0] = self.arglen;
+        words[1] = self.mode;
+        for (word, chunk) in words[2..].iter_mut().zip(self.arg.chunks_exact(4)) {
+            *word = u32::from_le_bytes(chunk.try_into().unwrap());
+        }
+    }
+
+    fn decode(words: &[u32]) -> Self {
+        let mut arg = [0; RESET_ARG_MAX + 1];
+        for (chunk, word) in arg.chunks_exact_mut(4).zip(&words[2..Self::WORDS]) {
+            chunk.copy_from_slice(&word.to_le_bytes());
+        }
+        Self {
+            arglen: words[0],
+            mode: words[1],
+            arg,
+        }
+    }
+}
+
+/// Fields shared by all RPC packets.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RpcHeader {
+    pub rec_id: u32,
+    /// Address of the packet, echoed back by the IOP.
+    pub pkt_addr: u32,
+    pub rpc_id: u32,
+    /// Address of the client data, echoed back by the IOP.
+    pub client: u32,
+}
+
+impl RpcHeader {
+    const WORDS: usize = 4;
+
+    fn encode(&self, words: &mut [u32]) {
+        words[0] = self.rec_id;
+        words[1] = self.pkt_addr;
+        words[2] = self.rpc_id;
+        words[3] = self.client;
+    }
+
+    fn decode(words: &[u32]) -> Self {
+        Self {
+            rec_id: words[0],
+            pkt_addr: words[1],
+            rpc_id: words[2],
+            client: words[3],
+        }
+    }
+}
+
+/// Bind a client to the server with id `sid`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RpcBind {
+    pub rpc: RpcHeader,
+    pub sid: u32,
+}
+
+impl Body for RpcBind {
+    const CID: u32 = CMD_RPC_BIND;
+    const WORDS: usize = RpcHeader::WORDS + 1;
+
+    fn encode(&self, words: &mut [u32]) {
+        self.rpc.encode(words);
+        words[4] = self.sid;
+    }
+
+    fn decode(words: &[u32]) -> Self {
+        Self {
+            rpc: RpcHeader::decode(words),
+            sid: words[4],
+        }
+    }
+}
+
+/// Call function `rpc_number` of a bound server.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RpcCall {
+    pub rpc: RpcHeader,
+    pub rpc_number: u32,
+    pub send_size: u32,
+    /// Physical address of the EE receive buffer.
+    pub receive: u32,
+    pub recv_size: u32,
+    /// Whether the server replies with an end packet.
+    pub rmode: u32,
+    /// IOP address of the server, as returned by the bind.
+    pub server: u32,
+}
+
+impl Body for RpcCall {
+    const CID: u32 = CMD_RPC_CALL;
+    const WORDS: usize = RPC_PACKET_SIZE / 4 - CmdHeader::WORDS;
+
+    fn encode(&self, words: &mut [u32]) {
+        self.rpc.encode(words);
+        words[4] = self.rpc_number;
+        words[5] = self.send_size;
+        words[6] = self.receive;
+        words[7] = self.recv_size;
+        words[8] = self.rmode;
+        words[9] = self.server;
+        words[10..].fill(0);
+    }
+
+    fn decode(words: &[u32]) -> Self {
+        Self {
+            rpc: RpcHeader::decode(words),
+            rpc_number: words[4],
+            send_size: words[5],
+            receive: words[6],
+            recv_size: words[7],
+            rmode: words[8],
+            server: words[9],
+        }
+    }
+}
+
+/// Completion of a bind or call, sent by the IOP.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RpcEnd {
+    pub rpc: RpcHeader,
+    /// Command id of the completed request.
+    pub cid: u32,
+    pub server: u32,
+    /// IOP address of the server receive buffer.
+    pub buff: u32,
+    /// IOP address of the server cache buffer.
+    pub cbuff: u32,
+}
+
+impl Body for RpcEnd {
+    const CID: u32 = CMD_RPC_END;
+    const WORDS: usize = RpcHeader::WORDS + 4;
+
+    fn encode(&self, words: &mut [u32]) {
+        self.rpc.encode(words);
+        words[4] = self.cid;
+        words[5] = self.server;
+        words[6] = self.buff;
+        words[7] = self.cbuff;
+    }
+
+    fn decode(words: &[u32]) -> Self {
+        Self {
+            rpc: RpcHeader::decode(words),
+            cid: words[4],
+            server: words[5],
+            buff: words[6],
+            cbuff: words[7],
+        }
+    }
+}
+
+/// Request from the IOP to send it a block of EE memory.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RpcRdata {
+    /// The client field holds the IOP receive descriptor, to be echoed in the end packet.
+    pub rpc: RpcHeader,
+    pub src: u32,
+    pub dest: u32,
+    pub size: u32,
+}
+
+impl Body for RpcRdata {
+    const CID: u32 = CMD_RPC_RDATA;
+    const WORDS: usize = RpcHeader::WORDS + 3;
+
+    fn encode(&self, words: &mut [u32]) {
+        self.rpc.encode(words);
+        words[4] = self.src;
+        words[5] = self.dest;
+        words[6] = self.size;
+    }
+
+    fn decode(words: &[u32]) -> Self {
+        Self {
+            rpc: RpcHeader::decode(words),
+            src: words[4],
+            dest: words[5],
+            size: words[6],
+        }
+    }
+}
+
+/// Encode the arguments of an RPC call as little endian words, padded with zeros to `M` bytes.
+pub fn words<const N: usize, const M: usize>(words: [u32; N]) -> [u8; M] {
+    assert!(N * 4 <= M, "too many words for the arguments");
+
+    let mut arg = [0; M];
+    for (chunk, word) in arg.chunks_exact_mut(4).zip(words) {
+        chunk.copy_from_slice(&word.to_le_bytes());
+    }
+    arg
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sif_header_encoding() {
+        let header = CmdHeader {
+            psize: 64,
+            dsize: 0x12_3456,
+            dest: 0x1_0000,
+            cid: CMD_RPC_CALL,
+            opt: 7,
+        };
+
+        let mut words = [0; 4];
+        header.encode(&mut words);
+        assert_eq!(words, [0x1234_5640, 0x1_0000, 0x8000_000a, 7]);
+        assert_eq!(CmdHeader::decode(&words), header);
+    }
+
+    #[test]
+    fn test_sif_rpc_call_encoding() {
+        let call = RpcCall {
+            rpc: RpcHeader {
+                rec_id: 0,
+                pkt_addr: 0,
+                rpc_id: 3,
+                client: 0x0010_0040,
+            },
+            rpc_number: 5,
+            send_size: 32,
+            receive: 0x0020_0000,
+            recv_size: 64,
+            rmode: 1,
+            server: 0x0003_0000,
+        };
+
+        let mut packet = RawPacket::new();
+        crate::encode(&mut packet, CmdHeader::default(), &call);
+
+        assert_eq!(RpcCall::packet_size(), RPC_PACKET_SIZE);
+        assert_eq!(packet.size(), RPC_PACKET_SIZE);
+        assert_eq!(packet.header().cid, CMD_RPC_CALL);
+        assert_eq!(
+            &packet.0[4..14],
+            &[0, 0, 3, 0x0010_0040, 5, 32, 0x0020_0000, 64, 1, 0x0003_0000]
+        );
+        assert_eq!(crate::decode::<RpcCall>(&packet), Some(call));
+        assert_eq!(crate::decode::<RpcEnd>(&packet), None);
+    }
+
+    #[test]
+    fn test_sif_rpc_end_decoding() {
+        let mut packet = RawPacket::new();
+        packet.0[..12].copy_from_slice(&[
+            48,
+            0,
+            CMD_RPC_END,
+            0,
+            0,
+            0,
+            1,
+            0x0010_0040,
+            0x8000_0009,
+            0x0003_0000,
+            0x0004_0000,
+            0x0005_0000,
+        ]);
+
+        let end = crate::decode::<RpcEnd>(&packet).unwrap();
+        assert_eq!(end.rpc.client, 0x0010_0040);
+        assert_eq!(end.cid, 0x8000_0009);
+        assert_eq!(end.server, 0x0003_0000);
+        assert_eq!(end.buff, 0x0004_0000);
+        assert_eq!(end.cbuff, 0x0005_0000);
+
+        assert_eq!(SetSreg::packet_size(), 24);
+    }
+
+    #[test]
+    fn test_sif_reset_encoding() {
+        let mut cmd = ResetCmd::default();
+        cmd.arg[..5].copy_from_slice(b"rom0:");
+        cmd.arglen = 5;
+
+        let mut packet = RawPacket::new();
+        crate::encode(&mut packet, CmdHeader::default(), &cmd);
+
+        assert_eq!(packet.size(), 104);
+        assert_eq!(packet.header().cid, CMD_RESET_CMD);
+        assert_eq!(&packet.0[4..8], &[5, 0, 0x306d_6f72, 0x3a]);
+        assert_eq!(crate::decode::<ResetCmd>(&packet), Some(cmd));
+    }
+
+    #[test]
+    fn test_rpc_arg_words() {
+        let arg: [u8; 12] = words([1, 0x1234_5678]);
+        assert_eq!(arg, [1, 0, 0, 0, 0x78, 0x56, 0x34, 0x12, 0, 0, 0, 0]);
+    }
+}
@@ -0,0 +1,99 @@
+use alloc::vec;
+use alloc::vec::Vec;
+
+use rps2_kernel::arch::{self, CacheAligned};
+
+use crate::cmd;
+
+/// Buffer usable as the source or destination of a SIF transfer.
+pub(crate) struct DmaBuf(Vec<CacheAligned<[u8; 64]>>);
+
+impl DmaBuf {
+    pub(crate) fn new(len: usize) -> Self {
+        Self(vec![CacheAligned([0; 64]); len.div_ceil(64)])
+    }
+
+    pub(crate) fn from_slice(src: &[u8]) -> Self {
+        let mut buf = Self::new(src.len());
+        buf.as_mut_slice(src.len()).copy_from_slice(src);
+        buf
+    }
+
+    /// Capacity of the buffer, in bytes.
+    pub(crate) fn len(&self) -> usize {
+        self.0.len() * 64
+    }
+
+    pub(crate) fn as_ptr(&self) -> *const u8 {
+        self.0.as_ptr() as *const u8
+    }
+
+    pub(crate) fn as_mut_ptr(&mut self) -> *mut u8 {
+        self.0.as_mut_ptr() as *mut u8
+    }
+
+    pub(crate) fn as_slice(&self, len: usize) -> &[u8] {
+        unsafe { core::slice::from_raw_parts(self.as_ptr(), len) }
+    }
+
+    pub(crate) fn as_mut_slice(&mut self, len: usize) -> &mut [u8] {
+        unsafe { core::slice::from_raw_parts_mut(self.as_mut_ptr(), len) }
+    }
+}
+
+/// Read into `buf` from an IOP service which only transfers whole blocks of `align` bytes.
+///
+/// The aligned part is sent straight to the destination, while the unaligned head and tail are
+/// stored in a separate block laid out as head size, tail size, head address, tail address,
+/// head data, tail data. `call` receives the physical addresses of the destination and of that
+/// block, and returns the number of bytes read.
+pub(crate) fn read_split<E>(
+    buf: &mut [u8],
+    align: usize,
+    call: impl FnOnce(u32, u32) -> Result<usize, E>,
+) -> Result<usize, E> {
+    if buf.is_empty() {
+        return Ok(0);
+    }
+
+    let len = buf.len();
+    let ends_size = 16 + 2 * align;
+    let mut bounce = (buf.as_ptr() as usize % 64 != 0 || len % 64 != 0).then(|| DmaBuf::new(len));
+    let ends = DmaBuf::new(ends_size);
+
+    let dest_ptr = bounce.as_mut().map_or(buf.as_mut_ptr(), DmaBuf::as_mut_ptr);
+    let dest = cmd::phys_addr(dest_ptr);
+
+    let invalidate = |dest_ptr: *mut u8, ends: &DmaBuf| unsafe {
+        arch::cache_dhwbin(dest_ptr as *const (), len.next_multiple_of(64));
+        arch::cache_dhwbin(ends.as_ptr() as *const (), ends_size.next_multiple_of(64));
+    };
+
+    invalidate(dest_ptr, &ends);
+    let res = call(dest, cmd::phys_addr(ends.as_ptr()));
+    invalidate(dest_ptr, &ends);
+    let read = res?.min(len);
+
+    let target = match bounce.as_mut() {
+        Some(bounce) => bounce.as_mut_slice(len),
+        None => &mut *buf,
+    };
+
+    let raw = ends.as_slice(ends_size);
+    let word = |offset: usize| u32::from_le_bytes(raw[offset..offset + 4].try_into().unwrap());
+    for (size, addr, data) in [
+        (word(0), word(8), &raw[16..16 + align]),
+        (word(4), word(12), &raw[16 + align..]),
+    ] {
+        let size = (size as usize).min(align);
+        let offset = addr.wrapping_sub(dest) as usize;
+        if size > 0 && offset + size <= len {
+            target[offset..offset + size].copy_from_slice(&data[..size]);
+        }
+    }
+
+    if let Some(bounce) = bounce {
+        buf[..read].copy_from_slice(&bounce.as_slice(len)[..read]);
+    }
+    Ok(read)
+}
@@ -0,0 +1,486 @@
+//! Disc access through the CDVDFSV services, resident on the IOP since boot.
+//!
+//! Files on ISO9660 discs can be located with [`iso9660`] on top of [`Disc`], instead of going
+//! through `cdrom0:` for every lookup.
+
+use core::convert::Infallible;
+use core::sync::atomic::{AtomicU32, Ordering};
+use core::time::Duration;
+
+use rps2_kernel::arch;
+use rps2_thread::thread;
+
+use crate::buf::DmaBuf;
+use crate::cmd;
+use crate::iop;
+use crate::proto;
+use crate::rpc::{ClientPool, Service, SifRpcClient};
+
+pub use rps2_formats::iso9660;
+
+/// Server id of the initialization service.
+pub const INIT_SID: u32 = 0x8000_0592;
+
+/// Server id of the S commands service, which returns immediately.
+pub const SCMD_SID: u32 = 0x8000_0593;
+
+/// Server id of the N commands service, which drives the mechanism.
+pub const NCMD_SID: u32 = 0x8000_0595;
+
+/// Server id of the disc ready service.
+pub const DISK_READY_SID: u32 = 0x8000_059a;
+
+/// Size of a sector as read by [`read_sectors`], without headers and error correction.
+pub const SECTOR_SIZE: usize = 2048;
+
+/// Size of the fragment record filled by the IOP, a header and two cache lines.
+const FRAGMENTS_SIZE: usize = 16 + 2 * 64;
+
+const RETRY: Duration = Duration::from_millis(1);
+
+static SCMD: ClientPool = ClientPool::new(SCMD_SID);
+static NCMD: ClientPool = ClientPool::new(NCMD_SID);
+static DISK_READY: ClientPool = ClientPool::new(DISK_READY_SID);
+static INIT: Service = Service::new(INIT_SID);
+
+/// Number of N commands in progress.
+static IN_FLIGHT: AtomicU32 = AtomicU32::new(0);
+
+mod scmd_fno {
+    pub const GET_DISK_TYPE: u32 = 0x03;
+    pub const GET_ERROR: u32 = 0x04;
+    pub const TRAY_REQ: u32 = 0x05;
+    pub const STATUS: u32 = 0x1c;
+}
+
+mod ncmd_fno {
+    pub const READ: u32 = 0x01;
+    pub const STANDBY: u32 = 0x06;
+    pub const STOP: u32 = 0x07;
+    pub const STREAM: u32 = 0x09;
+}
+
+mod stream_cmd {
+    pub const START: u32 = 1;
+    pub const READ: u32 = 2;
+    pub const STOP: u32 = 3;
+    pub const SEEK: u32 = 4;
+    pub const INIT: u32 = 5;
+}
+
+/// Initialization without waiting for a disc.
+const INIT_NO_DISC: u32 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    /// The command was aborted.
+    Aborted,
+    /// The tray is open.
+    TrayOpen,
+    NoDisc,
+    /// The drive is not ready to accept the command.
+    NotReady,
+    /// The sector could not be read.
+    ReadFailed,
+    /// The read went past the end of the disc.
+    EndOfMedia,
+    /// The buffer length is not a multiple of [`SECTOR_SIZE`].
+    InvalidLength,
+    /// The IOP ran out of memory.
+    NoMemory,
+    /// The driver refused the command.
+    Rejected,
+    /// Any other drive error code.
+    Other(i32),
+}
+
+impl Error {
+    pub fn from_code(code: i32) -> Self {
+        match code {
+            0x01 => Self::Aborted,
+            0x11 => Self::TrayOpen,
+            0x12 => Self::NoDisc,
+            0x13 => Self::NotReady,
+            0x30 => Self::ReadFailed,
+            0x32 => Self::EndOfMedia,
+            code => Self::Other(code),
+        }
+    }
+}
+
+pub type Result<T> = core::result::Result<T, Error>;
+
+fn call<const N: usize>(pool: &ClientPool, fno: u32, send: &[u8]) -> [i32; N] {
+    let mut recv = [0; 16];
+    pool.get().call(fno, send, &mut recv[..N * 4]);
+
+    let mut words = [0; N];
+    for (word, chunk) in words.iter_mut().zip(recv.chunks_exact(4)) {
+        *word = i32::from_le_bytes(chunk.try_into().unwrap());
+    }
+    words
+}
+
+/// Call an N command, which is tracked by [`sync`].
+fn call_ncmd<const N: usize>(fno: u32, send: &[u8]) -> [i32; N] {
+    IN_FLIGHT.fetch_add(1, Ordering::Relaxed);
+    let res = call(&NCMD, fno, send);
+    IN_FLIGHT.fetch_sub(1, Ordering::Release);
+    res
+}
+
+/// Initialize the drive, unless it is already.
+///
+/// Called by every other function, and needed again after an IOP reset.
+pub fn init() {
+    // The service is resident, only the drive needs initializing
+    let Ok(()) = INIT.init(|_| {
+        SifRpcClient::bind(INIT_SID).call(0, &INIT_NO_DISC.to_le_bytes(), &mut []);
+        Ok::<_, Infallible>(())
+    });
+}
+
+/// Kind of disc in the drive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiscType {
+    NoDisc,
+    /// The drive is still detecting the disc.
+    Detecting,
+    DetectingCd,
+    DetectingDvdSingle,
+    DetectingDvdDual,
+    Unknown,
+    Ps1Cd,
+    Ps1CdDa,
+    Ps2Cd,
+    Ps2CdDa,
+    Ps2Dvd,
+    AudioCd,
+    VideoDvd,
+    Illegal,
+    Other(u8),
+}
+
+impl DiscType {
+    pub fn from_raw(raw: u8) -> Self {
+        match raw {
+            0x00 => Self::NoDisc,
+            0x01 => Self::Detecting,
+            0x02 => Self::DetectingCd,
+            0x03 => Self::DetectingDvdSingle,
+            0x04 => Self::DetectingDvdDual,
+            0x05 => Self::Unknown,
+            0x10 => Self::Ps1Cd,
+            0x11 => Self::Ps1CdDa,
+            0x12 => Self::Ps2Cd,
+            0x13 => Self::Ps2CdDa,
+            0x14 => Self::Ps2Dvd,
+            0xfd => Self::AudioCd,
+            0xfe => Self::VideoDvd,
+            0xff => Self::Illegal,
+            other => Self::Other(other),
+        }
+    }
+
+    /// Whether the drive is still figuring out the disc type.
+    pub fn is_detecting(&self) -> bool {
+        matches!(
+            self,
+            Self::Detecting | Self::DetectingCd | Self::DetectingDvdSingle | Self::DetectingDvdDual
+        )
+    }
+}
+
+pub fn disc_type() -> DiscType {
+    init();
+    let [raw] = call(&SCMD, scmd_fno::GET_DISK_TYPE, &[]);
+    DiscType::from_raw(raw as u8)
+}
+
+/// Error of the last N command, `None` if it succeeded.
+pub fn last_error() -> Option<Error> {
+    init();
+    match call(&SCMD, scmd_fno::GET_ERROR, &[]) {
+        [0] => None,
+        [code] => Some(Error::from_code(code)),
+    }
+}
+
+/// State of the mechanism.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Status(pub u8);
+
+impl Status {
+    pub fn is_stopped(&self) -> bool {
+        self.0 & !0x01 == 0
+    }
+
+    pub fn is_tray_open(&self) -> bool {
+        self.0 & 0x01 != 0
+    }
+
+    pub fn is_spinning(&self) -> bool {
+        self.0 & 0x02 != 0
+    }
+
+    pub fn is_reading(&self) -> bool {
+        self.0 & 0x04 != 0
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.0 & 0x08 != 0
+    }
+
+    pub fn is_seeking(&self) -> bool {
+        self.0 & 0x10 != 0
+    }
+
+    /// Whether the drive stopped because of an error.
+    pub fn is_emergency(&self) -> bool {
+        self.0 & 0x20 != 0
+    }
+}
+
+pub fn status() -> Status {
+    init();
+    let [raw] = call(&SCMD, scmd_fno::STATUS, &[]);
+    Status(raw as u8)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrayRequest {
+    Open = 0,
+    Close = 1,
+    /// Only check if the tray moved.
+    Check = 2,
+}
+
+/// Move the tray, returns whether it was opened or closed since the previous request.
+pub fn tray_request(req: TrayRequest) -> Result<bool> {
+    init();
+    let [res, changed] = call(&SCMD, scmd_fno::TRAY_REQ, &(req as u32).to_le_bytes());
+    if res == 0 {
+        return Err(Error::Rejected);
+    }
+    Ok(changed != 0)
+}
+
+/// Whether the drive can accept N commands, optionally waiting until it can.
+pub fn disk_ready(block: bool) -> bool {
+    // Codes returned by the service
+    const COMPLETE: i32 = 0x02;
+
+    init();
+    let [res] = call(&DISK_READY, 0, &(!block as u32).to_le_bytes());
+    res == COMPLETE
+}
+
+/// Whether all N commands are done, optionally waiting for them.
+///
+/// Commands issued by this module are synchronous, this is only meaningful for commands sent
+/// from other threads.
+pub fn sync(block: bool) -> bool {
+    loop {
+        if IN_FLIGHT.load(Ordering::Acquire) == 0 {
+            return true;
+        }
+        if !block {
+            return false;
+        }
+        thread::sleep_for(RETRY);
+    }
+}
+
+/// Rotation speed of the disc.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Spindle {
+    /// Fastest speed, dropping down on read errors.
+    Max = 0,
+    /// Nominal speed for the disc.
+    #[default]
+    Nominal = 1,
+    /// Speed for CDDA streams.
+    Cdda = 2,
+    /// Slowest speed for DVDs.
+    DvdSlow = 3,
+}
+
+/// Options of a read.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ReadMode {
+    /// Retries on read errors, `0` means the driver default.
+    pub retries: u8,
+    pub spindle: Spindle,
+}
+
+impl ReadMode {
+    fn encode(&self) -> u32 {
+        // Retries, spindle, pattern (2048 byte sectors), padding
+        u32::from_le_bytes([self.retries, self.spindle as u8, 0, 0])
+    }
+}
+
+/// Read sectors starting at `lbn` into `buf`, whose length must be a multiple of
+/// [`SECTOR_SIZE`].
+///
+/// Blocks until the data is in `buf`.
+pub fn read_sectors(lbn: u32, buf: &mut [u8], mode: ReadMode) -> Result<()> {
+    if buf.len() % SECTOR_SIZE != 0 {
+        return Err(Error::InvalidLength);
+    }
+    if buf.is_empty() {
+        return Ok(());
+    }
+
+    init();
+
+    let len = buf.len();
+    let mut bounce = (buf.as_ptr() as usize % 64 != 0).then(|| DmaBuf::new(len));
+    let dest_ptr = bounce.as_mut().map_or(buf.as_mut_ptr(), DmaBuf::as_mut_ptr);
+
+    // The destination is aligned so no fragments should come back, but the IOP still writes
+    // the fragment record
+    let ends = DmaBuf::new(FRAGMENTS_SIZE);
+    let invalidate = || unsafe {
+        arch::cache_dhwbin(dest_ptr as *const (), len);
+        arch::cache_dhwbin(ends.as_ptr() as *const (), FRAGMENTS_SIZE);
+    };
+
+    invalidate();
+    let arg: [u8; 24] = proto::words([
+        lbn,
+        (len / SECTOR_SIZE) as u32,
+        cmd::phys_addr(dest_ptr),
+        cmd::phys_addr(ends.as_ptr()),
+        mode.encode(),
+    ]);
+    let [res] = call_ncmd(ncmd_fno::READ, &arg);
+    invalidate();
+
+    if res == 0 {
+        return Err(Error::Rejected);
+    }
+    if let Some(err) = last_error() {
+        return Err(err);
+    }
+
+    if let Some(bounce) = bounce {
+        buf.copy_from_slice(bounce.as_slice(len));
+    }
+    Ok(())
+}
+
+/// Spin the disc up and move the head to the start.
+pub fn standby() -> Result<()> {
+    init();
+    match call_ncmd(ncmd_fno::STANDBY, &[]) {
+        [0] => Err(Error::Rejected),
+        _ => Ok(()),
+    }
+}
+
+/// Stop the disc.
+pub fn stop() -> Result<()> {
+    init();
+    match call_ncmd(ncmd_fno::STOP, &[]) {
+        [0] => Err(Error::Rejected),
+        _ => Ok(()),
+    }
+}
+
+/// The disc as a sequence of sectors, for [`iso9660`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Disc {
+    pub mode: ReadMode,
+}
+
+impl iso9660::SectorRead for Disc {
+    type Error = Error;
+
+    fn read_sectors(&mut self, lbn: u32, buf: &mut [u8]) -> Result<()> {
+        read_sectors(lbn, buf, self.mode)
+    }
+}
+
+/// Streaming reads, buffered on the IOP ahead of the EE.
+///
+/// Meant for data read sequentially, like videos and music, the drive keeps reading into the
+/// ring buffer while the EE consumes it.
+#[derive(Debug)]
+pub struct Stream {
+    buf: u32,
+    mode: ReadMode,
+}
+
+impl Stream {
+    /// Allocate a ring buffer of `banks` banks of `bank_sectors` sectors on the IOP.
+    pub fn new(bank_sectors: u32, banks: u32, mode: ReadMode) -> Result<Self> {
+        init();
+
+        let size = (bank_sectors * banks) as usize * SECTOR_SIZE;
+        let buf = iop::alloc_heap(size).ok_or(Error::NoMemory)?;
+        let stream = Self { buf, mode };
+
+        stream.command(stream_cmd::INIT, bank_sectors * banks, banks, buf)?;
+        Ok(stream)
+    }
+
+    fn command(&self, cmd: u32, lbn: u32, sectors: u32, buf: u32) -> Result<u32> {
+        let arg: [u8; 24] = proto::words([lbn, sectors, buf, cmd, self.mode.encode()]);
+        match call_ncmd(ncmd_fno::STREAM, &arg) {
+            [0] if cmd != stream_cmd::READ => Err(Error::Rejected),
+            [res] => Ok(res as u32),
+        }
+    }
+
+    /// Start streaming from sector `lbn`.
+    pub fn start(&mut self, lbn: u32) -> Result<()> {
+        self.command(stream_cmd::START, lbn, 0, 0)?;
+        Ok(())
+    }
+
+    /// Continue streaming from sector `lbn`.
+    pub fn seek(&mut self, lbn: u32) -> Result<()> {
+        self.command(stream_cmd::SEEK, lbn, 0, 0)?;
+        Ok(())
+    }
+
+    /// Copy the buffered sectors into `buf`, returns the number of sectors copied.
+    ///
+    /// Does not wait for sectors to be buffered, `0` means the drive is behind.
+    pub fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        if buf.len() % SECTOR_SIZE != 0 {
+            return Err(Error::InvalidLength);
+        }
+
+        let len = buf.len();
+        let mut bounce = (buf.as_ptr() as usize % 64 != 0).then(|| DmaBuf::new(len));
+        let dest_ptr = bounce.as_mut().map_or(buf.as_mut_ptr(), DmaBuf::as_mut_ptr);
+
+        unsafe { arch::cache_dhwbin(dest_ptr as *const (), len) };
+        let sectors = (len / SECTOR_SIZE) as u32;
+        let read = self.command(stream_cmd::READ, 0, sectors, cmd::phys_addr(dest_ptr))?;
+        unsafe { arch::cache_dhwbin(dest_ptr as *const (), len) };
+
+        let read = (read as usize).min(len / SECTOR_SIZE);
+        if let Some(bounce) = bounce {
+            let len = read * SECTOR_SIZE;
+            buf[..len].copy_from_slice(bounce.as_slice(len));
+        }
+        Ok(read)
+    }
+
+    /// Stop streaming, the buffer can be started again.
+    pub fn stop(&mut self) -> Result<()> {
+        self.command(stream_cmd::STOP, 0, 0, 0)?;
+        Ok(())
+    }
+}
+
+impl Drop for Stream {
+    fn drop(&mut self) {
+        // The drive must not write to the buffer anymore once freed
+        let _ = self.stop();
+        iop::free_heap(self.buf);
+    }
+}
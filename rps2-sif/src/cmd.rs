@@ -0,0 +1,363 @@
+//! SIF command layer.
+//!
+//! Commands are small packets sent to the IOP command buffer over SIF1, optionally preceded by
+//! a block of extra data. Packets sent by the IOP land in a receive buffer over SIF0 and are
+//! dispatched from the DMAC interrupt to the handler registered for their command id.
+
+use core::cell::{RefCell, UnsafeCell};
+use core::ffi::c_void;
+use core::mem;
+use core::ptr;
+use critical_section::Mutex;
+
+use rps2_kernel::arch;
+use rps2_kernel::interrupt::{DmacChannel, DmacHandler, HandlerContext};
+use rps2_kernel::os::{self, SifDmaTransfer};
+
+use crate::proto::{self, Body, ChangeSaddr, CmdHeader, InitCmd, RawPacket, SetSreg};
+use crate::proto::{CMD_CHANGE_SADDR, CMD_SET_SREG, CMD_SYSTEM, MAX_PACKET_SIZE};
+
+/// SIF registers, accessed with `sif_get_reg` and `sif_set_reg`.
+pub mod reg {
+    pub const MAINADDR: u32 = 1;
+    pub const SUBADDR: u32 = 2;
+    pub const MSFLAG: u32 = 3;
+    pub const SMFLAG: u32 = 4;
+
+    /// Address of the IOP command buffer, as seen by the current program.
+    pub const SYS_SUBADDR: u32 = 0x8000_0000;
+    /// Address of the EE receive buffer, as seen by the current program.
+    pub const SYS_MAINADDR: u32 = 0x8000_0001;
+    /// Set once the RPC layer has been initialized.
+    pub const SYS_RPCINIT: u32 = 0x8000_0002;
+
+    pub const STAT_SIFINIT: u32 = 0x10000;
+    pub const STAT_CMDINIT: u32 = 0x20000;
+    pub const STAT_BOOTEND: u32 = 0x40000;
+}
+
+/// Number of handler slots, for both system and user commands.
+pub const HANDLERS: usize = 32;
+
+/// Number of software registers.
+pub const SREGS: usize = 32;
+
+const DMA_INT_O: i32 = 0x4;
+const DMA_ERT: i32 = 0x40;
+
+/// Handler of an incoming command, called from the SIF0 DMAC interrupt.
+pub type CmdHandler = fn(&RawPacket, &HandlerContext);
+
+/// Extra data sent along with a command, copied to `dest` in IOP memory before the packet.
+#[derive(Debug, Clone, Copy)]
+pub struct Data<'a> {
+    /// Must be aligned to 16 bytes.
+    pub src: &'a [u8],
+    pub dest: u32,
+}
+
+struct State {
+    handler: Option<DmacHandler>,
+    iop_buf: u32,
+    sys_handlers: [Option<CmdHandler>; HANDLERS],
+    usr_handlers: [Option<CmdHandler>; HANDLERS],
+    sregs: [i32; SREGS],
+}
+
+static STATE: Mutex<RefCell<State>> = Mutex::new(RefCell::new(State {
+    handler: None,
+    iop_buf: 0,
+    sys_handlers: [None; HANDLERS],
+    usr_handlers: [None; HANDLERS],
+    sregs: [0; SREGS],
+}));
+
+struct RecvBuf(UnsafeCell<RawPacket>);
+
+// SAFETY: Only accessed through the uncached segment from the SIF0 handler
+unsafe impl Sync for RecvBuf {}
+
+static RECV_BUF: RecvBuf = RecvBuf(UnsafeCell::new(RawPacket::new()));
+
+/// Physical address of `ptr`, as used by the DMA controllers.
+pub fn phys_addr<T>(ptr: *const T) -> u32 {
+    (ptr as usize & 0x1fff_ffff) as u32
+}
+
+fn sif0_handler(ctx: &HandlerContext) {
+    let buf = arch::uncached_seg_mut(RECV_BUF.0.get()) as *mut u32;
+
+    let size = unsafe { ptr::read_volatile(buf) } as usize & 0xff;
+    if size != 0 {
+        let mut packet = RawPacket::new();
+        for i in 0..size.min(MAX_PACKET_SIZE).div_ceil(4) {
+            packet.0[i] = unsafe { ptr::read_volatile(buf.add(i)) };
+        }
+        // Mark the buffer as consumed
+        unsafe { ptr::write_volatile(buf, 0) };
+
+        let cid = packet.header().cid;
+        let idx = (cid & !CMD_SYSTEM) as usize;
+        let handler = critical_section::with(|cs| {
+            let state = STATE.borrow_ref(cs);
+            let table = if cid & CMD_SYSTEM != 0 {
+                &state.sys_handlers
+            } else {
+                &state.usr_handlers
+            };
+            table.get(idx).copied().flatten()
+        });
+
+        if let Some(handler) = handler {
+            handler(&packet, ctx);
+        }
+    }
+
+    // Re-arm the receive channel
+    ctx.sif_set_d_chain();
+}
+
+fn change_saddr_handler(packet: &RawPacket, _ctx: &HandlerContext) {
+    if let Some(body) = proto::decode::<ChangeSaddr>(packet) {
+        critical_section::with(|cs| STATE.borrow_ref_mut(cs).iop_buf = body.addr);
+    }
+}
+
+fn set_sreg_handler(packet: &RawPacket, _ctx: &HandlerContext) {
+    if let Some(body) = proto::decode::<SetSreg>(packet) {
+        set_sreg(body.index as usize, body.value);
+    }
+}
+
+/// Initialize the command layer, does nothing if it is already initialized.
+///
+/// If no program initialized SIF since the IOP booted, this blocks until the IOP side is up.
+pub fn init() {
+    let initialized = critical_section::with(|cs| {
+        let mut state = STATE.borrow_ref_mut(cs);
+        if state.handler.is_some() {
+            return true;
+        }
+
+        let handler = DmacHandler::register(DmacChannel::Sif0, sif0_handler)
+            .expect("Failed to register SIF0 handler");
+        state.handler = Some(handler);
+        state.sys_handlers[(CMD_CHANGE_SADDR & !CMD_SYSTEM) as usize] = Some(change_saddr_handler);
+        state.sys_handlers[(CMD_SET_SREG & !CMD_SYSTEM) as usize] = Some(set_sreg_handler);
+        state.sregs = [0; SREGS];
+        false
+    });
+    if initialized {
+        return;
+    }
+
+    let buf = phys_addr(RECV_BUF.0.get());
+    unsafe {
+        // The handler reads the buffer uncached, so no dirty line may be written back over it
+        arch::cache_dhwbin(RECV_BUF.0.get() as *const (), mem::size_of::<RawPacket>());
+        os::sif_set_d_chain();
+
+        let mut iop_buf = os::sif_get_reg(reg::SYS_SUBADDR) as u32;
+        if iop_buf == 0 {
+            // First program to use SIF since the IOP booted, wait for it
+            while os::sif_get_reg(reg::SMFLAG) as u32 & reg::STAT_SIFINIT == 0 {}
+
+            iop_buf = os::sif_get_reg(reg::SUBADDR) as u32;
+            os::sif_set_reg(reg::MSFLAG, reg::STAT_SIFINIT as i32);
+            os::sif_set_reg(reg::SYS_SUBADDR, iop_buf as i32);
+            os::sif_set_reg(reg::SYS_MAINADDR, buf as i32);
+        }
+
+        critical_section::with(|cs| STATE.borrow_ref_mut(cs).iop_buf = iop_buf);
+    }
+
+    send(&InitCmd { buf }, 0, None);
+}
+
+/// Tear down the command layer, it must be initialized again before use.
+///
+/// Used when the IOP gets reset.
+pub fn deinit() {
+    let handler = critical_section::with(|cs| {
+        let mut state = STATE.borrow_ref_mut(cs);
+        state.iop_buf = 0;
+        state.handler.take()
+    });
+    drop(handler);
+}
+
+/// Whether [`init`] has been called.
+pub fn is_initialized() -> bool {
+    critical_section::with(|cs| STATE.borrow_ref(cs).handler.is_some())
+}
+
+/// Set the handler for command `cid`, returning the previous one.
+///
+/// # Panics
+/// If the index of `cid` is not below [`HANDLERS`].
+pub fn set_handler(cid: u32, handler: Option<CmdHandler>) -> Option<CmdHandler> {
+    let idx = (cid & !CMD_SYSTEM) as usize;
+    assert!(idx < HANDLERS, "Invalid SIF command id {cid:#x}");
+
+    critical_section::with(|cs| {
+        let mut state = STATE.borrow_ref_mut(cs);
+        let table = if cid & CMD_SYSTEM != 0 {
+            &mut state.sys_handlers
+        } else {
+            &mut state.usr_handlers
+        };
+        mem::replace(&mut table[idx], handler)
+    })
+}
+
+/// Read a software register, set by the IOP with `SET_SREG` commands.
+pub fn sreg(index: usize) -> i32 {
+    critical_section::with(|cs| STATE.borrow_ref(cs).sregs[index])
+}
+
+pub fn set_sreg(index: usize, value: i32) {
+    critical_section::with(|cs| {
+        if let Some(sreg) = STATE.borrow_ref_mut(cs).sregs.get_mut(index) {
+            *sreg = value;
+        }
+    });
+}
+
+/// Send the first `size` bytes of `packet` as command `cid`, along with `data`.
+///
+/// The size, destination and command id in the header are filled in, the option word is kept.
+/// Blocks until the DMA transfer is complete, not until the IOP handled the command.
+///
+/// # Panics
+/// If the command layer is not initialized, or the size or data are invalid.
+pub fn send_cmd(cid: u32, packet: &mut RawPacket, size: usize, data: Option<Data>) {
+    let (transfers, count) = prepare(cid, packet, size, data);
+    dma_transfer(&transfers[..count]);
+}
+
+/// Same as [`send_cmd`], from an interrupt handler.
+pub fn irq_send_cmd(
+    ctx: &HandlerContext,
+    cid: u32,
+    packet: &mut RawPacket,
+    size: usize,
+    data: Option<Data>,
+) {
+    let (transfers, count) = prepare(cid, packet, size, data);
+    unsafe {
+        let id = loop {
+            let id = ctx.sif_set_dma(transfers.as_ptr(), count as i32);
+            if id != 0 {
+                break id;
+            }
+        };
+        // The packet lives on the stack, so it has to be waited for here as well
+        while ctx.sif_dma_stat(id) >= 0 {}
+    }
+}
+
+fn prepare(
+    cid: u32,
+    packet: &mut RawPacket,
+    size: usize,
+    data: Option<Data>,
+) -> ([SifDmaTransfer; 2], usize) {
+    assert!(
+        (CmdHeader::WORDS * 4..=MAX_PACKET_SIZE).contains(&size) && size % 4 == 0,
+        "Invalid SIF packet size {size}"
+    );
+
+    let iop_buf = critical_section::with(|cs| STATE.borrow_ref(cs).iop_buf);
+    assert!(iop_buf != 0, "SIF command layer not initialized");
+
+    let mut header = CmdHeader {
+        psize: size as u8,
+        cid,
+        opt: packet.0[3],
+        ..Default::default()
+    };
+
+    let mut transfers = [const {
+        SifDmaTransfer {
+            src: ptr::null(),
+            dst: ptr::null_mut(),
+            size: 0,
+            attr: 0,
+        }
+    }; 2];
+    let mut count = 0;
+
+    if let Some(data) = data.filter(|data| !data.src.is_empty()) {
+        assert!(
+            data.src.as_ptr() as usize % 16 == 0,
+            "SIF extra data must be aligned to 16 bytes"
+        );
+
+        header.dsize = data.src.len() as u32;
+        header.dest = data.dest;
+
+        unsafe { arch::cache_dhwbin(data.src.as_ptr() as *const (), data.src.len()) };
+        transfers[count] = SifDmaTransfer {
+            src: data.src.as_ptr() as *const c_void,
+            dst: data.dest as usize as *mut c_void,
+            size: data.src.len() as i32,
+            attr: 0,
+        };
+        count += 1;
+    }
+
+    header.encode(&mut packet.0[..CmdHeader::WORDS]);
+
+    unsafe { arch::cache_dhwbin(packet.as_ptr() as *const (), mem::size_of::<RawPacket>()) };
+    transfers[count] = SifDmaTransfer {
+        src: packet.as_ptr() as *const c_void,
+        dst: iop_buf as usize as *mut c_void,
+        size: size.next_multiple_of(16) as i32,
+        attr: DMA_ERT | DMA_INT_O,
+    };
+    count += 1;
+
+    (transfers, count)
+}
+
+/// Queue `transfers` on SIF1 and wait for their completion.
+pub(crate) fn dma_transfer(transfers: &[SifDmaTransfer]) {
+    unsafe {
+        let id = loop {
+            // Zero means the kernel queue is full
+            let id = os::sif_set_dma(transfers.as_ptr(), transfers.len() as i32);
+            if id != 0 {
+                break id;
+            }
+        };
+        while os::sif_dma_stat(id) >= 0 {}
+    }
+}
+
+/// Encode and send `body`, with option word `opt` and the extra `data`.
+pub fn send<B: Body>(body: &B, opt: u32, data: Option<Data>) {
+    let mut packet = RawPacket::new();
+    proto::encode(
+        &mut packet,
+        CmdHeader {
+            opt,
+            ..Default::default()
+        },
+        body,
+    );
+    send_cmd(B::CID, &mut packet, B::packet_size(), data);
+}
+
+/// Same as [`send`], from an interrupt handler.
+pub fn irq_send<B: Body>(ctx: &HandlerContext, body: &B, opt: u32, data: Option<Data>) {
+    let mut packet = RawPacket::new();
+    proto::encode(
+        &mut packet,
+        CmdHeader {
+            opt,
+            ..Default::default()
+        },
+        body,
+    );
+    irq_send_cmd(ctx, B::CID, &mut packet, B::packet_size(), data);
+}
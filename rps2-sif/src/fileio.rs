@@ -0,0 +1,310 @@
+//! File I/O through the FILEIO service, which forwards requests to the IOP ioman.
+//!
+//! Paths are passed as-is to the IOP, starting with the device name (e.g. `host:`, `mc0:`,
+//! `cdrom0:`, `mass:` or `pfs0:`). Errors are positive IOP errno codes.
+
+use alloc::string::String;
+
+use rps2_kernel::arch;
+
+use crate::buf::{self, DmaBuf};
+use crate::cmd;
+use crate::proto;
+use crate::rpc::ClientPool;
+
+pub use rps2_formats::mcfs::Time;
+
+/// Server id of the FILEIO service.
+pub const FILEIO_SID: u32 = 0x8000_0001;
+
+/// Maximum length of a path, terminator included.
+pub const PATH_MAX: usize = 256;
+
+static FILEIO: ClientPool = ClientPool::new(FILEIO_SID);
+
+mod fno {
+    pub const OPEN: u32 = 0;
+    pub const CLOSE: u32 = 1;
+    pub const READ: u32 = 2;
+    pub const WRITE: u32 = 3;
+    pub const LSEEK: u32 = 4;
+    pub const REMOVE: u32 = 6;
+    pub const MKDIR: u32 = 7;
+    pub const RMDIR: u32 = 8;
+    pub const DOPEN: u32 = 9;
+    pub const DCLOSE: u32 = 10;
+    pub const DREAD: u32 = 11;
+    pub const GETSTAT: u32 = 12;
+}
+
+/// Flags of [`open`].
+pub mod flags {
+    pub const RDONLY: u32 = 0x0001;
+    pub const WRONLY: u32 = 0x0002;
+    pub const RDWR: u32 = 0x0003;
+    pub const NBLOCK: u32 = 0x0010;
+    pub const APPEND: u32 = 0x0100;
+    pub const CREAT: u32 = 0x0200;
+    pub const TRUNC: u32 = 0x0400;
+    pub const EXCL: u32 = 0x0800;
+}
+
+/// Origins of [`lseek`].
+pub mod whence {
+    pub const SET: u32 = 0;
+    pub const CUR: u32 = 1;
+    pub const END: u32 = 2;
+}
+
+/// File type bits of [`Stat::mode`].
+///
+/// Drivers written for the original ioman use the `SO_` encoding, newer ones the `S_` one.
+pub mod mode {
+    pub const SO_IFMT: u32 = 0x0038;
+    pub const SO_IFLNK: u32 = 0x0008;
+    pub const SO_IFREG: u32 = 0x0010;
+    pub const SO_IFDIR: u32 = 0x0020;
+
+    pub const S_IFMT: u32 = 0xf000;
+    pub const S_IFLNK: u32 = 0x4000;
+    pub const S_IFREG: u32 = 0x2000;
+    pub const S_IFDIR: u32 = 0x1000;
+}
+
+/// Error codes returned by the IOP.
+pub mod errno {
+    pub const EPERM: i32 = 1;
+    pub const ENOENT: i32 = 2;
+    pub const EINTR: i32 = 4;
+    pub const EIO: i32 = 5;
+    pub const ENXIO: i32 = 6;
+    pub const E2BIG: i32 = 7;
+    pub const EBADF: i32 = 9;
+    pub const EAGAIN: i32 = 11;
+    pub const ENOMEM: i32 = 12;
+    pub const EACCES: i32 = 13;
+    pub const EFAULT: i32 = 14;
+    pub const EBUSY: i32 = 16;
+    pub const EEXIST: i32 = 17;
+    pub const EXDEV: i32 = 18;
+    pub const ENODEV: i32 = 19;
+    pub const ENOTDIR: i32 = 20;
+    pub const EISDIR: i32 = 21;
+    pub const EINVAL: i32 = 22;
+    pub const ENFILE: i32 = 23;
+    pub const EMFILE: i32 = 24;
+    pub const EFBIG: i32 = 27;
+    pub const ENOSPC: i32 = 28;
+    pub const ESPIPE: i32 = 29;
+    pub const EROFS: i32 = 30;
+    pub const EMLINK: i32 = 31;
+    pub const EPIPE: i32 = 32;
+    pub const EDEADLK: i32 = 45;
+    pub const ENOSYS: i32 = 88;
+    pub const ENOTEMPTY: i32 = 90;
+    pub const ENAMETOOLONG: i32 = 91;
+    pub const ETIMEDOUT: i32 = 116;
+    pub const ENOTSUP: i32 = 134;
+}
+
+/// A positive IOP errno code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Errno(pub i32);
+
+pub type Result<T> = core::result::Result<T, Errno>;
+
+fn check(res: i32) -> Result<i32> {
+    if res < 0 {
+        Err(Errno(-res))
+    } else {
+        Ok(res)
+    }
+}
+
+/// Status of a file, as returned by [`getstat`] and [`dread`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Stat {
+    pub mode: u32,
+    pub attr: u32,
+    pub size: u64,
+    pub ctime: Time,
+    pub atime: Time,
+    pub mtime: Time,
+}
+
+impl Stat {
+    /// Size of the encoded structure.
+    pub const SIZE: usize = 40;
+
+    pub fn decode(raw: &[u8]) -> Self {
+        let word = |offset: usize| u32::from_le_bytes(raw[offset..offset + 4].try_into().unwrap());
+        Self {
+            mode: word(0),
+            attr: word(4),
+            size: (word(36) as u64) << 32 | word(8) as u64,
+            ctime: Time::decode(&raw[12..20]),
+            atime: Time::decode(&raw[20..28]),
+            mtime: Time::decode(&raw[28..36]),
+        }
+    }
+
+    pub fn is_dir(&self) -> bool {
+        self.mode & mode::SO_IFMT == mode::SO_IFDIR || self.mode & mode::S_IFMT == mode::S_IFDIR
+    }
+
+    pub fn is_file(&self) -> bool {
+        self.mode & mode::SO_IFMT == mode::SO_IFREG || self.mode & mode::S_IFMT == mode::S_IFREG
+    }
+
+    pub fn is_symlink(&self) -> bool {
+        self.mode & mode::SO_IFMT == mode::SO_IFLNK || self.mode & mode::S_IFMT == mode::S_IFLNK
+    }
+}
+
+/// A directory entry, as returned by [`dread`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dirent {
+    pub stat: Stat,
+    pub name: String,
+}
+
+impl Dirent {
+    /// Size of the encoded structure.
+    pub const SIZE: usize = Stat::SIZE + 256 + 4;
+
+    pub fn decode(raw: &[u8]) -> Self {
+        let name = &raw[Stat::SIZE..Stat::SIZE + 256];
+        let len = name.iter().position(|&c| c == 0).unwrap_or(name.len());
+        Self {
+            stat: Stat::decode(raw),
+            name: String::from_utf8_lossy(&name[..len]).into_owned(),
+        }
+    }
+}
+
+/// Encode `path` into a NUL terminated buffer, after `head`.
+fn path_arg<const N: usize>(head: &[u8], path: &str) -> Result<[u8; N]> {
+    let mut arg = [0; N];
+    // Leave room for the terminator
+    if path.len() >= N - head.len() {
+        return Err(Errno(errno::ENAMETOOLONG));
+    }
+    arg[..head.len()].copy_from_slice(head);
+    arg[head.len()..head.len() + path.len()].copy_from_slice(path.as_bytes());
+    Ok(arg)
+}
+
+fn call(fno: u32, send: &[u8]) -> Result<i32> {
+    let mut recv = [0; 4];
+    FILEIO.get().call(fno, send, &mut recv);
+    check(i32::from_le_bytes(recv))
+}
+
+/// Open the file at `path` with [`flags`], returning its descriptor.
+pub fn open(path: &str, flags: u32) -> Result<i32> {
+    let arg = path_arg::<{ 4 + PATH_MAX }>(&flags.to_le_bytes(), path)?;
+    call(fno::OPEN, &arg)
+}
+
+pub fn close(fd: i32) -> Result<()> {
+    call(fno::CLOSE, &fd.to_le_bytes()).map(drop)
+}
+
+/// Read into `buf`, returns the number of bytes read.
+pub fn read(fd: i32, buf: &mut [u8]) -> Result<usize> {
+    // Head and tail of the buffer that are not aligned to a quadword are sent separately
+    let len = buf.len() as u32;
+    buf::read_split(buf, 16, |dest, ends| {
+        let arg: [u8; 16] = proto::words([fd as u32, dest, len, ends]);
+        call(fno::READ, &arg).map(|read| read as usize)
+    })
+}
+
+/// Write `buf`, returns the number of bytes written.
+pub fn write(fd: i32, buf: &[u8]) -> Result<usize> {
+    if buf.is_empty() {
+        return Ok(0);
+    }
+
+    // The IOP fetches the data from EE memory, which must be aligned to a quadword
+    let bounce = (buf.as_ptr() as usize % 16 != 0).then(|| DmaBuf::from_slice(buf));
+    let src = bounce
+        .as_ref()
+        .map_or(buf, |bounce| bounce.as_slice(buf.len()));
+
+    // Small writes are sent inline
+    let mis = if src.len() < 16 { src.len() } else { 0 };
+
+    let mut arg: [u8; 32] = proto::words([
+        fd as u32,
+        cmd::phys_addr(src.as_ptr()),
+        src.len() as u32,
+        mis as u32,
+    ]);
+    arg[16..16 + mis].copy_from_slice(&src[..mis]);
+
+    unsafe { arch::cache_dhwbin(src.as_ptr() as *const (), src.len()) };
+    call(fno::WRITE, &arg).map(|res| res as usize)
+}
+
+/// Move the file offset according to [`whence`], returns the new offset.
+pub fn lseek(fd: i32, offset: i32, whence: u32) -> Result<u32> {
+    let arg: [u8; 16] = proto::words([fd as u32, offset as u32, whence]);
+    call(fno::LSEEK, &arg[..12]).map(|res| res as u32)
+}
+
+pub fn remove(path: &str) -> Result<()> {
+    let arg = path_arg::<PATH_MAX>(&[], path)?;
+    call(fno::REMOVE, &arg).map(drop)
+}
+
+pub fn mkdir(path: &str, mode: u32) -> Result<()> {
+    let arg = path_arg::<{ 4 + PATH_MAX }>(&mode.to_le_bytes(), path)?;
+    call(fno::MKDIR, &arg).map(drop)
+}
+
+pub fn rmdir(path: &str) -> Result<()> {
+    let arg = path_arg::<PATH_MAX>(&[], path)?;
+    call(fno::RMDIR, &arg).map(drop)
+}
+
+/// Open the directory at `path`, returning its descriptor.
+pub fn dopen(path: &str) -> Result<i32> {
+    let arg = path_arg::<PATH_MAX>(&[], path)?;
+    call(fno::DOPEN, &arg)
+}
+
+pub fn dclose(fd: i32) -> Result<()> {
+    call(fno::DCLOSE, &fd.to_le_bytes()).map(drop)
+}
+
+/// Read the next directory entry, returns `None` at the end of the directory.
+pub fn dread(fd: i32) -> Result<Option<Dirent>> {
+    let buf = DmaBuf::new(Dirent::SIZE);
+    let invalidate = |buf: &DmaBuf| unsafe {
+        arch::cache_dhwbin(buf.as_ptr() as *const (), Dirent::SIZE.next_multiple_of(64));
+    };
+
+    invalidate(&buf);
+    let arg: [u8; 16] = proto::words([fd as u32, cmd::phys_addr(buf.as_ptr())]);
+    let res = call(fno::DREAD, &arg[..8]);
+    invalidate(&buf);
+
+    Ok((res? > 0).then(|| Dirent::decode(buf.as_slice(Dirent::SIZE))))
+}
+
+pub fn getstat(path: &str) -> Result<Stat> {
+    let buf = DmaBuf::new(Stat::SIZE);
+    let invalidate = |buf: &DmaBuf| unsafe {
+        arch::cache_dhwbin(buf.as_ptr() as *const (), 64);
+    };
+
+    invalidate(&buf);
+    let addr = cmd::phys_addr(buf.as_ptr());
+    let arg = path_arg::<{ 4 + PATH_MAX }>(&addr.to_le_bytes(), path)?;
+    let res = call(fno::GETSTAT, &arg);
+    invalidate(&buf);
+
+    res?;
+    Ok(Stat::decode(buf.as_slice(Stat::SIZE)))
+}
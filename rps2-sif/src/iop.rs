@@ -0,0 +1,182 @@
+//! IOP reset and memory management.
+
+use core::ffi::c_void;
+
+use rps2_kernel::arch;
+use rps2_kernel::os::{self, SifDmaTransfer};
+
+use crate::buf::DmaBuf;
+use crate::cmd::{self, reg};
+use crate::proto::{ResetCmd, RESET_ARG_MAX};
+use crate::rpc::{self, ClientPool};
+
+/// Server id of the IOP heap service.
+pub const HEAP_SID: u32 = 0x8000_0003;
+
+/// Loader of the boot ROM, which reboots the IOP from an image file.
+const UDNL: &str = "rom0:UDNL ";
+
+/// Size of the IOP RAM.
+pub const RAM_SIZE: u32 = 0x20_0000;
+
+/// IOP RAM as mapped in the EE uncached kernel segment.
+const RAM_KSEG1: usize = 0xbc00_0000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    /// The image path does not fit in the reset command.
+    ImageTooLong,
+}
+
+pub type Result<T> = core::result::Result<T, Error>;
+
+/// Reboot the IOP, from the boot ROM or from `image` (e.g. `cdrom0:\\IOPRP.IMG;1`).
+///
+/// Blocks until the IOP is back up, then initializes the SIF command and RPC layers again.
+/// Clients bound before the reset must not be used anymore.
+///
+/// The LOADFILE module in the boot ROM does not support loading modules from EE memory, it gets
+/// patched by [`enable_load_module_buffer`](crate::loadfile::enable_load_module_buffer), which
+/// can be called right after the reset.
+pub fn reset(image: Option<&str>) -> Result<()> {
+    let mut cmd = ResetCmd::default();
+    if let Some(image) = image {
+        let len = UDNL.len() + image.len();
+        // Leave room for the terminator
+        if len > RESET_ARG_MAX {
+            return Err(Error::ImageTooLong);
+        }
+        cmd.arg[..UDNL.len()].copy_from_slice(UDNL.as_bytes());
+        cmd.arg[UDNL.len()..len].copy_from_slice(image.as_bytes());
+        // The terminator is part of the argument
+        cmd.arglen = len as u32 + 1;
+    }
+
+    cmd::init();
+
+    unsafe {
+        // Writing a flag clears it, the IOP sets it again once it finished booting
+        os::sif_set_reg(reg::SMFLAG, reg::STAT_BOOTEND as i32);
+    }
+    cmd::send(&cmd, 0, None);
+
+    // Forget the state of the previous IOP session
+    unsafe {
+        os::sif_set_reg(reg::SMFLAG, reg::STAT_SIFINIT as i32);
+        os::sif_set_reg(reg::SMFLAG, reg::STAT_CMDINIT as i32);
+        os::sif_set_reg(reg::SYS_RPCINIT, 0);
+        os::sif_set_reg(reg::SYS_SUBADDR, 0);
+    }
+    cmd::deinit();
+    rpc::deinit();
+
+    while !is_booted() {}
+
+    rpc::init();
+    Ok(())
+}
+
+/// Whether the IOP finished booting after a [`reset`].
+pub fn is_booted() -> bool {
+    unsafe { os::sif_get_reg(reg::SMFLAG) as u32 & reg::STAT_BOOTEND != 0 }
+}
+
+static HEAP: ClientPool = ClientPool::new(HEAP_SID);
+
+mod heap_fno {
+    pub const ALLOC: u32 = 1;
+    pub const FREE: u32 = 2;
+}
+
+/// Allocate `size` bytes from the IOP heap, returns the IOP address of the block.
+pub fn alloc_heap(size: usize) -> Option<u32> {
+    let mut recv = [0; 4];
+    HEAP.get()
+        .call(heap_fno::ALLOC, &(size as u32).to_le_bytes(), &mut recv);
+
+    match u32::from_le_bytes(recv) {
+        0 => None,
+        addr => Some(addr),
+    }
+}
+
+/// Free a block returned by [`alloc_heap`].
+pub fn free_heap(addr: u32) -> bool {
+    let mut recv = [0; 4];
+    HEAP.get()
+        .call(heap_fno::FREE, &addr.to_le_bytes(), &mut recv);
+
+    i32::from_le_bytes(recv) >= 0
+}
+
+/// Copy `src` to `dest` in IOP memory, blocking until the transfer is complete.
+///
+/// The IOP receives whole quadwords, so the length of `src` must be a multiple of 16 bytes.
+pub fn write(dest: u32, src: &[u8]) {
+    assert!(
+        src.len() % 16 == 0,
+        "IOP writes must be a multiple of 16 bytes"
+    );
+    if src.is_empty() {
+        return;
+    }
+
+    cmd::init();
+
+    let bounce = (src.as_ptr() as usize % 16 != 0).then(|| DmaBuf::from_slice(src));
+    let src = bounce.as_ref().map_or(src, |buf| buf.as_slice(src.len()));
+
+    unsafe { arch::cache_dhwbin(src.as_ptr() as *const (), src.len()) };
+    cmd::dma_transfer(&[SifDmaTransfer {
+        src: src.as_ptr() as *const c_void,
+        dst: dest as usize as *mut c_void,
+        size: src.len() as i32,
+        attr: 0,
+    }]);
+}
+
+fn ram_word(addr: u32) -> *mut u32 {
+    // Drop the segment of IOP kernel addresses
+    let addr = addr & 0x1fff_ffff;
+    assert!(
+        addr % 4 == 0 && addr < RAM_SIZE,
+        "invalid IOP address {addr:#x}"
+    );
+    (RAM_KSEG1 + addr as usize) as *mut u32
+}
+
+/// Read a word of IOP memory, directly through the EE bus.
+pub fn read_word(addr: u32) -> u32 {
+    let mut word = [0];
+    read(addr, &mut word);
+    word[0]
+}
+
+/// Read words of IOP memory starting at `addr`, directly through the EE bus.
+pub fn read(addr: u32, buf: &mut [u32]) {
+    let ptr = ram_word(addr);
+    ram_word(addr + (buf.len().saturating_sub(1) * 4) as u32);
+
+    // Don't keep interrupts disabled for too long
+    for (i, chunk) in buf.chunks_mut(256).enumerate() {
+        let ptr = unsafe { ptr.add(i * 256) };
+        arch::with_kernel_mode(|| {
+            for (j, word) in chunk.iter_mut().enumerate() {
+                *word = unsafe { ptr.add(j).read_volatile() };
+            }
+        });
+    }
+}
+
+/// Write a word of IOP memory, directly through the EE bus.
+///
+/// # Safety
+///
+/// The IOP must not be using the memory in a way the new value breaks, e.g. running the code.
+pub unsafe fn write_word(addr: u32, val: u32) {
+    let ptr = ram_word(addr);
+    arch::with_kernel_mode(|| unsafe {
+        ptr.write_volatile(val);
+        arch::sync();
+    });
+}
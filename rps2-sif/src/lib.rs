@@ -0,0 +1,17 @@
+#![no_std]
+#![deny(missing_debug_implementations)]
+extern crate alloc;
+
+pub mod cdvd;
+pub mod cmd;
+pub mod fileio;
+pub mod iop;
+pub mod loadfile;
+pub mod memcard;
+pub mod rpc;
+
+mod buf;
+
+pub use rps2_sif_proto as proto;
+
+pub use rpc::{PendingCall, SifRpcClient};
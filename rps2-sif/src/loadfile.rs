@@ -0,0 +1,345 @@
+//! Loading IRX modules on the IOP, through the LOADFILE service.
+
+use alloc::vec;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicU32, Ordering};
+
+use crate::iop;
+use crate::rpc::{self, ClientPool};
+
+/// Server id of the LOADFILE service.
+pub const LOADFILE_SID: u32 = 0x8000_0006;
+
+/// Maximum length of a module path, terminator included.
+pub const PATH_MAX: usize = 252;
+
+/// Maximum length of the module arguments, terminators included.
+pub const ARGS_MAX: usize = 252;
+
+static LOADFILE: ClientPool = ClientPool::new(LOADFILE_SID);
+
+mod fno {
+    pub const MOD_LOAD: u32 = 0;
+    pub const MOD_BUF_LOAD: u32 = 6;
+}
+
+// Layout of the request: result/argument length, module result/argument length, path, args
+const ARG_SIZE: usize = 8 + PATH_MAX + ARGS_MAX;
+const PATH_OFFSET: usize = 8;
+const ARGS_OFFSET: usize = PATH_OFFSET + PATH_MAX;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    /// The path does not fit in [`PATH_MAX`] bytes.
+    PathTooLong,
+    /// The arguments do not fit in [`ARGS_MAX`] bytes.
+    ArgsTooLong,
+    /// The module could not be linked to the libraries it imports.
+    LinkError,
+    /// The file is not a valid IRX module.
+    IllegalObject,
+    UnknownModule,
+    /// The file does not exist.
+    NoFile,
+    /// The file could not be read.
+    FileError,
+    /// The memory the module is linked at is in use.
+    MemoryInUse,
+    /// The IOP ran out of memory.
+    NoMemory,
+    /// LOADFILE could not be patched to load modules from EE memory.
+    PatchFailed,
+    /// Any other IOP kernel error code.
+    Other(i32),
+}
+
+impl Error {
+    pub fn from_code(code: i32) -> Self {
+        match code {
+            -200 => Self::LinkError,
+            -201 => Self::IllegalObject,
+            -202 => Self::UnknownModule,
+            -203 => Self::NoFile,
+            -204 => Self::FileError,
+            -205 => Self::MemoryInUse,
+            -400 => Self::NoMemory,
+            code => Self::Other(code),
+        }
+    }
+}
+
+pub type Result<T> = core::result::Result<T, Error>;
+
+/// A module loaded on the IOP.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Module {
+    id: i32,
+    result: i32,
+}
+
+impl Module {
+    /// Module id assigned by the IOP.
+    pub fn id(&self) -> i32 {
+        self.id
+    }
+
+    /// Value returned by the module entry point.
+    pub fn result(&self) -> i32 {
+        self.result
+    }
+
+    /// Whether the module stayed resident after its entry point returned.
+    pub fn is_resident(&self) -> bool {
+        // NO_RESIDENT_END
+        self.result & 3 != 1
+    }
+}
+
+/// Encode `args` as a sequence of NUL terminated strings.
+fn encode_args(args: &[&str]) -> Result<Vec<u8>> {
+    let mut buf = Vec::new();
+    for arg in args {
+        buf.extend_from_slice(arg.as_bytes());
+        buf.push(0);
+    }
+
+    if buf.len() > ARGS_MAX {
+        return Err(Error::ArgsTooLong);
+    }
+    Ok(buf)
+}
+
+fn call(fno: u32, head: [u32; 2], path: &[u8], args: &[u8]) -> Result<Module> {
+    let mut arg = [0; ARG_SIZE];
+    arg[0..4].copy_from_slice(&head[0].to_le_bytes());
+    arg[4..8].copy_from_slice(&head[1].to_le_bytes());
+    arg[PATH_OFFSET..PATH_OFFSET + path.len()].copy_from_slice(path);
+    arg[ARGS_OFFSET..ARGS_OFFSET + args.len()].copy_from_slice(args);
+
+    let mut recv = [0; 8];
+    LOADFILE.get().call(fno, &arg, &mut recv);
+
+    let id = i32::from_le_bytes(recv[0..4].try_into().unwrap());
+    let result = i32::from_le_bytes(recv[4..8].try_into().unwrap());
+    if id < 0 {
+        return Err(Error::from_code(id));
+    }
+    Ok(Module { id, result })
+}
+
+/// Load and start the module at `path`, as seen by the IOP (e.g. `rom0:SIO2MAN`).
+pub fn load_module(path: &str, args: &[&str]) -> Result<Module> {
+    // Leave room for the terminator
+    if path.len() >= PATH_MAX {
+        return Err(Error::PathTooLong);
+    }
+    let args = encode_args(args)?;
+
+    call(
+        fno::MOD_LOAD,
+        [args.len() as u32, 0],
+        path.as_bytes(),
+        &args,
+    )
+}
+
+/// Load and start a module from EE memory, e.g. embedded with `include_bytes!`.
+///
+/// The module is copied to a temporary buffer on the IOP heap. The LOADFILE module in the boot
+/// ROM does not implement this, so it gets patched first, see [`enable_load_module_buffer`].
+pub fn load_module_buffer(module: &[u8], args: &[&str]) -> Result<Module> {
+    let args = encode_args(args)?;
+    enable_load_module_buffer()?;
+
+    // The IOP receives whole quadwords
+    let size = module.len().next_multiple_of(16);
+    let padded;
+    let module = if module.len() != size {
+        padded = [module, &[0; 15][..size - module.len()]].concat();
+        &padded
+    } else {
+        module
+    };
+
+    let addr = iop::alloc_heap(size).ok_or(Error::NoMemory)?;
+    iop::write(addr, module);
+
+    let res = call(fno::MOD_BUF_LOAD, [addr, args.len() as u32], &[], &args);
+    iop::free_heap(addr);
+    res
+}
+
+/// RPC generation LOADFILE was patched in, plus one.
+static PATCHED: AtomicU32 = AtomicU32::new(0);
+
+/// Head of the IOP kernel module list.
+const MODULE_LIST: u32 = 0x800;
+
+/// First word of an IRX export table.
+const EXPORT_MAGIC: u32 = 0x41e0_0000;
+
+mod modload {
+    pub const NAME: &[u8; 8] = b"modload\0";
+
+    /// `int StartModule(int id, const char *path, int arg_len, const char *args, int *result)`
+    pub const START_MODULE: usize = 8;
+    /// `int LoadModuleBuffer(void *ptr)`
+    pub const LOAD_MODULE_BUFFER: usize = 10;
+}
+
+/// Patch LOADFILE so that [`load_module_buffer`] works, does nothing if it is already patched.
+///
+/// Like the `sbv` patches, this relies on the IOP kernel structures, but rather than patching
+/// the code of a specific LOADFILE version, the RPC function of the server is replaced with a
+/// small trampoline handling [`load_module_buffer`] through the `modload` library, and
+/// forwarding every other call. The patch is lost on an IOP reset.
+pub fn enable_load_module_buffer() -> Result<()> {
+    let generation = rpc::generation() + 1;
+    if PATCHED.load(Ordering::Relaxed) == generation {
+        return Ok(());
+    }
+
+    // The function list is terminated by a null entry, older versions might lack the function
+    let exports = find_exports(modload::NAME).ok_or(Error::PatchFailed)?;
+    let mut funcs = [0; modload::LOAD_MODULE_BUFFER + 1];
+    iop::read(exports, &mut funcs);
+    if funcs.contains(&0) {
+        return Err(Error::PatchFailed);
+    }
+    let load_module_buffer = funcs[modload::LOAD_MODULE_BUFFER];
+    let start_module = funcs[modload::START_MODULE];
+
+    // The IOP address of the server data is returned by the bind, its second word holds the
+    // RPC function
+    let server = LOADFILE.get().server();
+    let func_addr = server + 4;
+    let func = iop::read_word(func_addr);
+
+    let code = trampoline(func, load_module_buffer, start_module);
+    let size = code.len() * 4;
+    let addr = iop::alloc_heap(size).ok_or(Error::NoMemory)?;
+    unsafe {
+        // SAFETY: The memory was just allocated, and the IOP reads the function pointer on
+        // every call
+        for (i, word) in code.iter().enumerate() {
+            iop::write_word(addr + i as u32 * 4, *word);
+        }
+        iop::write_word(func_addr, addr);
+    }
+
+    PATCHED.store(generation, Ordering::Relaxed);
+    Ok(())
+}
+
+/// Find the function table of the export library `name`, by scanning the loaded modules.
+fn find_exports(name: &[u8; 8]) -> Option<u32> {
+    let valid = |addr: u32| addr % 4 == 0 && addr < iop::RAM_SIZE - 64;
+
+    let mut module = MODULE_LIST;
+    // Guard against a corrupted list
+    for _ in 0..256 {
+        if !valid(module) {
+            return None;
+        }
+
+        // ModuleInfo: next, name, version/flags, id/flags, entry, gp, text start, text size,
+        // data size, ...
+        let mut info = [0; 9];
+        iop::read(module, &mut info);
+        let (start, size) = (info[6], info[7].saturating_add(info[8]));
+        if valid(start) && size <= iop::RAM_SIZE - start {
+            let mut image = vec![0; size as usize / 4];
+            iop::read(start, &mut image);
+
+            // Export table: magic, next, version/mode, name, functions
+            for (i, table) in image.windows(5).enumerate() {
+                if table[0] != EXPORT_MAGIC {
+                    continue;
+                }
+                let table_name = [table[3].to_le_bytes(), table[4].to_le_bytes()].concat();
+                if table_name == name {
+                    return Some(start + i as u32 * 4 + 20);
+                }
+            }
+        }
+
+        match info[0] {
+            0 => return None,
+            next => module = next,
+        }
+    }
+    None
+}
+
+/// IOP code replacing the LOADFILE RPC function `func`.
+///
+/// `void *func(int fno, void *buf, int size)` gets the request described by [`call`], and
+/// returns the reply, function [`fno::MOD_BUF_LOAD`] writes the module id and result over the
+/// first two words of the request.
+#[rustfmt::skip]
+fn trampoline(func: u32, load_module_buffer: u32, start_module: u32) -> [u32; 33] {
+    // R3000 registers and instructions
+    const ZERO: u32 = 0;
+    const V0: u32 = 2;
+    const A0: u32 = 4;
+    const A1: u32 = 5;
+    const A2: u32 = 6;
+    const A3: u32 = 7;
+    const T0: u32 = 8;
+    const T1: u32 = 9;
+    const S0: u32 = 16;
+    const SP: u32 = 29;
+    const RA: u32 = 31;
+
+    let i_type = |op: u32, rs: u32, rt: u32, imm: u32| op << 26 | rs << 21 | rt << 16 | imm & 0xffff;
+    let addiu = |rt, rs, imm: i16| i_type(0x09, rs, rt, imm as u32);
+    let lw = |rt, off: i16, base| i_type(0x23, base, rt, off as u32);
+    let sw = |rt, off: i16, base| i_type(0x2b, base, rt, off as u32);
+    let lui = |rt, imm| i_type(0x0f, 0, rt, imm);
+    let ori = |rt, rs, imm| i_type(0x0d, rs, rt, imm);
+    let beq = |rs, rt, off: i16| i_type(0x04, rs, rt, off as u32);
+    let bltz = |rs, off: i16| i_type(0x01, rs, 0, off as u32);
+    let addu = |rd: u32, rs: u32, rt: u32| rs << 21 | rt << 16 | rd << 11 | 0x21;
+    let jr = |rs: u32| rs << 21 | 0x08;
+    let jalr = |rs: u32| rs << 21 | RA << 11 | 0x09;
+    const NOP: u32 = 0;
+
+    [
+        addiu(T0, ZERO, fno::MOD_BUF_LOAD as i16),
+        beq(A0, T0, 5),                         // -> load
+        NOP,
+        // Forward any other function
+        lui(T0, func >> 16),
+        ori(T0, T0, func),
+        jr(T0),
+        NOP,
+        // load:
+        addiu(SP, SP, -32),
+        sw(RA, 28, SP),
+        sw(S0, 24, SP),
+        addu(S0, A1, ZERO),
+        lw(A0, 0, S0),                          // module address
+        lui(T0, load_module_buffer >> 16),
+        ori(T0, T0, load_module_buffer),
+        jalr(T0),
+        NOP,
+        bltz(V0, 10),                           // -> done
+        addu(A0, V0, ZERO),                     // module id
+        addiu(A1, S0, PATH_OFFSET as i16),      // empty path
+        lw(A2, 4, S0),                          // arguments length
+        addiu(A3, S0, ARGS_OFFSET as i16),
+        addiu(T1, S0, 4),                       // module result
+        sw(T1, 16, SP),
+        lui(T0, start_module >> 16),
+        ori(T0, T0, start_module),
+        jalr(T0),
+        NOP,
+        // done:
+        sw(V0, 0, S0),                          // module id or error
+        addu(V0, S0, ZERO),
+        lw(RA, 28, SP),
+        lw(S0, 24, SP),
+        jr(RA),
+        addiu(SP, SP, 32),
+    ]
+}
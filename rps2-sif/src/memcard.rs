@@ -0,0 +1,379 @@
+//! Memory card access through the XMCSERV service.
+//!
+//! Paths are relative to the root of the card in `port` and `slot`, e.g. `/BESLES-12345/save`.
+//! Save directories shown by the browser can be built with [`SaveDirBuilder`] and written with
+//! [`write_save`].
+
+use alloc::vec::Vec;
+
+use rps2_kernel::arch;
+
+use crate::buf::{self, DmaBuf};
+use crate::cmd;
+use crate::loadfile;
+use crate::proto;
+use crate::rpc::{ClientPool, Service};
+
+pub use rps2_formats::icon::NAME_MAX;
+pub use rps2_formats::icon::{to_shift_jis, IconSys, IconSysError, SaveDir, SaveDirBuilder};
+pub use rps2_formats::mcfs::{attr, DirEntry, Time};
+
+/// Server id of the memory card service.
+pub const MCSERV_SID: u32 = 0x8000_0400;
+
+/// Maximum length of a path, terminator included.
+pub const PATH_MAX: usize = 1024;
+
+const SIO2MAN: &str = "rom0:XSIO2MAN";
+const MCMAN: &str = "rom0:XMCMAN";
+const MCSERV: &str = "rom0:XMCSERV";
+
+static MCSERV_POOL: ClientPool = ClientPool::new(MCSERV_SID);
+static SERVICE: Service = Service::new(MCSERV_SID);
+
+mod fno {
+    pub const INIT: u32 = 0x70;
+    pub const OPEN: u32 = 0x71;
+    pub const CLOSE: u32 = 0x72;
+    pub const READ: u32 = 0x73;
+    pub const WRITE: u32 = 0x74;
+    pub const SEEK: u32 = 0x75;
+    pub const GET_INFO: u32 = 0x78;
+    pub const DELETE: u32 = 0x79;
+    pub const GET_DIR: u32 = 0x7c;
+}
+
+/// Flags of [`open`].
+pub mod flags {
+    pub const RDONLY: u32 = 0x0001;
+    pub const WRONLY: u32 = 0x0002;
+    pub const RDWR: u32 = 0x0003;
+    /// Create a directory instead of a file, see [`mkdir`](super::mkdir).
+    pub const CREATE_DIR: u32 = 0x0040;
+    pub const CREAT: u32 = 0x0200;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    /// The path does not fit in [`PATH_MAX`] bytes.
+    PathTooLong,
+    /// The service could not be loaded.
+    Load(loadfile::Error),
+    /// The card was changed since the last call to [`get_info`].
+    ChangedCard,
+    /// The card is not formatted.
+    NoFormat,
+    /// The card is full.
+    Full,
+    /// The file or directory does not exist.
+    NoEntry,
+    /// The file is protected, or opened in an incompatible mode.
+    Denied,
+    /// The directory is not empty.
+    NotEmpty,
+    /// Too many files are open.
+    TooManyFiles,
+    /// Any other error code of the service.
+    Other(i32),
+}
+
+impl Error {
+    pub fn from_code(code: i32) -> Self {
+        match code {
+            -1 => Self::ChangedCard,
+            -2 => Self::NoFormat,
+            -3 => Self::Full,
+            -4 => Self::NoEntry,
+            -5 => Self::Denied,
+            -6 => Self::NotEmpty,
+            -7 => Self::TooManyFiles,
+            code => Self::Other(code),
+        }
+    }
+}
+
+impl From<loadfile::Error> for Error {
+    fn from(err: loadfile::Error) -> Self {
+        Self::Load(err)
+    }
+}
+
+pub type Result<T> = core::result::Result<T, Error>;
+
+fn check(res: i32) -> Result<i32> {
+    if res < 0 {
+        Err(Error::from_code(res))
+    } else {
+        Ok(res)
+    }
+}
+
+fn call(fno: u32, send: &[u8]) -> i32 {
+    let mut recv = [0; 4];
+    MCSERV_POOL.get().call(fno, send, &mut recv);
+    i32::from_le_bytes(recv)
+}
+
+// Layout of descriptor requests: fd, port, slot, size, offset, origin, buffer, param, inline data
+const DESC_SIZE: usize = 48;
+const DESC_DATA: usize = 32;
+
+fn desc_arg(words: [u32; 8]) -> [u8; DESC_SIZE] {
+    proto::words(words)
+}
+
+// Layout of name requests: port, slot, flags, max entries, table, name
+const NAME_HEAD: usize = 20;
+const NAME_SIZE: usize = NAME_HEAD + PATH_MAX;
+
+fn name_arg(
+    port: u32,
+    slot: u32,
+    flags: u32,
+    maxent: u32,
+    table: u32,
+    path: &str,
+) -> Result<Vec<u8>> {
+    // Leave room for the terminator
+    if path.len() >= PATH_MAX {
+        return Err(Error::PathTooLong);
+    }
+
+    let mut arg = Vec::with_capacity(NAME_SIZE);
+    for word in [port, slot, flags, maxent, table] {
+        arg.extend_from_slice(&word.to_le_bytes());
+    }
+    arg.extend_from_slice(path.as_bytes());
+    arg.resize(NAME_SIZE, 0);
+    Ok(arg)
+}
+
+/// Load and initialize the memory card service, unless it is already running.
+///
+/// Called by every other function, and needed again after an IOP reset.
+pub fn init() -> Result<()> {
+    SERVICE.init(|running| {
+        // The service might have been loaded by a previous program
+        if !running {
+            // Fails harmlessly if the pad driver already loaded it
+            loadfile::load_module(SIO2MAN, &[])?;
+            loadfile::load_module(MCMAN, &[])?;
+            loadfile::load_module(MCSERV, &[])?;
+        }
+
+        call(fno::INIT, &[0; DESC_SIZE]);
+        Ok(())
+    })
+}
+
+/// Kind of card inserted in a slot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CardType {
+    None,
+    Ps1,
+    Ps2,
+    PocketStation,
+    Unknown(i32),
+}
+
+impl CardType {
+    pub fn from_raw(raw: i32) -> Self {
+        match raw {
+            0 => Self::None,
+            1 => Self::Ps1,
+            2 => Self::Ps2,
+            3 => Self::PocketStation,
+            other => Self::Unknown(other),
+        }
+    }
+}
+
+/// Status of a card, as returned by [`get_info`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Info {
+    pub card_type: CardType,
+    /// Free space, in 1 KiB clusters.
+    pub free: u32,
+    pub formatted: bool,
+    /// Whether the card was changed since the previous call.
+    pub changed: bool,
+}
+
+/// Query the card in `port` and `slot`, which also acknowledges a card change.
+pub fn get_info(port: u32, slot: u32) -> Result<Info> {
+    // Results land in a separate buffer: type, free clusters, formatted
+    const INFO_SIZE: usize = 64;
+
+    init()?;
+
+    let info = DmaBuf::new(INFO_SIZE);
+    let invalidate =
+        |info: &DmaBuf| unsafe { arch::cache_dhwbin(info.as_ptr() as *const (), INFO_SIZE) };
+
+    invalidate(&info);
+    let arg = desc_arg([0, port, slot, 1, 1, 1, 0, cmd::phys_addr(info.as_ptr())]);
+    let res = call(fno::GET_INFO, &arg);
+    invalidate(&info);
+
+    // -1 and -2 report a new card, formatted or not
+    if res < -2 {
+        return Err(Error::from_code(res));
+    }
+
+    let raw = info.as_slice(INFO_SIZE);
+    let word = |offset: usize| i32::from_le_bytes(raw[offset..offset + 4].try_into().unwrap());
+    Ok(Info {
+        card_type: CardType::from_raw(word(0)),
+        free: word(4) as u32,
+        formatted: word(8) != 0,
+        changed: res != 0,
+    })
+}
+
+/// Open the file at `path` with [`flags`], returning its descriptor.
+pub fn open(port: u32, slot: u32, path: &str, flags: u32) -> Result<i32> {
+    init()?;
+    let arg = name_arg(port, slot, flags, 0, 0, path)?;
+    check(call(fno::OPEN, &arg))
+}
+
+pub fn close(fd: i32) -> Result<()> {
+    let arg = desc_arg([fd as u32, 0, 0, 0, 0, 0, 0, 0]);
+    check(call(fno::CLOSE, &arg))?;
+    Ok(())
+}
+
+/// Move the position of `fd`, with an origin from [`fileio::whence`](crate::fileio::whence).
+pub fn seek(fd: i32, offset: i32, whence: u32) -> Result<u32> {
+    let arg = desc_arg([fd as u32, 0, 0, 0, offset as u32, whence, 0, 0]);
+    Ok(check(call(fno::SEEK, &arg))? as u32)
+}
+
+/// Read into `buf`, returns the number of bytes read.
+pub fn read(fd: i32, buf: &mut [u8]) -> Result<usize> {
+    // Head and tail of the buffer that are not aligned to a cache line are sent separately
+    let len = buf.len() as u32;
+    buf::read_split(buf, 64, |dest, ends| {
+        let arg = desc_arg([fd as u32, 0, 0, len, 0, 0, dest, ends]);
+        check(call(fno::READ, &arg)).map(|read| read as usize)
+    })
+}
+
+/// Write `buf`, returns the number of bytes written.
+pub fn write(fd: i32, buf: &[u8]) -> Result<usize> {
+    if buf.is_empty() {
+        return Ok(0);
+    }
+
+    // The IOP fetches the data from EE memory, which must be aligned to a quadword
+    let bounce = (buf.as_ptr() as usize % 16 != 0).then(|| DmaBuf::from_slice(buf));
+    let src = bounce
+        .as_ref()
+        .map_or(buf, |bounce| bounce.as_slice(buf.len()));
+
+    // Small writes are sent inline
+    let (size, inline) = if src.len() < 16 {
+        (0, src.len())
+    } else {
+        (src.len(), 0)
+    };
+
+    let mut arg = desc_arg([
+        fd as u32,
+        0,
+        0,
+        size as u32,
+        0,
+        inline as u32,
+        cmd::phys_addr(src.as_ptr()),
+        0,
+    ]);
+    arg[DESC_DATA..DESC_DATA + inline].copy_from_slice(&src[..inline]);
+
+    unsafe { arch::cache_dhwbin(src.as_ptr() as *const (), src.len()) };
+    Ok(check(call(fno::WRITE, &arg))? as usize)
+}
+
+/// Create the directory at `path`.
+pub fn mkdir(port: u32, slot: u32, path: &str) -> Result<()> {
+    open(port, slot, path, flags::CREATE_DIR)?;
+    Ok(())
+}
+
+/// Delete the file or empty directory at `path`.
+pub fn delete(port: u32, slot: u32, path: &str) -> Result<()> {
+    init()?;
+    let arg = name_arg(port, slot, 0, 0, 0, path)?;
+    check(call(fno::DELETE, &arg))?;
+    Ok(())
+}
+
+/// List the entries matching `pattern`, which may end with a `*` wildcard (e.g. `/SAVE/*`).
+pub fn getdir(port: u32, slot: u32, pattern: &str) -> Result<Vec<DirEntry>> {
+    // Entries fetched per call
+    const BATCH: usize = 16;
+
+    init()?;
+
+    let table = DmaBuf::new(BATCH * DirEntry::SIZE);
+    let invalidate = |table: &DmaBuf| unsafe {
+        arch::cache_dhwbin(table.as_ptr() as *const (), BATCH * DirEntry::SIZE)
+    };
+
+    let mut entries = Vec::new();
+    // The first call starts the search, the following ones continue it
+    for continued in 0.. {
+        invalidate(&table);
+        let arg = name_arg(
+            port,
+            slot,
+            (continued != 0) as u32,
+            BATCH as u32,
+            cmd::phys_addr(table.as_ptr()),
+            pattern,
+        )?;
+        let res = call(fno::GET_DIR, &arg);
+        invalidate(&table);
+        let count = (check(res)? as usize).min(BATCH);
+
+        let raw = table.as_slice(count * DirEntry::SIZE);
+        entries.extend(raw.chunks_exact(DirEntry::SIZE).map(DirEntry::decode));
+        if count < BATCH {
+            break;
+        }
+    }
+    Ok(entries)
+}
+
+/// Write `save` to the card in `port` and `slot`, replacing existing files.
+pub fn write_save(port: u32, slot: u32, save: &SaveDir) -> Result<()> {
+    let dir = alloc::format!("/{}", save.name());
+    if getdir(port, slot, &dir)?.is_empty() {
+        mkdir(port, slot, &dir)?;
+    }
+
+    for (name, data) in save.files() {
+        let path = alloc::format!("{dir}/{name}");
+        // Files can not be truncated, replace them instead
+        match delete(port, slot, &path) {
+            Ok(()) | Err(Error::NoEntry) => {}
+            Err(err) => return Err(err),
+        }
+
+        let fd = open(port, slot, &path, flags::CREAT | flags::WRONLY)?;
+        let res = write_all(fd, data);
+        close(fd)?;
+        res?;
+    }
+    Ok(())
+}
+
+fn write_all(fd: i32, mut data: &[u8]) -> Result<()> {
+    while !data.is_empty() {
+        match write(fd, data)? {
+            0 => return Err(Error::Full),
+            len => data = &data[len..],
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,538 @@
+//! SIF remote procedure calls to IOP servers.
+
+use alloc::boxed::Box;
+use core::cell::RefCell;
+use core::fmt::{self, Debug};
+use core::ops::{Deref, DerefMut};
+use core::slice;
+use core::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use core::time::Duration;
+use critical_section::Mutex;
+
+use rps2_kernel::arch;
+use rps2_kernel::interrupt::HandlerContext;
+use rps2_kernel::os;
+use rps2_thread::sema::Sema;
+use rps2_thread::thread;
+
+use crate::buf::DmaBuf;
+use crate::cmd::{self, reg, Data};
+use crate::proto::{self, InitCmd, RawPacket, RpcBind, RpcCall, RpcEnd, RpcHeader, RpcRdata};
+use crate::proto::{CMD_RPC_BIND, CMD_RPC_END, CMD_RPC_RDATA};
+
+/// Delay between attempts of [`SifRpcClient::bind`].
+const BIND_RETRY: Duration = Duration::from_millis(1);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum InitState {
+    Uninit,
+    Running,
+    Done,
+}
+
+static INIT: Mutex<RefCell<InitState>> = Mutex::new(RefCell::new(InitState::Uninit));
+
+static NEXT_RPC_ID: AtomicU32 = AtomicU32::new(0);
+
+/// Incremented on every IOP reset, which invalidates all bound clients.
+static GENERATION: AtomicU32 = AtomicU32::new(0);
+
+/// Client state shared with the IOP, which echoes its address in end packets.
+struct ClientData {
+    sema: i32,
+    server: AtomicU32,
+    buff: AtomicU32,
+    cbuff: AtomicU32,
+}
+
+fn rpc_end_handler(packet: &RawPacket, ctx: &HandlerContext) {
+    let Some(end) = proto::decode::<RpcEnd>(packet) else {
+        return;
+    };
+    if end.rpc.client == 0 {
+        return;
+    }
+
+    let client = unsafe {
+        // SAFETY: A client waits for the end packet of its last request before being freed
+        &*(end.rpc.client as usize as *const ClientData)
+    };
+    if end.cid == CMD_RPC_BIND {
+        client.server.store(end.server, Ordering::Relaxed);
+        client.buff.store(end.buff, Ordering::Relaxed);
+        client.cbuff.store(end.cbuff, Ordering::Relaxed);
+    }
+    ctx.signal_sema(client.sema);
+}
+
+fn rpc_rdata_handler(packet: &RawPacket, ctx: &HandlerContext) {
+    let Some(rdata) = proto::decode::<RpcRdata>(packet) else {
+        return;
+    };
+
+    let end = RpcEnd {
+        rpc: rdata.rpc,
+        cid: CMD_RPC_RDATA,
+        ..Default::default()
+    };
+    let src = unsafe {
+        // SAFETY: The IOP only requests memory handed to it by a pending call
+        slice::from_raw_parts(rdata.src as usize as *const u8, rdata.size as usize)
+    };
+    cmd::irq_send(
+        ctx,
+        &end,
+        0,
+        Some(Data {
+            src,
+            dest: rdata.dest,
+        }),
+    );
+}
+
+/// Initialize the RPC layer, along with the command layer.
+///
+/// Does nothing if it is already initialized, possibly by a previous program.
+pub fn init() {
+    cmd::init();
+
+    loop {
+        let state = critical_section::with(|cs| {
+            let mut init = INIT.borrow_ref_mut(cs);
+            let state = *init;
+            if state == InitState::Uninit {
+                *init = InitState::Running;
+            }
+            state
+        });
+
+        match state {
+            InitState::Uninit => break,
+            InitState::Running => thread::sleep_for(BIND_RETRY),
+            InitState::Done => return,
+        }
+    }
+
+    cmd::set_handler(CMD_RPC_END, Some(rpc_end_handler));
+    cmd::set_handler(CMD_RPC_RDATA, Some(rpc_rdata_handler));
+
+    if unsafe { os::sif_get_reg(reg::SYS_RPCINIT) } == 0 {
+        cmd::send(&InitCmd::default(), 1, None);
+        // The IOP acknowledges by setting software register 0
+        while cmd::sreg(0) == 0 {
+            thread::sleep_for(BIND_RETRY);
+        }
+        unsafe { os::sif_set_reg(reg::SYS_RPCINIT, 1) };
+    }
+
+    critical_section::with(|cs| *INIT.borrow_ref_mut(cs) = InitState::Done);
+}
+
+/// Forget the RPC layer state, used when the IOP gets reset.
+pub(crate) fn deinit() {
+    critical_section::with(|cs| *INIT.borrow_ref_mut(cs) = InitState::Uninit);
+    GENERATION.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Number of IOP resets so far.
+pub(crate) fn generation() -> u32 {
+    GENERATION.load(Ordering::Relaxed)
+}
+
+/// A client bound to an RPC server on the IOP.
+///
+/// Dropping the client blocks until its last call completes, as the IOP still refers to it.
+pub struct SifRpcClient {
+    sid: u32,
+    generation: u32,
+    data: Box<ClientData>,
+    sema: Sema,
+    /// Replies land here and are copied out by [`PendingCall`], so the IOP never writes to
+    /// memory the client does not own.
+    recv_buf: DmaBuf,
+    /// A call was sent and its end packet was not received yet.
+    pending: bool,
+}
+
+impl SifRpcClient {
+    /// Bind to server `sid`, retrying until it gets registered on the IOP.
+    pub fn bind(sid: u32) -> Self {
+        loop {
+            if let Some(client) = Self::try_bind(sid) {
+                return client;
+            }
+            thread::sleep_for(BIND_RETRY);
+        }
+    }
+
+    /// Bind to server `sid`, returns `None` if it is not registered on the IOP.
+    pub fn try_bind(sid: u32) -> Option<Self> {
+        init();
+
+        let sema = Sema::builder()
+            .init_count(0)
+            .max_count(1)
+            .build()
+            .expect("Failed to build sema");
+        let client = Self {
+            sid,
+            generation: GENERATION.load(Ordering::Relaxed),
+            data: Box::new(ClientData {
+                sema: sema.id(),
+                server: AtomicU32::new(0),
+                buff: AtomicU32::new(0),
+                cbuff: AtomicU32::new(0),
+            }),
+            sema,
+            recv_buf: DmaBuf::new(0),
+            pending: false,
+        };
+
+        let body = RpcBind {
+            rpc: client.rpc_header(),
+            sid,
+        };
+        cmd::send(&body, 0, None);
+        client.sema.wait();
+
+        (client.server() != 0).then_some(client)
+    }
+
+    pub fn sid(&self) -> u32 {
+        self.sid
+    }
+
+    /// Whether the client is still bound, clients are invalidated by an IOP reset.
+    pub fn is_valid(&self) -> bool {
+        self.generation == GENERATION.load(Ordering::Relaxed)
+    }
+
+    /// IOP address of the server.
+    pub fn server(&self) -> u32 {
+        self.data.server.load(Ordering::Relaxed)
+    }
+
+    /// IOP address of the server receive buffer.
+    pub fn buff(&self) -> u32 {
+        self.data.buff.load(Ordering::Relaxed)
+    }
+
+    fn rpc_header(&self) -> RpcHeader {
+        RpcHeader {
+            rec_id: 0,
+            pkt_addr: 0,
+            rpc_id: NEXT_RPC_ID.fetch_add(1, Ordering::Relaxed),
+            client: &*self.data as *const ClientData as u32,
+        }
+    }
+
+    /// Call function `fno` of the server with `send` as arguments, storing the reply in `recv`.
+    pub fn call(&mut self, fno: u32, send: &[u8], recv: &mut [u8]) {
+        self.call_async(fno, send, recv).wait();
+    }
+
+    /// Start a call of function `fno`, `send` is copied to the IOP before returning.
+    ///
+    /// The reply is copied to `recv` once the returned [`PendingCall`] completes.
+    pub fn call_async<'a>(
+        &'a mut self,
+        fno: u32,
+        send: &[u8],
+        recv: &'a mut [u8],
+    ) -> PendingCall<'a> {
+        // A leaked call might still be writing to the receive buffer
+        self.wait_pending();
+
+        // The IOP transfers whole quadwords
+        let bounce_send = (send.as_ptr() as usize % 16 != 0).then(|| DmaBuf::from_slice(send));
+        let send = bounce_send
+            .as_ref()
+            .map_or(send, |buf| buf.as_slice(send.len()));
+
+        let recv_len = recv.len();
+        if recv_len > self.recv_buf.len() {
+            self.recv_buf = DmaBuf::new(recv_len);
+        }
+        if recv_len != 0 {
+            let ptr = self.recv_buf.as_ptr() as *const ();
+            unsafe { arch::cache_dhwbin(ptr, recv_len.next_multiple_of(64)) };
+        }
+
+        // Discard a stale completion
+        self.sema.poll();
+
+        let body = RpcCall {
+            rpc: self.rpc_header(),
+            rpc_number: fno,
+            send_size: send.len() as u32,
+            receive: if recv_len != 0 {
+                cmd::phys_addr(self.recv_buf.as_ptr())
+            } else {
+                0
+            },
+            recv_size: recv_len as u32,
+            rmode: 1,
+            server: self.server(),
+        };
+        let data = Data {
+            src: send,
+            dest: self.buff(),
+        };
+        self.pending = true;
+        cmd::send(&body, 0, Some(data));
+
+        PendingCall {
+            client: self,
+            recv,
+            done: false,
+        }
+    }
+
+    /// Wait for the end packet of the last call, if any.
+    fn wait_pending(&mut self) {
+        // The IOP forgot about the call if it was reset
+        if self.pending && self.is_valid() {
+            self.sema.wait();
+        }
+        self.pending = false;
+    }
+}
+
+impl Drop for SifRpcClient {
+    fn drop(&mut self) {
+        // The end handler refers to the client data, and the IOP to the receive buffer
+        self.wait_pending();
+    }
+}
+
+impl Debug for SifRpcClient {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("SifRpcClient")
+            .field("sid", &self.sid)
+            .field("server", &self.server())
+            .finish_non_exhaustive()
+    }
+}
+
+/// An RPC call in flight. Dropping it blocks until the call completes.
+///
+/// Leaking it with [`core::mem::forget`] leaves `recv` untouched, the client waits for the call
+/// to complete before its next use.
+#[must_use]
+pub struct PendingCall<'a> {
+    client: &'a mut SifRpcClient,
+    recv: &'a mut [u8],
+    done: bool,
+}
+
+impl PendingCall<'_> {
+    /// Returns `true` once the server replied.
+    pub fn is_finished(&mut self) -> bool {
+        if !self.done && self.client.sema.poll() {
+            self.finish();
+        }
+        self.done
+    }
+
+    /// Block until the server replied.
+    pub fn wait(mut self) {
+        self.wait_inner();
+    }
+
+    fn wait_inner(&mut self) {
+        if !self.done {
+            self.client.sema.wait();
+            self.finish();
+        }
+    }
+
+    fn finish(&mut self) {
+        self.done = true;
+        self.client.pending = false;
+
+        let len = self.recv.len();
+        if len != 0 {
+            let buf = &self.client.recv_buf;
+            unsafe { arch::cache_dhwbin(buf.as_ptr() as *const (), len.next_multiple_of(64)) };
+            self.recv.copy_from_slice(buf.as_slice(len));
+        }
+    }
+}
+
+impl Drop for PendingCall<'_> {
+    fn drop(&mut self) {
+        self.wait_inner();
+    }
+}
+
+impl Debug for PendingCall<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("PendingCall")
+            .field("client", &self.client)
+            .field("done", &self.done)
+            .finish_non_exhaustive()
+    }
+}
+
+const POOL_SIZE: usize = 4;
+
+/// A pool of clients bound to the same server, for services shared between threads.
+///
+/// Clients are bound on demand, and rebound after an IOP reset.
+pub struct ClientPool {
+    sid: u32,
+    clients: Mutex<RefCell<[Option<SifRpcClient>; POOL_SIZE]>>,
+}
+
+impl ClientPool {
+    pub const fn new(sid: u32) -> Self {
+        Self {
+            sid,
+            clients: Mutex::new(RefCell::new([const { None }; POOL_SIZE])),
+        }
+    }
+
+    /// Take a client from the pool, binding a new one if none is free.
+    pub fn get(&self) -> PooledClient<'_> {
+        loop {
+            let client = critical_section::with(|cs| {
+                let mut clients = self.clients.borrow_ref_mut(cs);
+                clients.iter_mut().find_map(Option::take)
+            });
+
+            match client {
+                Some(client) if client.is_valid() => {
+                    return PooledClient {
+                        pool: self,
+                        client: Some(client),
+                    }
+                }
+                // Stale client, drop it outside of the critical section
+                Some(_) => continue,
+                None => {
+                    return PooledClient {
+                        pool: self,
+                        client: Some(SifRpcClient::bind(self.sid)),
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Debug for ClientPool {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ClientPool")
+            .field("sid", &self.sid)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A client borrowed from a [`ClientPool`], returned to it when dropped.
+#[derive(Debug)]
+pub struct PooledClient<'a> {
+    pool: &'a ClientPool,
+    client: Option<SifRpcClient>,
+}
+
+impl Deref for PooledClient<'_> {
+    type Target = SifRpcClient;
+
+    fn deref(&self) -> &SifRpcClient {
+        self.client.as_ref().unwrap()
+    }
+}
+
+impl DerefMut for PooledClient<'_> {
+    fn deref_mut(&mut self) -> &mut SifRpcClient {
+        self.client.as_mut().unwrap()
+    }
+}
+
+impl Drop for PooledClient<'_> {
+    fn drop(&mut self) {
+        let client = self.client.take().filter(SifRpcClient::is_valid);
+        let rest = critical_section::with(|cs| {
+            let mut clients = self.pool.clients.borrow_ref_mut(cs);
+            match clients.iter_mut().find(|slot| slot.is_none()) {
+                Some(slot) => {
+                    *slot = client;
+                    None
+                }
+                None => client,
+            }
+        });
+        // Pool is full, unbind it outside of the critical section
+        drop(rest);
+    }
+}
+
+/// A service on the IOP that needs to be started once, and again after an IOP reset.
+///
+/// A client stays bound once the service is started, to notice resets.
+pub struct Service {
+    sid: u32,
+    starting: AtomicBool,
+    ready: Mutex<RefCell<Option<SifRpcClient>>>,
+}
+
+impl Service {
+    pub const fn new(sid: u32) -> Self {
+        Self {
+            sid,
+            starting: AtomicBool::new(false),
+            ready: Mutex::new(RefCell::new(None)),
+        }
+    }
+
+    pub fn sid(&self) -> u32 {
+        self.sid
+    }
+
+    /// Whether the service was started since the last IOP reset.
+    pub fn is_ready(&self) -> bool {
+        critical_section::with(|cs| {
+            self.ready
+                .borrow_ref(cs)
+                .as_ref()
+                .is_some_and(SifRpcClient::is_valid)
+        })
+    }
+
+    /// Call `start` unless the service is ready, concurrent callers wait for it to finish.
+    ///
+    /// `start` is told whether the server is already registered, e.g. by a previous program,
+    /// otherwise it has to load the modules providing it.
+    pub fn init<E>(&self, start: impl FnOnce(bool) -> Result<(), E>) -> Result<(), E> {
+        while self.starting.swap(true, Ordering::Acquire) {
+            thread::sleep_for(BIND_RETRY);
+        }
+        let res = self.start(start);
+        self.starting.store(false, Ordering::Release);
+        res
+    }
+
+    fn start<E>(&self, start: impl FnOnce(bool) -> Result<(), E>) -> Result<(), E> {
+        if self.is_ready() {
+            return Ok(());
+        }
+
+        let client = SifRpcClient::try_bind(self.sid);
+        start(client.is_some())?;
+        let client = client.unwrap_or_else(|| SifRpcClient::bind(self.sid));
+
+        let stale = critical_section::with(|cs| self.ready.borrow_ref_mut(cs).replace(client));
+        // Unbind outside of the critical section
+        drop(stale);
+        Ok(())
+    }
+}
+
+impl Debug for Service {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Service")
+            .field("sid", &self.sid)
+            .field("ready", &self.is_ready())
+            .finish()
+    }
+}
@@ -0,0 +1,11 @@
+use rps2::cdvd::{self, ReadMode};
+
+#[rps2_libtest::test]
+fn test_cdvd_drive() {
+    assert!(cdvd::tray_request(cdvd::TrayRequest::Check).is_ok());
+    assert_eq!(
+        cdvd::read_sectors(16, &mut [0; 100], ReadMode::default()),
+        Err(cdvd::Error::InvalidLength)
+    );
+    assert!(cdvd::sync(false));
+}
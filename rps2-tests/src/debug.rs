@@ -0,0 +1,35 @@
+use rps2::debug::{self, ring};
+
+#[rps2_libtest::test]
+fn test_debug_ring() {
+    ring::clear();
+    ring::sink("hello ");
+    ring::sink("world");
+    assert_eq!(ring::len(), 11);
+
+    let mut buf = [0; 8];
+    assert_eq!(ring::read(&mut buf), 8);
+    assert_eq!(&buf, b"hello wo");
+    assert_eq!(ring::read(&mut buf), 3);
+    assert_eq!(&buf[..3], b"rld");
+    assert!(ring::is_empty());
+
+    // Only the latest output is kept
+    for _ in 0..ring::SIZE / 4 + 1 {
+        ring::sink("abcd");
+    }
+    assert_eq!(ring::len(), ring::SIZE);
+    assert_eq!(ring::read(&mut buf), 8);
+    assert_eq!(&buf, b"abcdabcd");
+
+    ring::clear();
+    assert!(ring::is_empty());
+}
+
+#[rps2_libtest::test]
+fn test_debug_set_sink() {
+    let prev = debug::set_sink(ring::sink);
+    assert_eq!(debug::sink() as usize, ring::sink as usize);
+    assert_eq!(debug::set_sink(prev) as usize, ring::sink as usize);
+    assert_eq!(debug::sink() as usize, prev as usize);
+}
@@ -0,0 +1,41 @@
+use rps2::dma::{Channel, ChannelId, DmaBuffer, BUFFER_ALIGN};
+use rps2::pac::gif::Packet;
+use rps2::pac::gs::general::Texflush;
+
+#[rps2_libtest::test]
+fn test_dma_buffer_alignment() {
+    let buf = DmaBuffer::new([0u8; 3]);
+    assert_eq!(buf.as_ptr() as usize % BUFFER_ALIGN, 0);
+    assert_eq!(buf.qwc(), 1);
+
+    let buf = DmaBuffer::from_slice(&[1u32, 2, 3, 4, 5]);
+    assert_eq!(buf.as_ptr() as *const u8 as usize % BUFFER_ALIGN, 0);
+    assert_eq!(&*buf, &[1, 2, 3, 4, 5]);
+    assert_eq!(buf.size(), 20);
+    assert_eq!(buf.qwc(), 2);
+}
+
+#[rps2_libtest::test]
+fn test_dma_channel_exclusive() {
+    let gif = Channel::take(ChannelId::Gif).unwrap();
+    assert!(Channel::take(ChannelId::Gif).is_none());
+
+    drop(gif);
+    assert!(Channel::take(ChannelId::Gif).is_some());
+}
+
+#[rps2_libtest::test]
+fn test_dma_gif_send() {
+    let mut packet = Packet::new();
+    packet.a_d().reg(Texflush::new());
+
+    let buf = DmaBuffer::from_slice(packet.as_slice());
+    let mut gif = Channel::take(ChannelId::Gif).unwrap();
+
+    let transfer = gif.send(buf);
+    let buf = transfer.wait();
+    assert_eq!(buf.qwc(), 2);
+
+    // A second transfer on the same buffer must work as well
+    drop(gif.send(buf));
+}
@@ -0,0 +1,62 @@
+use rps2::fs::{self, File, OpenOptions};
+use rps2::io::{Error, ErrorKind, Read, Seek, SeekFrom};
+use rps2::prelude::*;
+
+const DIR: &str = "host:rps2-tests";
+
+#[rps2_libtest::test]
+fn test_io_error_kind() {
+    assert_eq!(Error::from_raw_os_error(2).kind(), ErrorKind::NotFound);
+    assert_eq!(
+        Error::from_raw_os_error(17).kind(),
+        ErrorKind::AlreadyExists
+    );
+    assert_eq!(
+        Error::from_raw_os_error(90).kind(),
+        ErrorKind::DirectoryNotEmpty
+    );
+    assert_eq!(Error::from_raw_os_error(2).raw_os_error(), Some(2));
+    assert_eq!(Error::from(ErrorKind::Other).raw_os_error(), None);
+}
+
+#[rps2_libtest::test]
+fn test_fs_open_options() {
+    let err = OpenOptions::new().open("host:nothing").unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+
+    let err = OpenOptions::new()
+        .read(true)
+        .truncate(true)
+        .open("host:nothing")
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+}
+
+#[rps2_libtest::test]
+fn test_fs_host_roundtrip() {
+    fs::create_dir_all(DIR).unwrap();
+
+    let path = "host:rps2-tests/file.bin";
+    let data: Vec<u8> = (0..1000).map(|i| i as u8).collect();
+    fs::write(path, &data).unwrap();
+    assert_eq!(fs::read(path).unwrap(), data);
+    assert_eq!(fs::metadata(path).unwrap().len(), 1000);
+
+    // Unaligned reads go through the bounce buffer
+    let mut file = File::open(path).unwrap();
+    assert_eq!(file.seek(SeekFrom::Start(3)).unwrap(), 3);
+    let mut buf = [0; 7];
+    assert_eq!(file.read(&mut buf).unwrap(), 7);
+    assert_eq!(&buf, &data[3..10]);
+    drop(file);
+
+    let names: Vec<String> = fs::read_dir(DIR)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into())
+        .collect();
+    assert_eq!(names, ["file.bin"]);
+
+    fs::remove_file(path).unwrap();
+    assert_eq!(fs::metadata(path).unwrap_err().kind(), ErrorKind::NotFound);
+    fs::remove_dir(DIR).unwrap();
+}
@@ -0,0 +1,26 @@
+use rps2::interrupt::{IntcCause, IntcHandler};
+use rps2::sync::atomic::{AtomicU32, Ordering};
+use rps2::sync::{Arc, Sema};
+
+#[rps2_libtest::test]
+fn test_intc_handler() {
+    let sema = Sema::new().unwrap();
+    let counter = Arc::new(AtomicU32::new(0));
+
+    let sid = sema.id();
+    let handler_counter = counter.clone();
+    let handler = IntcHandler::register(IntcCause::VblankStart, move |ctx| {
+        handler_counter.fetch_add(1, Ordering::Relaxed);
+        ctx.signal_sema(sid);
+    })
+    .unwrap();
+
+    sema.wait();
+    sema.wait();
+    drop(handler);
+
+    // The closure must not run after the guard is dropped
+    let count = counter.load(Ordering::Relaxed);
+    assert!(count >= 2);
+    assert_eq!(Arc::strong_count(&counter), 1);
+}
@@ -0,0 +1,110 @@
+use rps2::io::{
+    self, BufRead, BufReader, BufWriter, Cursor, ErrorKind, Read, Seek, SeekFrom, Write,
+};
+use rps2::prelude::*;
+
+#[rps2_libtest::test]
+fn test_io_read_helpers() {
+    let mut reader: &[u8] = b"hello world";
+    let mut buf = [0; 5];
+    reader.read_exact(&mut buf).unwrap();
+    assert_eq!(&buf, b"hello");
+
+    let mut rest = String::new();
+    reader.read_to_string(&mut rest).unwrap();
+    assert_eq!(rest, " world");
+
+    let err = reader.read_exact(&mut buf).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+
+    let bytes: Vec<u8> = (&b"abcdef"[..])
+        .take(3)
+        .bytes()
+        .map(|byte| byte.unwrap())
+        .collect();
+    assert_eq!(bytes, b"abc");
+}
+
+#[rps2_libtest::test]
+fn test_io_write_helpers() {
+    let mut buf = [0; 4];
+    let mut writer = &mut buf[..];
+    let err = writer.write_all(b"hello").unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::WriteZero);
+    assert_eq!(&buf, b"hell");
+
+    let mut out = Vec::new();
+    write!(out, "{}-{:02x}", 12, 0xa).unwrap();
+    assert_eq!(out, b"12-0a");
+}
+
+#[rps2_libtest::test]
+fn test_io_cursor() {
+    let mut cursor = Cursor::new(Vec::new());
+    cursor.write_all(b"hello").unwrap();
+    cursor.seek(SeekFrom::Start(1)).unwrap();
+    cursor.write_all(b"EL").unwrap();
+    // Writing past the end fills the gap with zeros
+    cursor.seek(SeekFrom::End(2)).unwrap();
+    cursor.write_all(b"!").unwrap();
+    assert_eq!(cursor.get_ref(), b"hELlo\0\0!");
+
+    cursor.rewind().unwrap();
+    let mut buf = [0; 3];
+    assert_eq!(cursor.read(&mut buf).unwrap(), 3);
+    assert_eq!(&buf, b"hEL");
+    assert_eq!(cursor.stream_position().unwrap(), 3);
+
+    let err = cursor.seek(SeekFrom::Current(-4)).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+
+    let mut cursor = Cursor::new([0; 4]);
+    assert_eq!(cursor.write(b"hello").unwrap(), 4);
+    assert_eq!(cursor.write(b"!").unwrap(), 0);
+}
+
+#[rps2_libtest::test]
+fn test_io_buf_reader() {
+    let data: &[u8] = b"first\nsecond\r\n\nlast";
+    let mut reader = BufReader::with_capacity(4, data);
+
+    let mut line = String::new();
+    assert_eq!(reader.read_line(&mut line).unwrap(), 6);
+    assert_eq!(line, "first\n");
+
+    let lines: Vec<String> = reader.lines().map(|line| line.unwrap()).collect();
+    assert_eq!(lines, ["second", "", "last"]);
+
+    let mut reader = BufReader::with_capacity(4, Cursor::new(b"0123456789"));
+    assert_eq!(reader.fill_buf().unwrap(), b"0123");
+    reader.consume(1);
+    // Relative seeks account for the buffered data
+    assert_eq!(reader.seek(SeekFrom::Current(2)).unwrap(), 3);
+    assert_eq!(reader.fill_buf().unwrap(), b"3456");
+}
+
+#[rps2_libtest::test]
+fn test_io_buf_writer() {
+    let mut writer = BufWriter::with_capacity(4, Vec::new());
+    writer.write_all(b"ab").unwrap();
+    assert!(writer.get_ref().is_empty());
+    assert_eq!(writer.buffer(), b"ab");
+
+    writer.write_all(b"cde").unwrap();
+    assert_eq!(writer.get_ref(), b"ab");
+
+    // Writes larger than the buffer skip it
+    writer.write_all(b"0123456789").unwrap();
+    assert_eq!(writer.into_inner().unwrap(), b"abcde0123456789");
+}
+
+#[rps2_libtest::test]
+fn test_io_copy() {
+    let data: Vec<u8> = (0..10000).map(|i| i as u8).collect();
+    let mut out = Vec::new();
+    assert_eq!(io::copy(&mut &data[..], &mut out).unwrap(), 10000);
+    assert_eq!(out, data);
+
+    writeln!(io::stdout(), "io::stdout works").unwrap();
+    io::stderr().write_all(b"io::stderr works\n").unwrap();
+}
@@ -0,0 +1,30 @@
+use rps2::sif::iop::{self, Error};
+use rps2::sif::loadfile;
+
+#[rps2_libtest::test]
+fn test_iop_reset() {
+    let image = "x".repeat(80);
+    assert_eq!(iop::reset(Some(&image)), Err(Error::ImageTooLong));
+
+    iop::reset(None).unwrap();
+    assert!(iop::is_booted());
+
+    // RPC must work again after the reset
+    assert_eq!(
+        loadfile::load_module("rom0:DOESNOTEXIST", &[]),
+        Err(loadfile::Error::NoFile)
+    );
+}
+
+#[rps2_libtest::test]
+fn test_iop_heap() {
+    let addr = iop::alloc_heap(1024).unwrap();
+    iop::write(addr, &[0xaa; 1024]);
+    assert!(iop::free_heap(addr));
+}
+
+#[rps2_libtest::test]
+#[should_panic]
+fn test_iop_write_partial_quadword() {
+    iop::write(0x10_0000, &[0xaa; 20]);
+}
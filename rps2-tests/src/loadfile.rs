@@ -0,0 +1,42 @@
+use rps2::sif::loadfile::{self, Error, PATH_MAX};
+
+#[rps2_libtest::test]
+fn test_load_module_errors() {
+    let long = "x".repeat(PATH_MAX);
+    assert_eq!(loadfile::load_module(&long, &[]), Err(Error::PathTooLong));
+
+    let arg = "y".repeat(200);
+    assert_eq!(
+        loadfile::load_module("rom0:SIO2MAN", &[&arg, &arg]),
+        Err(Error::ArgsTooLong)
+    );
+
+    assert_eq!(
+        loadfile::load_module("rom0:DOESNOTEXIST", &[]),
+        Err(Error::NoFile)
+    );
+}
+
+#[rps2_libtest::test]
+fn test_error_codes() {
+    assert_eq!(Error::from_code(-203), Error::NoFile);
+    assert_eq!(Error::from_code(-400), Error::NoMemory);
+    assert_eq!(Error::from_code(-1), Error::Other(-1));
+}
+
+#[rps2_libtest::test]
+fn test_load_module_buffer() {
+    loadfile::enable_load_module_buffer().unwrap();
+    loadfile::enable_load_module_buffer().unwrap();
+
+    assert_eq!(
+        loadfile::load_module_buffer(&[0; 100], &[]),
+        Err(Error::IllegalObject)
+    );
+
+    // Other functions are forwarded to LOADFILE
+    assert_eq!(
+        loadfile::load_module("rom0:DOESNOTEXIST", &[]),
+        Err(Error::NoFile)
+    );
+}
@@ -0,0 +1,9 @@
+use rps2::logger::{self, LevelFilter};
+
+#[rps2_libtest::test]
+fn test_logger_level() {
+    let prev = logger::level();
+    logger::set_level(LevelFilter::Debug);
+    assert_eq!(logger::level(), LevelFilter::Debug);
+    logger::set_level(prev);
+}
@@ -1,6 +1,22 @@
 #![no_std]
 
+mod cdvd;
+mod debug;
+mod dma;
+mod fs;
+mod interrupt;
+mod io;
+mod iop;
+mod loadfile;
+mod logger;
+mod memcard;
+mod pad;
 mod sync;
+mod thread;
+mod time;
+mod timeout;
+mod vblank;
+mod video;
 
 fn main() {
     rps2_libtest::start::start();
@@ -0,0 +1,9 @@
+use rps2::memcard;
+
+#[rps2_libtest::test]
+fn test_memcard_get_info() {
+    let info = memcard::get_info(0, 0).unwrap();
+    if info.card_type == memcard::CardType::Ps2 && info.formatted {
+        assert!(!memcard::getdir(0, 0, "/*").unwrap().is_empty());
+    }
+}
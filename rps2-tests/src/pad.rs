@@ -0,0 +1,11 @@
+use rps2::pad::{Pad, Status};
+
+#[rps2_libtest::test]
+fn test_pad_open() {
+    let mut pad = Pad::open(0, 0).unwrap();
+    assert_ne!(pad.status(), Status::Error);
+    drop(pad);
+
+    // Ports can be opened again once closed
+    Pad::open(0, 0).unwrap();
+}
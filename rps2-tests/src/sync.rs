@@ -1,4 +1,8 @@
+use core::time::Duration;
+
+use rps2::prelude::*;
 use rps2::sync::atomic::{AtomicU32, Ordering};
+use rps2::sync::{Arc, Condvar, Mutex, RwLock, RwLockUpgradableReadGuard, RwLockWriteGuard};
 
 fn very_expensive() -> u32 {
     42
@@ -26,3 +30,233 @@ fn test_lazy_lock() {
     // Make sure it didn't trigger again
     assert_eq!(counter.load(Ordering::Relaxed), 1);
 }
+
+#[rps2_libtest::test]
+fn test_condvar_wait_while() {
+    let pair = Arc::new((Mutex::new(0u32), Condvar::new()));
+    let pair2 = pair.clone();
+
+    let handle = rps2::thread::spawn(move || {
+        let (lock, cvar) = &*pair2;
+        for _ in 0..3 {
+            *lock.lock() += 1;
+            cvar.notify_one();
+        }
+    })
+    .unwrap();
+
+    let (lock, cvar) = &*pair;
+    let guard = cvar.wait_while(lock.lock(), |count| *count < 3);
+    assert_eq!(*guard, 3);
+    drop(guard);
+
+    handle.join().unwrap();
+}
+
+#[rps2_libtest::test]
+fn test_condvar_notify_all() {
+    let pair = Arc::new((Mutex::new(false), Condvar::new()));
+    let handles = (0..3)
+        .map(|_| {
+            let pair = pair.clone();
+            rps2::thread::spawn(move || {
+                let (lock, cvar) = &*pair;
+                let _guard = cvar.wait_while(lock.lock(), |ready| !*ready);
+            })
+            .unwrap()
+        })
+        .collect::<Vec<_>>();
+
+    let (lock, cvar) = &*pair;
+    *lock.lock() = true;
+    cvar.notify_all();
+
+    for handle in handles {
+        handle.join().unwrap();
+    }
+}
+
+#[rps2_libtest::test]
+fn test_condvar_wait_timeout() {
+    let lock = Mutex::new(());
+    let cvar = Condvar::new();
+
+    let (guard, res) = cvar.wait_timeout(lock.lock(), Duration::from_millis(10));
+    assert!(res.timed_out());
+    drop(guard);
+
+    // Nobody is left waiting, so this is a no-op
+    cvar.notify_one();
+    let (_guard, res) = cvar.wait_timeout(lock.lock(), Duration::from_millis(10));
+    assert!(res.timed_out());
+}
+
+#[rps2_libtest::test]
+fn test_rwlock() {
+    let lock = RwLock::new(1);
+
+    let a = lock.read();
+    let b = lock.read();
+    assert_eq!(*a + *b, 2);
+    assert!(lock.try_write().is_none());
+    drop((a, b));
+
+    let mut guard = lock.write();
+    *guard += 1;
+    assert!(lock.try_read().is_none());
+    assert!(lock.try_upgradable_read().is_none());
+
+    let guard = RwLockWriteGuard::downgrade(guard);
+    assert_eq!(*guard, 2);
+    assert!(lock.try_read().is_some());
+    drop(guard);
+
+    assert_eq!(lock.into_inner(), 2);
+}
+
+#[rps2_libtest::test]
+fn test_rwlock_upgradable() {
+    let lock = RwLock::new(0);
+
+    let upgradable = lock.upgradable_read();
+    // Plain readers can share the lock, another upgradable reader can't
+    let reader = lock.try_read().unwrap();
+    assert!(lock.try_upgradable_read().is_none());
+
+    let upgradable = RwLockUpgradableReadGuard::try_upgrade(upgradable).unwrap_err();
+    drop(reader);
+
+    let mut guard = RwLockUpgradableReadGuard::upgrade(upgradable);
+    *guard = 42;
+    drop(guard);
+
+    assert_eq!(*lock.read(), 42);
+}
+
+#[rps2_libtest::test]
+fn test_rwlock_writer_preference() {
+    let lock = Arc::new(RwLock::new(0));
+    let reader = lock.read();
+
+    let lock2 = lock.clone();
+    let handle = rps2::thread::spawn(move || *lock2.write() += 1).unwrap();
+    // Let the writer block on the lock
+    rps2::thread::sleep_for(Duration::from_millis(5));
+
+    // New readers queue up behind the waiting writer
+    assert!(lock.try_read().is_none());
+    drop(reader);
+
+    handle.join().unwrap();
+    assert_eq!(*lock.read(), 1);
+}
+
+#[rps2_libtest::test]
+fn test_mpsc_channel() {
+    use rps2::sync::mpsc::{self, RecvError, RecvTimeoutError, SendError, TryRecvError};
+
+    let (tx, rx) = mpsc::channel();
+    let handles = (0..3)
+        .map(|i| {
+            let tx = tx.clone();
+            rps2::thread::spawn(move || {
+                for j in 0..10 {
+                    tx.send(i * 10 + j).unwrap();
+                }
+            })
+            .unwrap()
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        rx.recv_timeout(Duration::from_millis(100)).map(|_| ()),
+        Ok(())
+    );
+    drop(tx);
+
+    // Ends once every sender is gone
+    assert_eq!(rx.iter().count(), 29);
+    for handle in handles {
+        handle.join().unwrap();
+    }
+
+    assert_eq!(rx.recv(), Err(RecvError));
+    assert_eq!(rx.try_recv(), Err(TryRecvError::Disconnected));
+    assert_eq!(
+        rx.recv_timeout(Duration::from_millis(10)),
+        Err(RecvTimeoutError::Disconnected)
+    );
+
+    let (tx, rx) = mpsc::channel::<u32>();
+    assert_eq!(rx.try_recv(), Err(TryRecvError::Empty));
+    assert_eq!(
+        rx.recv_timeout(Duration::from_millis(10)),
+        Err(RecvTimeoutError::Timeout)
+    );
+    drop(rx);
+    assert_eq!(tx.send(1), Err(SendError(1)));
+}
+
+#[rps2_libtest::test]
+fn test_mpsc_sync_channel() {
+    use rps2::sync::mpsc::{self, TrySendError};
+
+    let (tx, rx) = mpsc::sync_channel(2);
+    tx.try_send(1).unwrap();
+    tx.send(2).unwrap();
+    assert_eq!(tx.try_send(3), Err(TrySendError::Full(3)));
+
+    // A blocked receiver wakes up when the last sender goes away
+    let handle = rps2::thread::spawn(move || rx.into_iter().collect::<Vec<_>>()).unwrap();
+    rps2::thread::sleep_for(Duration::from_millis(5));
+    tx.send(3).unwrap();
+    drop(tx);
+    assert_eq!(handle.join().unwrap(), [1, 2, 3]);
+
+    // A sender blocked on a full channel wakes up when the receiver goes away
+    let (tx, rx) = mpsc::sync_channel(1);
+    tx.send(1).unwrap();
+    let handle = rps2::thread::spawn(move || tx.send(2)).unwrap();
+    rps2::thread::sleep_for(Duration::from_millis(5));
+    drop(rx);
+    assert_eq!(handle.join().unwrap(), Err(mpsc::SendError(2)));
+
+    // Senders find out about a dropped receiver before blocking as well
+    let (tx, rx) = mpsc::sync_channel(1);
+    drop(rx);
+    assert_eq!(tx.send(1), Err(mpsc::SendError(1)));
+}
+
+#[rps2_libtest::test]
+fn test_mpsc_irq_send() {
+    use rps2::interrupt::{IntcCause, IntcHandler};
+    use rps2::sync::mpsc::{self, RecvError, TrySendError};
+
+    let (tx, rx) = mpsc::sync_channel(2);
+    let full = Arc::new(AtomicU32::new(0));
+
+    let handler_full = full.clone();
+    let mut next = 0;
+    let handler = IntcHandler::register(IntcCause::VblankStart, move |_| {
+        match unsafe { tx.irq_try_send(next) } {
+            Ok(()) => next += 1,
+            Err(TrySendError::Full(_)) => {
+                handler_full.fetch_add(1, Ordering::Relaxed);
+            }
+            Err(TrySendError::Disconnected(_)) => unreachable!(),
+        }
+    })
+    .unwrap();
+
+    // A blocked receiver is woken up by the handler
+    assert_eq!(rx.recv(), Ok(0));
+    assert_eq!(rx.recv(), Ok(1));
+
+    // The handler is told the channel is full instead of blocking
+    while full.load(Ordering::Relaxed) == 0 {
+        rps2::thread::sleep_for(Duration::from_millis(5));
+    }
+    // Dropping the handler drops the sender
+    drop(handler);
+    assert_eq!(rx.try_iter().collect::<Vec<_>>(), [2, 3]);
+    assert_eq!(rx.recv(), Err(RecvError));
+}
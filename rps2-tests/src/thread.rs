@@ -0,0 +1,110 @@
+use core::cell::{Cell, RefCell};
+
+use rps2::prelude::*;
+use rps2::sync::atomic::{AtomicU32, Ordering};
+use rps2::thread::AccessError;
+use rps2::thread_local;
+
+static DROPPED: AtomicU32 = AtomicU32::new(0);
+
+struct Tracked(u32);
+
+impl Drop for Tracked {
+    fn drop(&mut self) {
+        DROPPED.fetch_add(self.0, Ordering::Relaxed);
+        // Destroyed values can't be accessed anymore
+        assert_eq!(TRACKED.try_with(|_| ()), Err(AccessError));
+    }
+}
+
+thread_local! {
+    static COUNTER: Cell<u32> = Cell::new(0);
+    static NAMES: RefCell<Vec<&'static str>> = RefCell::new(Vec::new());
+    static TRACKED: Tracked = Tracked(COUNTER.with(Cell::get));
+}
+
+#[rps2_libtest::test]
+fn test_thread_local() {
+    COUNTER.with(|counter| counter.set(1));
+    NAMES.with(|names| names.borrow_mut().push("main"));
+
+    let handle = rps2::thread::spawn(|| {
+        // Each thread starts from a fresh value
+        let counter = COUNTER.with(|counter| {
+            counter.set(counter.get() + 10);
+            counter.get()
+        });
+        NAMES.with(|names| names.borrow_mut().push("worker"));
+        (counter, NAMES.with(|names| names.borrow().len()))
+    })
+    .unwrap();
+
+    assert_eq!(handle.join().unwrap(), (10, 1));
+    assert_eq!(COUNTER.with(Cell::get), 1);
+    NAMES.with(|names| assert_eq!(*names.borrow(), ["main"]));
+}
+
+#[rps2_libtest::test]
+fn test_thread_local_dtors() {
+    let before = DROPPED.load(Ordering::Relaxed);
+
+    let handle = rps2::thread::spawn(|| {
+        COUNTER.with(|counter| counter.set(5));
+        TRACKED.with(|tracked| tracked.0)
+    })
+    .unwrap();
+
+    // Values are dropped before the thread is joined
+    assert_eq!(handle.join().unwrap(), 5);
+    assert_eq!(DROPPED.load(Ordering::Relaxed) - before, 5);
+
+    // A thread reusing the id starts from fresh values
+    let handle = rps2::thread::spawn(|| COUNTER.try_with(Cell::get)).unwrap();
+    assert_eq!(handle.join().unwrap(), Ok(0));
+}
+
+#[rps2_libtest::test]
+fn test_thread_scope() {
+    let mut data = [1, 2, 3, 4];
+    let total = AtomicU32::new(0);
+
+    let doubled = rps2::thread::scope(|s| {
+        for chunk in data.chunks(2) {
+            let total = &total;
+            s.spawn(move || {
+                let sum: u32 = chunk.iter().sum();
+                total.fetch_add(sum, Ordering::Relaxed);
+            })
+            .unwrap();
+        }
+
+        let handle = s
+            .spawn(|| data.iter().map(|x| x * 2).collect::<Vec<_>>())
+            .unwrap();
+        handle.join().unwrap()
+    });
+
+    // The unjoined threads are done once the scope returns
+    assert_eq!(total.load(Ordering::Relaxed), 10);
+    assert_eq!(doubled, [2, 4, 6, 8]);
+
+    // Borrows ended with the scope
+    data[0] = 0;
+    assert_eq!(data[0], 0);
+}
+
+#[rps2_libtest::test]
+fn test_thread_scope_panic() {
+    let res = rps2::panic::catch_unwind(|| {
+        rps2::thread::scope(|s| {
+            s.spawn(|| panic!("scoped")).unwrap();
+        })
+    });
+    assert!(res.is_err());
+
+    // A panic that was joined explicitly is not propagated
+    rps2::thread::scope(|s| {
+        let handle = s.spawn(|| panic!("scoped")).unwrap();
+        assert!(handle.join().is_err());
+    });
+}
@@ -0,0 +1,37 @@
+use rps2::time::{Duration, Instant};
+
+#[rps2_libtest::test]
+fn test_instant_monotonic() {
+    let mut last = Instant::now();
+    for _ in 0..1000 {
+        let now = Instant::now();
+        assert!(now >= last);
+        last = now;
+    }
+}
+
+#[rps2_libtest::test]
+fn test_instant_elapsed() {
+    let start = Instant::now();
+    rps2::thread::sleep_for(Duration::from_millis(20));
+
+    let elapsed = start.elapsed();
+    assert!(elapsed >= Duration::from_millis(20));
+    assert!(elapsed < Duration::from_secs(1));
+}
+
+#[rps2_libtest::test]
+fn test_instant_arithmetic() {
+    let now = Instant::now();
+    let later = now + Duration::from_secs(100);
+
+    assert!(later > now);
+    assert_eq!(later - Duration::from_secs(100), now);
+
+    let diff = later - now;
+    assert!(diff <= Duration::from_secs(100));
+    assert!(diff > Duration::from_millis(99_999));
+
+    assert_eq!(now.checked_duration_since(later), None);
+    assert_eq!(now.saturating_duration_since(later), Duration::ZERO);
+}
@@ -0,0 +1,59 @@
+use core::time::Duration;
+
+use rps2::sync::mpmc::BoundedQueue;
+use rps2::sync::{Arc, Mutex, Sema};
+
+#[rps2_libtest::test]
+fn test_sleep_for() {
+    rps2::thread::sleep_for(Duration::from_millis(5));
+    rps2::thread::sleep_for(Duration::ZERO);
+}
+
+#[rps2_libtest::test]
+fn test_sema_wait_timeout() {
+    let sema = Sema::builder().init_count(1).max_count(1).build().unwrap();
+
+    assert!(sema.wait_timeout(Duration::from_millis(10)));
+    assert!(!sema.wait_timeout(Duration::from_millis(10)));
+
+    let sema = Arc::new(sema);
+    let sema2 = sema.clone();
+    let handle = rps2::thread::spawn(move || sema2.signal()).unwrap();
+
+    assert!(sema.wait_timeout(Duration::from_secs(1)));
+    handle.join().unwrap();
+}
+
+#[rps2_libtest::test]
+fn test_mutex_lock_timeout() {
+    let mutex = Mutex::new(0);
+
+    let guard = mutex.lock();
+    assert!(mutex.lock_timeout(Duration::from_millis(10)).is_none());
+    drop(guard);
+
+    *mutex.lock_timeout(Duration::from_millis(10)).unwrap() += 1;
+    assert_eq!(*mutex.lock(), 1);
+}
+
+#[rps2_libtest::test]
+fn test_queue_pop_timeout() {
+    let queue = BoundedQueue::new(2);
+    assert_eq!(queue.pop_timeout(Duration::from_millis(10)), None);
+
+    queue.push(42);
+    assert_eq!(queue.pop_timeout(Duration::from_millis(10)), Some(42));
+
+    // Many concurrent timeouts share the same wheel
+    let queue = Arc::new(queue);
+    let handles = (0..4)
+        .map(|i| {
+            let queue = queue.clone();
+            rps2::thread::spawn(move || queue.pop_timeout(Duration::from_millis(5 * i))).unwrap()
+        })
+        .collect::<rps2::vec::Vec<_>>();
+
+    for handle in handles {
+        assert_eq!(handle.join().unwrap(), None);
+    }
+}
@@ -0,0 +1,26 @@
+use rps2::video::{frame_count, wait_vblank, VBlank};
+
+#[rps2_libtest::test]
+fn test_vblank_wait() {
+    let vblank = VBlank::subscribe();
+
+    let first = vblank.wait();
+    let second = vblank.wait();
+    assert!(second > first);
+    assert!(frame_count() >= second);
+}
+
+#[rps2_libtest::test]
+fn test_vblank_multiple_subscribers() {
+    let handle = rps2::thread::spawn(|| {
+        let vblank = VBlank::subscribe();
+        vblank.wait();
+        vblank.wait()
+    })
+    .unwrap();
+
+    // Both subscribers get woken up by the same handler
+    let first = wait_vblank();
+    let other = handle.join().unwrap();
+    assert!(first > 0 && other > 0);
+}
@@ -0,0 +1,118 @@
+use rps2::pac::gif::Packet;
+use rps2::pac::gs::Psm;
+use rps2::video::{console, Error, Framebuffer, Video, VideoMode};
+
+#[rps2_libtest::test]
+fn test_video_display() {
+    let display = Video::builder(VideoMode::NtscField).display().unwrap();
+    assert_eq!(display.dx(), 632);
+    assert_eq!(display.dy(), 50);
+    assert_eq!(display.magh(), 3);
+    assert_eq!(display.magv(), 0);
+    assert_eq!(display.dw(), 2559);
+    assert_eq!(display.dh(), 447);
+
+    // Smaller resolutions get magnified and centered
+    let display = Video::builder(VideoMode::PalField)
+        .resolution(480, 256)
+        .display()
+        .unwrap();
+    assert_eq!(display.dx(), 652 + 80);
+    assert_eq!(display.dy(), 72);
+    assert_eq!(display.magh(), 4);
+    assert_eq!(display.magv(), 1);
+    assert_eq!(display.dw(), 2399);
+    assert_eq!(display.dh(), 511);
+
+    let res = Video::builder(VideoMode::Dtv480p)
+        .resolution(1920, 480)
+        .display();
+    assert_eq!(res, Err(Error::InvalidResolution));
+}
+
+#[rps2_libtest::test]
+fn test_video_framebuffers() {
+    let (buffers, pages) = Video::builder(VideoMode::NtscField).framebuffers().unwrap();
+    assert_eq!(
+        buffers[0],
+        Framebuffer {
+            fbp: 0,
+            fbw: 10,
+            psm: Psm::CT32
+        }
+    );
+    assert_eq!(
+        buffers[1],
+        Framebuffer {
+            fbp: 140,
+            fbw: 10,
+            psm: Psm::CT32
+        }
+    );
+    assert_eq!(pages, 280);
+
+    let (buffers, pages) = Video::builder(VideoMode::NtscField)
+        .psm(Psm::CT16S)
+        .double_buffered(false)
+        .framebuffers()
+        .unwrap();
+    assert_eq!(buffers[0], buffers[1]);
+    assert_eq!(pages, 70);
+
+    let res = Video::builder(VideoMode::NtscField)
+        .psm(Psm::T8)
+        .framebuffers();
+    assert_eq!(res, Err(Error::UnsupportedPsm));
+}
+
+#[rps2_libtest::test]
+fn test_video_console_text() {
+    console::resize(8, 3);
+    assert_eq!(console::size(), (8, 3));
+
+    console::sink("hello\nwrapped line");
+    assert_eq!(console::line(0), "hello");
+    assert_eq!(console::line(1), "wrapped");
+    assert_eq!(console::line(2), "line");
+
+    // Scrolls once the last row is full
+    console::sink("\n\tx\u{e9}");
+    assert_eq!(console::line(0), "wrapped");
+    assert_eq!(console::line(1), "line");
+    assert_eq!(console::line(2), "    x?");
+
+    console::sink("\rab");
+    assert_eq!(console::line(2), "ab  x?");
+
+    console::clear();
+    assert_eq!(console::line(1), "");
+}
+
+#[rps2_libtest::test]
+fn test_video_console_draw() {
+    console::resize(2, 1);
+    console::sink("A");
+
+    let fb = Framebuffer {
+        fbp: 10,
+        fbw: 10,
+        psm: Psm::CT32,
+    };
+    let mut packet = Packet::new();
+    console::draw(&mut packet, &fb, [0xff, 0xff, 0xff], [0, 0, 0]).unwrap();
+
+    // A+D tag with 4 registers, then an IMAGE tag with 16x8 pixels
+    let data = packet.as_bytes();
+    assert_eq!(data.len(), (5 + 1 + 16 * 8 * 4 / 16) * 16);
+
+    // Top row of the 'A', two columns of the first character
+    let image = &data[6 * 16..];
+    assert_eq!(&image[0..8], &[0, 0, 0, 0x80, 0, 0, 0, 0x80]);
+    assert_eq!(&image[8..12], &[0xff, 0xff, 0xff, 0x80]);
+
+    let fb = Framebuffer { psm: Psm::T8, ..fb };
+    let res = console::draw(&mut Packet::new(), &fb, [0xff; 3], [0; 3]);
+    assert_eq!(res, Err(Error::UnsupportedPsm));
+
+    console::resize(80, 30);
+}
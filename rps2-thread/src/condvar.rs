@@ -0,0 +1,135 @@
+use crate::mutex::{Mutex, MutexGuard};
+use crate::sema::Sema;
+
+use core::cell::RefCell;
+use core::fmt::{self, Debug};
+use core::time::Duration;
+
+/// Whether a [`Condvar::wait_timeout`] returned because of the timeout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WaitTimeoutResult(bool);
+
+impl WaitTimeoutResult {
+    pub fn timed_out(&self) -> bool {
+        self.0
+    }
+}
+
+/// A condition variable, used with a [`Mutex`] to block until some state changes.
+///
+/// Waiters block on a kernel semaphore, each notification signals it once per waiter woken.
+/// Like in `std`, waits can return spuriously, prefer [`Condvar::wait_while`].
+pub struct Condvar {
+    sema: Sema,
+    /// Threads blocked or about to block on `sema`.
+    waiters: critical_section::Mutex<RefCell<u32>>,
+}
+
+impl Condvar {
+    pub fn new() -> Self {
+        let sema = Sema::builder()
+            .init_count(0)
+            .max_count(i32::MAX as u32)
+            .build()
+            .expect("Failed to create semaphore");
+
+        Self {
+            sema,
+            waiters: critical_section::Mutex::new(RefCell::new(0)),
+        }
+    }
+
+    /// Unlock `guard` and block until notified, then lock it again.
+    pub fn wait<'a, T: ?Sized>(&self, guard: MutexGuard<'a, T>) -> MutexGuard<'a, T> {
+        let mutex = self.unlock(guard);
+        self.sema.wait();
+        mutex.lock()
+    }
+
+    /// Block until `condition` returns `false`, it is checked with the lock held.
+    pub fn wait_while<'a, T: ?Sized, F>(
+        &self,
+        mut guard: MutexGuard<'a, T>,
+        mut condition: F,
+    ) -> MutexGuard<'a, T>
+    where
+        F: FnMut(&mut T) -> bool,
+    {
+        while condition(&mut guard) {
+            guard = self.wait(guard);
+        }
+        guard
+    }
+
+    /// Like [`Condvar::wait`], giving up after `timeout`.
+    pub fn wait_timeout<'a, T: ?Sized>(
+        &self,
+        guard: MutexGuard<'a, T>,
+        timeout: Duration,
+    ) -> (MutexGuard<'a, T>, WaitTimeoutResult) {
+        let mutex = self.unlock(guard);
+
+        let timed_out = !self.sema.wait_timeout(timeout) && {
+            let notified = critical_section::with(|cs| {
+                let mut waiters = self.waiters.borrow_ref_mut(cs);
+                // Every waiter was counted out by a notifier, so a signal is on its way
+                let notified = *waiters == 0;
+                if !notified {
+                    *waiters -= 1;
+                }
+                notified
+            });
+            if notified {
+                // Take the signal meant for us, so that it is not left for a later wait
+                self.sema.wait();
+            }
+            !notified
+        };
+
+        (mutex.lock(), WaitTimeoutResult(timed_out))
+    }
+
+    /// Wake up one blocked thread, if any.
+    pub fn notify_one(&self) {
+        let notify = critical_section::with(|cs| {
+            let mut waiters = self.waiters.borrow_ref_mut(cs);
+            let notify = *waiters > 0;
+            if notify {
+                *waiters -= 1;
+            }
+            notify
+        });
+
+        if notify {
+            self.sema.signal();
+        }
+    }
+
+    /// Wake up every blocked thread.
+    pub fn notify_all(&self) {
+        let count = critical_section::with(|cs| self.waiters.replace(cs, 0));
+        for _ in 0..count {
+            self.sema.signal();
+        }
+    }
+
+    /// Register as a waiter, then release the lock.
+    fn unlock<'a, T: ?Sized>(&self, guard: MutexGuard<'a, T>) -> &'a Mutex<T> {
+        critical_section::with(|cs| *self.waiters.borrow_ref_mut(cs) += 1);
+        let mutex = MutexGuard::mutex(&guard);
+        drop(guard);
+        mutex
+    }
+}
+
+impl Default for Condvar {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Debug for Condvar {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Condvar").finish_non_exhaustive()
+    }
+}
@@ -5,7 +5,7 @@ use rps2_kernel::os;
 use core::ffi::c_void;
 use core::mem::MaybeUninit;
 
-pub use os::{thread_status, SemaParam, ThreadParam, ThreadStatus};
+pub use os::{thread_status, AlarmHandler, SemaParam, ThreadParam, ThreadStatus};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Syscall {
@@ -28,6 +28,8 @@ pub enum Syscall {
     WaitSema,
     PollSema,
     ReferSemaStatus,
+    SetAlarm,
+    ReleaseAlarm,
 }
 
 use Syscall::*;
@@ -230,3 +232,36 @@ pub unsafe fn irq_refer_sema_status(sid: i32) -> Result<SemaParam> {
     )
     .map(|_| unsafe { status.assume_init() })
 }
+
+/// Call `handler` with `common` from the alarm interrupt after `time` horizontal syncs.
+///
+/// # Safety
+///
+/// `handler` must end with `ExitHandler` and must only use interrupt safe syscalls, and
+/// `common` must stay valid until the alarm fires or is released.
+pub unsafe fn set_alarm(time: u16, handler: AlarmHandler, common: *mut c_void) -> Result<i32> {
+    handle_res(os::set_alarm(time, handler, common), SetAlarm)
+}
+
+/// Like [`set_alarm`], from an interrupt handler.
+///
+/// # Safety
+///
+/// Same as [`set_alarm`], and must be called from an interrupt handler.
+pub unsafe fn irq_set_alarm(time: u16, handler: AlarmHandler, common: *mut c_void) -> Result<i32> {
+    handle_res(os::i_set_alarm(time, handler, common), SetAlarm)
+}
+
+/// # Safety
+///
+/// `alarm_id` must be an alarm set by the caller, whose `common` data may be freed afterwards.
+pub unsafe fn release_alarm(alarm_id: i32) -> Result<()> {
+    handle_res_none(os::release_alarm(alarm_id), ReleaseAlarm)
+}
+
+/// # Safety
+///
+/// Same as [`release_alarm`], and must be called from an interrupt handler.
+pub unsafe fn irq_release_alarm(alarm_id: i32) -> Result<()> {
+    handle_res_none(os::i_release_alarm(alarm_id), ReleaseAlarm)
+}
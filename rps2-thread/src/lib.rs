@@ -2,13 +2,18 @@
 #![deny(missing_debug_implementations)]
 extern crate alloc;
 
+pub mod condvar;
 pub mod lazy_lock;
 pub mod mpmc;
+pub mod mpsc;
 pub mod mutex;
 pub mod once;
 pub mod once_lock;
+pub mod rwlock;
 pub mod sema;
 pub mod thread;
+pub mod timer;
+pub mod vblank;
 
 pub mod ffi;
 
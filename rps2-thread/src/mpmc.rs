@@ -1,6 +1,7 @@
 use alloc::collections::VecDeque;
 use core::cell::RefCell;
 use core::fmt::{self, Debug};
+use core::time::Duration;
 use critical_section::Mutex;
 
 use crate::sema::Sema;
@@ -72,6 +73,15 @@ impl<T> UnboundedQueue<T> {
         }
     }
 
+    pub fn pop_timeout(&self, timeout: Duration) -> Option<T> {
+        if self.csema.wait_timeout(timeout) {
+            let res = self.inner.pop();
+            Some(res.expect("Queue is actually empty!"))
+        } else {
+            None
+        }
+    }
+
     pub unsafe fn irq_push(&self, val: T) {
         self.inner.push(val);
         self.csema.irq_signal();
@@ -132,6 +142,18 @@ impl<T> BoundedQueue<T> {
         self.csema.signal();
     }
 
+    /// Like [`BoundedQueue::push`], but hands `val` back if `cond` is false once a slot is free.
+    pub(crate) fn push_if(&self, val: T, cond: impl FnOnce() -> bool) -> Result<(), T> {
+        self.psema.wait();
+        if !cond() {
+            self.psema.signal();
+            return Err(val);
+        }
+        self.inner.push(val);
+        self.csema.signal();
+        Ok(())
+    }
+
     pub fn try_push(&self, val: T) -> Result<(), T> {
         if self.psema.poll() {
             self.inner.push(val);
@@ -161,6 +183,17 @@ impl<T> BoundedQueue<T> {
         }
     }
 
+    pub fn pop_timeout(&self, timeout: Duration) -> Option<T> {
+        if self.csema.wait_timeout(timeout) {
+            let res = self.inner.pop();
+            let res = res.expect("Queue is actually empty!");
+            self.psema.signal();
+            Some(res)
+        } else {
+            None
+        }
+    }
+
     pub unsafe fn irq_try_push(&self, val: T) -> Result<(), T> {
         if self.psema.irq_poll() {
             self.inner.push(val);
@@ -0,0 +1,361 @@
+//! Channels in the shape of `std::sync::mpsc`, on top of the [`mpmc`](crate::mpmc) queues.
+//!
+//! Unlike the bare queues, a channel knows when its senders or its receiver went away, so a
+//! blocked [`Receiver::recv`] returns an error once every sender is dropped.
+
+use alloc::sync::Arc;
+use core::fmt::{self, Debug};
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use core::time::Duration;
+
+use crate::mpmc::{BoundedQueue, UnboundedQueue};
+
+/// The receiver is gone, the value is handed back.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SendError<T>(pub T);
+
+/// Every sender is gone and the channel is empty.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RecvError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrySendError<T> {
+    Full(T),
+    Disconnected(T),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TryRecvError {
+    Empty,
+    Disconnected,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecvTimeoutError {
+    Timeout,
+    Disconnected,
+}
+
+// `None` is pushed by the last sender to wake up a blocked receiver
+enum Queue<T> {
+    Unbounded(UnboundedQueue<Option<T>>),
+    Bounded(BoundedQueue<Option<T>>),
+}
+
+struct Shared<T> {
+    queue: Queue<T>,
+    senders: AtomicUsize,
+    receiver: AtomicBool,
+}
+
+impl<T> Shared<T> {
+    fn new(queue: Queue<T>) -> Arc<Self> {
+        Arc::new(Self {
+            queue,
+            senders: AtomicUsize::new(1),
+            receiver: AtomicBool::new(true),
+        })
+    }
+
+    fn is_receiver_alive(&self) -> bool {
+        self.receiver.load(Ordering::SeqCst)
+    }
+
+    fn is_disconnected(&self) -> bool {
+        self.senders.load(Ordering::SeqCst) == 0
+    }
+
+    fn try_pop(&self) -> Option<Option<T>> {
+        match &self.queue {
+            Queue::Unbounded(queue) => queue.try_pop(),
+            Queue::Bounded(queue) => queue.try_pop(),
+        }
+    }
+
+    fn pop(&self) -> Option<T> {
+        match &self.queue {
+            Queue::Unbounded(queue) => queue.pop(),
+            Queue::Bounded(queue) => queue.pop(),
+        }
+    }
+
+    fn pop_timeout(&self, timeout: Duration) -> Option<Option<T>> {
+        match &self.queue {
+            Queue::Unbounded(queue) => queue.pop_timeout(timeout),
+            Queue::Bounded(queue) => queue.pop_timeout(timeout),
+        }
+    }
+
+    fn add_sender(&self) {
+        self.senders.fetch_add(1, Ordering::SeqCst);
+    }
+
+    fn drop_sender(&self) {
+        if self.senders.fetch_sub(1, Ordering::SeqCst) != 1 {
+            return;
+        }
+
+        match &self.queue {
+            Queue::Unbounded(queue) => queue.push(None),
+            // A full queue means the receiver is not blocked, it checks the senders once empty
+            Queue::Bounded(queue) => {
+                let _ = queue.try_push(None);
+            }
+        }
+    }
+}
+
+/// Create a channel with an unbounded queue, sending never blocks.
+pub fn channel<T>() -> (Sender<T>, Receiver<T>) {
+    let shared = Shared::new(Queue::Unbounded(UnboundedQueue::new()));
+    (
+        Sender {
+            shared: shared.clone(),
+        },
+        Receiver { shared },
+    )
+}
+
+/// Create a channel holding up to `bound` values, sending blocks while it is full.
+///
+/// There is no rendezvous channel, a `bound` of 0 is treated as 1.
+pub fn sync_channel<T>(bound: usize) -> (SyncSender<T>, Receiver<T>) {
+    let shared = Shared::new(Queue::Bounded(BoundedQueue::new(bound.max(1))));
+    (
+        SyncSender {
+            shared: shared.clone(),
+        },
+        Receiver { shared },
+    )
+}
+
+pub struct Sender<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T> Sender<T> {
+    pub fn send(&self, val: T) -> Result<(), SendError<T>> {
+        if !self.shared.is_receiver_alive() {
+            return Err(SendError(val));
+        }
+
+        match &self.shared.queue {
+            Queue::Unbounded(queue) => queue.push(Some(val)),
+            Queue::Bounded(_) => unreachable!(),
+        }
+        Ok(())
+    }
+}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Self {
+        self.shared.add_sender();
+        Self {
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        self.shared.drop_sender();
+    }
+}
+
+impl<T> Debug for Sender<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Sender").finish_non_exhaustive()
+    }
+}
+
+pub struct SyncSender<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T> SyncSender<T> {
+    fn queue(&self) -> &BoundedQueue<Option<T>> {
+        match &self.shared.queue {
+            Queue::Bounded(queue) => queue,
+            Queue::Unbounded(_) => unreachable!(),
+        }
+    }
+
+    /// Send `val`, blocking while the channel is full.
+    ///
+    /// Hands `val` back if the receiver is gone, also when it goes away while blocked.
+    pub fn send(&self, val: T) -> Result<(), SendError<T>> {
+        if !self.shared.is_receiver_alive() {
+            return Err(SendError(val));
+        }
+
+        // The receiver frees every slot when dropped, check it again once one is ours
+        self.queue()
+            .push_if(Some(val), || self.shared.is_receiver_alive())
+            .map_err(|val| SendError(val.unwrap()))
+    }
+
+    pub fn try_send(&self, val: T) -> Result<(), TrySendError<T>> {
+        if !self.shared.is_receiver_alive() {
+            return Err(TrySendError::Disconnected(val));
+        }
+
+        self.queue()
+            .try_push(Some(val))
+            .map_err(|val| TrySendError::Full(val.unwrap()))
+    }
+
+    /// Like [`SyncSender::try_send`], from an interrupt handler.
+    ///
+    /// The queue is allocated up front, so this never allocates.
+    ///
+    /// # Safety
+    ///
+    /// Must be called from an interrupt handler.
+    pub unsafe fn irq_try_send(&self, val: T) -> Result<(), TrySendError<T>> {
+        if !self.shared.is_receiver_alive() {
+            return Err(TrySendError::Disconnected(val));
+        }
+
+        self.queue()
+            .irq_try_push(Some(val))
+            .map_err(|val| TrySendError::Full(val.unwrap()))
+    }
+}
+
+impl<T> Clone for SyncSender<T> {
+    fn clone(&self) -> Self {
+        self.shared.add_sender();
+        Self {
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+impl<T> Drop for SyncSender<T> {
+    fn drop(&mut self) {
+        self.shared.drop_sender();
+    }
+}
+
+impl<T> Debug for SyncSender<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("SyncSender").finish_non_exhaustive()
+    }
+}
+
+pub struct Receiver<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T> Receiver<T> {
+    /// Block until a value arrives, fails once every sender is gone and the channel is empty.
+    pub fn recv(&self) -> Result<T, RecvError> {
+        match self.try_recv() {
+            Ok(val) => Ok(val),
+            Err(TryRecvError::Disconnected) => Err(RecvError),
+            Err(TryRecvError::Empty) => self.shared.pop().ok_or(RecvError),
+        }
+    }
+
+    pub fn try_recv(&self) -> Result<T, TryRecvError> {
+        match self.shared.try_pop() {
+            Some(Some(val)) => Ok(val),
+            Some(None) => Err(TryRecvError::Disconnected),
+            None if self.shared.is_disconnected() => Err(TryRecvError::Disconnected),
+            None => Err(TryRecvError::Empty),
+        }
+    }
+
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<T, RecvTimeoutError> {
+        match self.try_recv() {
+            Ok(val) => Ok(val),
+            Err(TryRecvError::Disconnected) => Err(RecvTimeoutError::Disconnected),
+            Err(TryRecvError::Empty) => match self.shared.pop_timeout(timeout) {
+                Some(Some(val)) => Ok(val),
+                Some(None) => Err(RecvTimeoutError::Disconnected),
+                None => Err(RecvTimeoutError::Timeout),
+            },
+        }
+    }
+
+    /// Iterate over received values, until every sender is gone.
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter { rx: self }
+    }
+
+    /// Iterate over the values already in the channel.
+    pub fn try_iter(&self) -> TryIter<'_, T> {
+        TryIter { rx: self }
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        self.shared.receiver.store(false, Ordering::SeqCst);
+        // Wake up the senders blocked on a full channel
+        while self.shared.try_pop().is_some() {}
+    }
+}
+
+impl<T> Debug for Receiver<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Receiver").finish_non_exhaustive()
+    }
+}
+
+#[derive(Debug)]
+pub struct Iter<'a, T> {
+    rx: &'a Receiver<T>,
+}
+
+impl<T> Iterator for Iter<'_, T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        self.rx.recv().ok()
+    }
+}
+
+#[derive(Debug)]
+pub struct TryIter<'a, T> {
+    rx: &'a Receiver<T>,
+}
+
+impl<T> Iterator for TryIter<'_, T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        self.rx.try_recv().ok()
+    }
+}
+
+#[derive(Debug)]
+pub struct IntoIter<T> {
+    rx: Receiver<T>,
+}
+
+impl<T> Iterator for IntoIter<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        self.rx.recv().ok()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a Receiver<T> {
+    type Item = T;
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Iter<'a, T> {
+        self.iter()
+    }
+}
+
+impl<T> IntoIterator for Receiver<T> {
+    type Item = T;
+    type IntoIter = IntoIter<T>;
+
+    fn into_iter(self) -> IntoIter<T> {
+        IntoIter { rx: self }
+    }
+}
@@ -4,6 +4,7 @@ use core::cell::UnsafeCell;
 use core::fmt::{self, Debug};
 use core::marker::PhantomData;
 use core::ops::{Deref, DerefMut};
+use core::time::Duration;
 
 pub struct Mutex<T: ?Sized> {
     sema: Sema,
@@ -68,6 +69,17 @@ impl<T: ?Sized> Mutex<T> {
         }
     }
 
+    pub fn lock_timeout(&self, timeout: Duration) -> Option<MutexGuard<'_, T>> {
+        if self.sema.wait_timeout(timeout) {
+            Some(MutexGuard {
+                lock: self,
+                _marker: PhantomData,
+            })
+        } else {
+            None
+        }
+    }
+
     pub unsafe fn irq_try_lock(&self) -> Option<MutexGuard<'_, T>> {
         if self.sema.irq_poll() {
             Some(MutexGuard {
@@ -98,6 +110,12 @@ impl<T: Debug + ?Sized> Debug for Mutex<T> {
     }
 }
 
+impl<'a, T: ?Sized> MutexGuard<'a, T> {
+    pub(crate) fn mutex(this: &Self) -> &'a Mutex<T> {
+        this.lock
+    }
+}
+
 impl<T: ?Sized> Deref for MutexGuard<'_, T> {
     type Target = T;
 
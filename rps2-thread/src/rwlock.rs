@@ -0,0 +1,443 @@
+use crate::sema::Sema;
+
+use core::cell::{RefCell, UnsafeCell};
+use core::fmt::{self, Debug};
+use core::marker::PhantomData;
+use core::mem;
+use core::ops::{Deref, DerefMut};
+
+/// A reader-writer lock, with upgradable reads.
+///
+/// The EE kernel always runs the highest priority ready thread, so a stream of readers could
+/// keep a writer waiting forever. To prevent that, new readers queue up behind waiting writers,
+/// and a released writer lets the readers queued so far in before the next writer.
+///
+/// Waiters block on kernel semaphores and are handed the lock by the thread releasing it.
+pub struct RwLock<T: ?Sized> {
+    state: critical_section::Mutex<RefCell<State>>,
+    read_sema: Sema,
+    upgradable_sema: Sema,
+    write_sema: Sema,
+    upgrade_sema: Sema,
+    data: UnsafeCell<T>,
+}
+
+#[derive(Default)]
+struct State {
+    /// Readers holding the lock, including the upgradable one.
+    readers: u32,
+    writer: bool,
+    upgradable: bool,
+    /// The upgradable reader waits for the other readers to leave.
+    upgrading: bool,
+    waiting_readers: u32,
+    waiting_upgradable: u32,
+    waiting_writers: u32,
+}
+
+/// Threads handed the lock, to wake up outside of the critical section.
+#[derive(Default)]
+struct Wakeup {
+    readers: u32,
+    upgradable: bool,
+    writer: bool,
+    upgrader: bool,
+}
+
+impl State {
+    fn can_read(&self) -> bool {
+        !self.writer && !self.upgrading && self.waiting_writers == 0
+    }
+
+    fn can_write(&self) -> bool {
+        !self.writer && self.readers == 0
+    }
+
+    /// Hand the lock to the waiters, `readers_first` lets queued readers in before a writer.
+    fn dispatch(&mut self, readers_first: bool) -> Wakeup {
+        let mut wakeup = Wakeup::default();
+
+        if self.upgrading {
+            if self.readers == 1 {
+                self.readers = 0;
+                self.upgradable = false;
+                self.upgrading = false;
+                self.writer = true;
+                wakeup.upgrader = true;
+            }
+            return wakeup;
+        }
+
+        let has_readers = self.waiting_readers > 0 || self.waiting_upgradable > 0;
+        if self.waiting_writers > 0 && self.can_write() && !(readers_first && has_readers) {
+            self.waiting_writers -= 1;
+            self.writer = true;
+            wakeup.writer = true;
+            return wakeup;
+        }
+
+        if !self.writer && (self.waiting_writers == 0 || readers_first) {
+            wakeup.readers = mem::take(&mut self.waiting_readers);
+            self.readers += wakeup.readers;
+
+            if !self.upgradable && self.waiting_upgradable > 0 {
+                self.waiting_upgradable -= 1;
+                self.readers += 1;
+                self.upgradable = true;
+                wakeup.upgradable = true;
+            }
+        }
+        wakeup
+    }
+}
+
+unsafe impl<T: ?Sized + Send> Send for RwLock<T> {}
+unsafe impl<T: ?Sized + Send + Sync> Sync for RwLock<T> {}
+
+impl<T> RwLock<T> {
+    pub fn new(val: T) -> Self {
+        let sema = |max_count| {
+            Sema::builder()
+                .init_count(0)
+                .max_count(max_count)
+                .build()
+                .expect("Failed to create semaphore")
+        };
+
+        Self {
+            state: critical_section::Mutex::new(RefCell::new(State::default())),
+            read_sema: sema(i32::MAX as u32),
+            upgradable_sema: sema(i32::MAX as u32),
+            write_sema: sema(i32::MAX as u32),
+            upgrade_sema: sema(1),
+            data: UnsafeCell::new(val),
+        }
+    }
+
+    pub fn into_inner(self) -> T {
+        self.data.into_inner()
+    }
+}
+
+impl<T: ?Sized> RwLock<T> {
+    pub fn get_mut(&mut self) -> &mut T {
+        self.data.get_mut()
+    }
+
+    /// Lock for shared access, blocking while a writer holds or waits for the lock.
+    pub fn read(&self) -> RwLockReadGuard<'_, T> {
+        let acquired = self.with_state(|state| {
+            if state.can_read() {
+                state.readers += 1;
+                true
+            } else {
+                state.waiting_readers += 1;
+                false
+            }
+        });
+
+        if !acquired {
+            self.read_sema.wait();
+        }
+        RwLockReadGuard {
+            lock: self,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn try_read(&self) -> Option<RwLockReadGuard<'_, T>> {
+        let acquired = self.with_state(|state| {
+            let acquired = state.can_read();
+            if acquired {
+                state.readers += 1;
+            }
+            acquired
+        });
+
+        acquired.then(|| RwLockReadGuard {
+            lock: self,
+            _marker: PhantomData,
+        })
+    }
+
+    /// Lock for shared access that can later be upgraded to exclusive access.
+    ///
+    /// Only one upgradable reader can hold the lock at a time, alongside plain readers.
+    pub fn upgradable_read(&self) -> RwLockUpgradableReadGuard<'_, T> {
+        let acquired = self.with_state(|state| {
+            if state.can_read() && !state.upgradable {
+                state.readers += 1;
+                state.upgradable = true;
+                true
+            } else {
+                state.waiting_upgradable += 1;
+                false
+            }
+        });
+
+        if !acquired {
+            self.upgradable_sema.wait();
+        }
+        RwLockUpgradableReadGuard {
+            lock: self,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn try_upgradable_read(&self) -> Option<RwLockUpgradableReadGuard<'_, T>> {
+        let acquired = self.with_state(|state| {
+            let acquired = state.can_read() && !state.upgradable;
+            if acquired {
+                state.readers += 1;
+                state.upgradable = true;
+            }
+            acquired
+        });
+
+        acquired.then(|| RwLockUpgradableReadGuard {
+            lock: self,
+            _marker: PhantomData,
+        })
+    }
+
+    /// Lock for exclusive access, blocking until every reader left.
+    pub fn write(&self) -> RwLockWriteGuard<'_, T> {
+        let acquired = self.with_state(|state| {
+            if state.can_write() {
+                state.writer = true;
+                true
+            } else {
+                state.waiting_writers += 1;
+                false
+            }
+        });
+
+        if !acquired {
+            self.write_sema.wait();
+        }
+        RwLockWriteGuard {
+            lock: self,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn try_write(&self) -> Option<RwLockWriteGuard<'_, T>> {
+        let acquired = self.with_state(|state| {
+            let acquired = state.can_write();
+            if acquired {
+                state.writer = true;
+            }
+            acquired
+        });
+
+        acquired.then(|| RwLockWriteGuard {
+            lock: self,
+            _marker: PhantomData,
+        })
+    }
+
+    fn with_state<R>(&self, f: impl FnOnce(&mut State) -> R) -> R {
+        critical_section::with(|cs| f(&mut self.state.borrow_ref_mut(cs)))
+    }
+
+    /// Update the state and wake up the threads handed the lock.
+    fn release(&self, f: impl FnOnce(&mut State) -> Wakeup) {
+        let wakeup = self.with_state(f);
+
+        if wakeup.upgrader {
+            self.upgrade_sema.signal();
+        }
+        if wakeup.writer {
+            self.write_sema.signal();
+        }
+        if wakeup.upgradable {
+            self.upgradable_sema.signal();
+        }
+        for _ in 0..wakeup.readers {
+            self.read_sema.signal();
+        }
+    }
+}
+
+impl<T: Default> Default for RwLock<T> {
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+impl<T> From<T> for RwLock<T> {
+    fn from(value: T) -> Self {
+        Self::new(value)
+    }
+}
+
+impl<T: Debug + ?Sized> Debug for RwLock<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("RwLock").finish_non_exhaustive()
+    }
+}
+
+pub struct RwLockReadGuard<'a, T: ?Sized + 'a> {
+    lock: &'a RwLock<T>,
+    // Make the type !Send + !Sync
+    _marker: PhantomData<*const ()>,
+}
+
+pub struct RwLockUpgradableReadGuard<'a, T: ?Sized + 'a> {
+    lock: &'a RwLock<T>,
+    // Make the type !Send + !Sync
+    _marker: PhantomData<*const ()>,
+}
+
+pub struct RwLockWriteGuard<'a, T: ?Sized + 'a> {
+    lock: &'a RwLock<T>,
+    // Make the type !Send + !Sync
+    _marker: PhantomData<*const ()>,
+}
+
+impl<'a, T: ?Sized> RwLockUpgradableReadGuard<'a, T> {
+    /// Turn into exclusive access, blocking until the other readers left.
+    pub fn upgrade(this: Self) -> RwLockWriteGuard<'a, T> {
+        let lock = this.lock;
+        mem::forget(this);
+
+        let acquired = lock.with_state(|state| {
+            if state.readers == 1 {
+                state.readers = 0;
+                state.upgradable = false;
+                state.writer = true;
+                true
+            } else {
+                // Keeps new readers out, the last reader leaving hands over the lock
+                state.upgrading = true;
+                false
+            }
+        });
+
+        if !acquired {
+            lock.upgrade_sema.wait();
+        }
+        RwLockWriteGuard {
+            lock,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Turn into exclusive access if no other reader holds the lock.
+    pub fn try_upgrade(this: Self) -> Result<RwLockWriteGuard<'a, T>, Self> {
+        let acquired = this.lock.with_state(|state| {
+            let acquired = state.readers == 1;
+            if acquired {
+                state.readers = 0;
+                state.upgradable = false;
+                state.writer = true;
+            }
+            acquired
+        });
+
+        if acquired {
+            let lock = this.lock;
+            mem::forget(this);
+            Ok(RwLockWriteGuard {
+                lock,
+                _marker: PhantomData,
+            })
+        } else {
+            Err(this)
+        }
+    }
+}
+
+impl<'a, T: ?Sized> RwLockWriteGuard<'a, T> {
+    /// Turn into shared access, letting waiting readers in.
+    pub fn downgrade(this: Self) -> RwLockReadGuard<'a, T> {
+        let lock = this.lock;
+        mem::forget(this);
+
+        lock.release(|state| {
+            state.writer = false;
+            state.readers = 1;
+            state.dispatch(true)
+        });
+        RwLockReadGuard {
+            lock,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T: ?Sized> Deref for RwLockReadGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        unsafe { self.lock.data.get().as_ref().unwrap() }
+    }
+}
+
+impl<T: ?Sized> Deref for RwLockUpgradableReadGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        unsafe { self.lock.data.get().as_ref().unwrap() }
+    }
+}
+
+impl<T: ?Sized> Deref for RwLockWriteGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        unsafe { self.lock.data.get().as_ref().unwrap() }
+    }
+}
+
+impl<T: ?Sized> DerefMut for RwLockWriteGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        unsafe { self.lock.data.get().as_mut().unwrap() }
+    }
+}
+
+impl<T: ?Sized> Drop for RwLockReadGuard<'_, T> {
+    fn drop(&mut self) {
+        self.lock.release(|state| {
+            state.readers -= 1;
+            state.dispatch(false)
+        });
+    }
+}
+
+impl<T: ?Sized> Drop for RwLockUpgradableReadGuard<'_, T> {
+    fn drop(&mut self) {
+        self.lock.release(|state| {
+            state.readers -= 1;
+            state.upgradable = false;
+            state.dispatch(false)
+        });
+    }
+}
+
+impl<T: ?Sized> Drop for RwLockWriteGuard<'_, T> {
+    fn drop(&mut self) {
+        self.lock.release(|state| {
+            state.writer = false;
+            state.dispatch(true)
+        });
+    }
+}
+
+impl<T: Debug + ?Sized> Debug for RwLockReadGuard<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        (**self).fmt(f)
+    }
+}
+
+impl<T: Debug + ?Sized> Debug for RwLockUpgradableReadGuard<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        (**self).fmt(f)
+    }
+}
+
+impl<T: Debug + ?Sized> Debug for RwLockWriteGuard<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        (**self).fmt(f)
+    }
+}
@@ -1,5 +1,7 @@
 use core::mem;
+use core::time::Duration;
 
+use crate::timer::Timeout;
 use crate::{ffi, Result};
 
 #[derive(Debug, Clone, Copy)]
@@ -83,6 +85,23 @@ impl Sema {
         }
     }
 
+    /// Wait for the semaphore, giving up after `timeout`. Returns `true` if it was acquired.
+    pub fn wait_timeout(&self, timeout: Duration) -> bool {
+        if self.poll() {
+            return true;
+        }
+
+        let timer = Timeout::start(timeout);
+        let res = unsafe { ffi::wait_sema(self.0) };
+        let expired = timer.cancel();
+
+        match res {
+            Ok(()) => true,
+            Err(_) if expired => false,
+            Err(_) => panic!("Semaphore got unexpectedly deleted!"),
+        }
+    }
+
     pub fn signal(&self) {
         unsafe {
             ffi::signal_sema(self.0).expect("Semaphore got unexpectedly deleted!");
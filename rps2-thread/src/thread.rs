@@ -7,10 +7,17 @@ use core::ffi::c_void;
 use core::fmt::{self, Debug};
 use core::marker::PhantomData;
 use core::ptr::addr_of_mut;
+use core::time::Duration;
 
 use alloc::boxed::Box;
 use alloc::sync::Arc;
 
+mod local;
+mod scoped;
+
+pub use local::{AccessError, LocalKey};
+pub use scoped::{scope, Scope, ScopedJoinHandle};
+
 pub const DEFAULT_STACK_SIZE: u32 = 64 * 1024;
 pub const MIN_STACK_SIZE: u32 = 512;
 pub const MAX_STACK_SIZE: u32 = 1024 * 1024;
@@ -42,6 +49,18 @@ pub fn sleep() {
     }
 }
 
+/// Put the current thread to sleep for at least `duration`.
+pub fn sleep_for(duration: Duration) {
+    let sema = Sema::builder()
+        .init_count(0)
+        .max_count(1)
+        .build()
+        .expect("Failed to create semaphore");
+
+    // Nobody else can signal it, so this only returns once the timeout expires
+    sema.wait_timeout(duration);
+}
+
 pub fn rotate_ready_queue(priority: u32) {
     let priority = priority.clamp(MIN_PRIORITY, MAX_PRIORITY);
     unsafe {
@@ -120,6 +139,9 @@ pub struct JoinHandle<T> {
     _marker: PhantomData<T>,
 }
 
+// SAFETY: The stack is only freed by the owner of the handle, once the thread is done
+unsafe impl<T: Send> Send for JoinHandle<T> {}
+
 impl<T> JoinHandle<T> {
     pub fn thread(&self) -> Thread {
         Thread(self.tid)
@@ -240,9 +262,17 @@ where
 
     let packet2 = Arc::clone(&packet);
     let (tid, stack) = raw_spawn2(builder, move || {
+        // A previous thread with the same id might have left its locals destroyed
+        local::reset();
+
         // Catch possible unwinds
         let ret = rps2_panic::catch_unwind(f);
 
+        // Drop the thread locals before signaling, as joining terminates the thread
+        if rps2_panic::catch_unwind(local::run_dtors).is_err() {
+            rps2_panic::abort();
+        }
+
         unsafe {
             // SAFETY: Since we haven't signaled the semaphore yet, we are guaranteed to be the
             // only ones accessing this packet.
@@ -291,6 +321,7 @@ fn raw_terminate<T>(handle: JoinHandle<T>) {
         let _ = ffi::terminate_thread(handle.tid);
         let _ = ffi::delete_thread(handle.tid);
     }
+    local::forget(handle.tid);
 
     // Destroy the stack
     unsafe {
@@ -0,0 +1,241 @@
+//! Thread local storage, see [`thread_local!`](crate::thread_local).
+//!
+//! The EE has no thread pointer register, so every thread gets a table of values indexed by
+//! key, found through its kernel thread id. Values are created on first access and dropped when
+//! the closure of a spawned thread returns. For the main thread, created by the startup code
+//! rather than [`spawn`](super::spawn), they are dropped through `.fini_array` once `main`
+//! returns.
+
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::any::Any;
+use core::fmt::{self, Debug};
+use core::ptr;
+use core::sync::atomic::{AtomicPtr, AtomicUsize, Ordering};
+
+use crate::ffi;
+
+/// The kernel can't have more threads alive at the same time.
+const MAX_THREADS: usize = 256;
+
+enum Slot {
+    Uninit,
+    /// Leaked box, so that taking the table mutably doesn't alias references to the value.
+    Alive(*mut dyn Any),
+    Destroyed,
+}
+
+struct Locals {
+    slots: Vec<Slot>,
+}
+
+/// Values of each thread, only ever accessed by the thread itself.
+static THREADS: [AtomicPtr<Locals>; MAX_THREADS] =
+    [const { AtomicPtr::new(ptr::null_mut()) }; MAX_THREADS];
+
+/// Index of the next key, assigned on first use.
+static NEXT_KEY: AtomicUsize = AtomicUsize::new(0);
+
+/// Its address marks a thread whose values were already dropped.
+static DESTROYED: u8 = 0;
+
+fn destroyed() -> *mut Locals {
+    ptr::addr_of!(DESTROYED) as *mut Locals
+}
+
+fn thread_slot(tid: i32) -> &'static AtomicPtr<Locals> {
+    THREADS.get(tid as usize).expect("Thread id out of range!")
+}
+
+/// Table of the current thread, `None` once its values were dropped.
+fn current_locals() -> Option<*mut Locals> {
+    let slot = thread_slot(unsafe { ffi::get_thread_id() });
+    let mut locals = slot.load(Ordering::Relaxed);
+    if locals == destroyed() {
+        return None;
+    }
+    if locals.is_null() {
+        locals = Box::into_raw(Box::new(Locals { slots: Vec::new() }));
+        slot.store(locals, Ordering::Relaxed);
+    }
+    Some(locals)
+}
+
+/// Clear the state left behind by a previous thread with the same id as the current one.
+pub(crate) fn reset() {
+    thread_slot(unsafe { ffi::get_thread_id() }).store(ptr::null_mut(), Ordering::Relaxed);
+}
+
+/// Drop the values of the current thread, including the ones created by the destructors.
+///
+/// Values accessed afterwards are reported as destroyed, instead of being created again.
+pub(crate) fn run_dtors() {
+    let slot = thread_slot(unsafe { ffi::get_thread_id() });
+    let locals = slot.load(Ordering::Relaxed);
+    if locals.is_null() || locals == destroyed() {
+        slot.store(destroyed(), Ordering::Relaxed);
+        return;
+    }
+
+    loop {
+        // Don't hold a reference to the table while a destructor runs, as it might access it
+        let val = unsafe {
+            (*locals).slots.iter_mut().find_map(|slot| match *slot {
+                Slot::Alive(val) => {
+                    *slot = Slot::Destroyed;
+                    Some(val)
+                }
+                _ => None,
+            })
+        };
+
+        match val {
+            Some(val) => drop(unsafe { Box::from_raw(val) }),
+            None => break,
+        }
+    }
+
+    slot.store(destroyed(), Ordering::Relaxed);
+    unsafe { drop(Box::from_raw(locals)) };
+}
+
+/// Detach the values of a thread that was terminated, so that its id can be reused.
+///
+/// The values are leaked, as they might not be safe to drop from another thread.
+pub(crate) fn forget(tid: i32) {
+    thread_slot(tid).store(ptr::null_mut(), Ordering::Relaxed);
+}
+
+#[used]
+#[link_section = ".fini_array"]
+static MAIN_DTORS: extern "C" fn() = main_dtors;
+
+extern "C" fn main_dtors() {
+    run_dtors();
+}
+
+/// A value was accessed after its destructor ran.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AccessError;
+
+/// A key to a thread local value, created by [`thread_local!`](crate::thread_local).
+pub struct LocalKey<T: 'static> {
+    init: fn() -> T,
+    /// Index in the tables plus one, zero until first used.
+    key: AtomicUsize,
+}
+
+impl<T: 'static> LocalKey<T> {
+    #[doc(hidden)]
+    pub const fn new(init: fn() -> T) -> Self {
+        Self {
+            init,
+            key: AtomicUsize::new(0),
+        }
+    }
+
+    fn key(&self) -> usize {
+        match self.key.load(Ordering::Relaxed) {
+            0 => {
+                let key = NEXT_KEY.fetch_add(1, Ordering::Relaxed) + 1;
+                // Another thread might have been faster
+                match self
+                    .key
+                    .compare_exchange(0, key, Ordering::Relaxed, Ordering::Relaxed)
+                {
+                    Ok(_) => key - 1,
+                    Err(other) => other - 1,
+                }
+            }
+            key => key - 1,
+        }
+    }
+
+    /// Call `f` with the value of the current thread, creating it if needed.
+    ///
+    /// Panics if the value was already dropped, from the destructor of another value.
+    pub fn with<F, R>(&'static self, f: F) -> R
+    where
+        F: FnOnce(&T) -> R,
+    {
+        self.try_with(f)
+            .expect("Thread local accessed after being destroyed!")
+    }
+
+    pub fn try_with<F, R>(&'static self, f: F) -> Result<R, AccessError>
+    where
+        F: FnOnce(&T) -> R,
+    {
+        let key = self.key();
+        let locals = current_locals().ok_or(AccessError)?;
+
+        let val = match unsafe { (*locals).slots.get(key) } {
+            Some(Slot::Alive(val)) => *val,
+            Some(Slot::Destroyed) => return Err(AccessError),
+            _ => {
+                // The initializer might access other keys, don't hold on to the table
+                let val: *mut dyn Any = Box::into_raw(Box::new((self.init)()));
+
+                let slots = unsafe { &mut (*locals).slots };
+                if slots.len() <= key {
+                    slots.resize_with(key + 1, || Slot::Uninit);
+                }
+                match slots[key] {
+                    Slot::Uninit => {
+                        slots[key] = Slot::Alive(val);
+                        val
+                    }
+                    // Keep the value set by a recursive initialization, if any
+                    Slot::Alive(other) => {
+                        drop(unsafe { Box::from_raw(val) });
+                        other
+                    }
+                    Slot::Destroyed => {
+                        drop(unsafe { Box::from_raw(val) });
+                        return Err(AccessError);
+                    }
+                }
+            }
+        };
+
+        // SAFETY: Values are boxed, so they don't move when the table grows, and are only
+        // dropped by this thread once it's done. The table only holds raw pointers to them
+        let val = unsafe { &*val };
+        Ok(f(val.downcast_ref().unwrap()))
+    }
+}
+
+impl<T: 'static> Debug for LocalKey<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("LocalKey").finish_non_exhaustive()
+    }
+}
+
+/// Declare thread local statics of type [`LocalKey`].
+///
+/// ```ignore
+/// thread_local! {
+///     static COUNTER: Cell<u32> = Cell::new(0);
+/// }
+///
+/// COUNTER.with(|counter| counter.set(counter.get() + 1));
+/// ```
+#[macro_export]
+macro_rules! thread_local {
+    () => {};
+
+    ($(#[$attr:meta])* $vis:vis static $name:ident: $t:ty = $init:expr; $($rest:tt)*) => {
+        $crate::thread_local!($(#[$attr])* $vis static $name: $t = $init);
+        $crate::thread_local!($($rest)*);
+    };
+
+    ($(#[$attr:meta])* $vis:vis static $name:ident: $t:ty = $init:expr) => {
+        $(#[$attr])*
+        $vis static $name: $crate::thread::LocalKey<$t> = {
+            fn __init() -> $t {
+                $init
+            }
+            $crate::thread::LocalKey::new(__init)
+        };
+    };
+}
@@ -0,0 +1,191 @@
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+use core::fmt::{self, Debug};
+use core::marker::PhantomData;
+use core::mem::ManuallyDrop;
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use critical_section::Mutex;
+
+use super::{Builder, JoinHandle, Result, Thread};
+use crate::ffi;
+use crate::sema::Sema;
+
+/// Spawn threads borrowing from the current stack, they are all joined before returning.
+///
+/// Threads not joined through their [`ScopedJoinHandle`] are joined once `f` returns, even if it
+/// panicked. A panic of `f` is then resumed, otherwise this panics if any of the automatically
+/// joined threads panicked.
+///
+/// ```ignore
+/// let mut data = [1, 2, 3];
+/// thread::scope(|s| {
+///     s.spawn(|| kprintln!("{data:?}")).unwrap();
+///     s.spawn(|| kprintln!("{}", data.len())).unwrap();
+/// });
+/// data[0] = 0;
+/// ```
+pub fn scope<'env, F, T>(f: F) -> T
+where
+    F: for<'scope> FnOnce(&'scope Scope<'scope, 'env>) -> T,
+{
+    let scope = Scope {
+        running: AtomicUsize::new(0),
+        done: Sema::builder()
+            .init_count(0)
+            .max_count(i32::MAX as u32)
+            .build()
+            .expect("Failed to create semaphore"),
+        detached: ManuallyDrop::new(Mutex::new(RefCell::new(Vec::new()))),
+        a_thread_panicked: AtomicBool::new(false),
+        _scope: PhantomData,
+        _env: PhantomData,
+    };
+
+    let res = rps2_panic::catch_unwind(|| f(&scope));
+    scope.join_all();
+
+    match res {
+        Err(payload) => rps2_panic::resume_unwind(payload),
+        Ok(_) if scope.a_thread_panicked.load(Ordering::Relaxed) => {
+            panic!("a scoped thread panicked")
+        }
+        Ok(res) => res,
+    }
+}
+
+/// A type erased [`JoinHandle`] dropped without being joined.
+trait Detached: Send {
+    /// Returns `true` if the thread panicked.
+    fn join(self: Box<Self>) -> bool;
+}
+
+impl<T: Send> Detached for JoinHandle<T> {
+    fn join(self: Box<Self>) -> bool {
+        (*self).join().is_err()
+    }
+}
+
+type DetachedList<'scope> = Mutex<RefCell<Vec<Box<dyn Detached + 'scope>>>>;
+
+/// Spawns threads in a [`scope`].
+pub struct Scope<'scope, 'env: 'scope> {
+    /// Threads still running their closure.
+    running: AtomicUsize,
+    /// Signaled when `running` drops to zero.
+    done: Sema,
+    /// Emptied by `join_all`, without drop glue so the scope can be borrowed for `'scope`.
+    detached: ManuallyDrop<DetachedList<'scope>>,
+    a_thread_panicked: AtomicBool,
+    // Invariance over both lifetimes, like in std
+    _scope: PhantomData<&'scope mut &'scope ()>,
+    _env: PhantomData<&'env mut &'env ()>,
+}
+
+impl<'scope> Scope<'scope, '_> {
+    pub fn spawn<F, T>(&'scope self, f: F) -> ffi::Result<ScopedJoinHandle<'scope, T>>
+    where
+        F: FnOnce() -> T + Send + 'scope,
+        T: Send + 'scope,
+    {
+        Builder::default().spawn_scoped(self, f)
+    }
+
+    fn join_all(&self) {
+        // Handles might have been forgotten, so wait for the closures themselves
+        while self.running.load(Ordering::Acquire) != 0 {
+            self.done.wait();
+        }
+
+        // Joining can drop more handles, e.g. returned by a thread
+        loop {
+            let detached = critical_section::with(|cs| self.detached.take(cs));
+            if detached.is_empty() {
+                break;
+            }
+
+            for handle in detached {
+                if handle.join() {
+                    self.a_thread_panicked.store(true, Ordering::Relaxed);
+                }
+            }
+        }
+    }
+}
+
+impl Debug for Scope<'_, '_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Scope").finish_non_exhaustive()
+    }
+}
+
+impl Builder {
+    /// Like [`Builder::spawn`], for a thread of `scope`.
+    pub fn spawn_scoped<'scope, 'env, F, T>(
+        self,
+        scope: &'scope Scope<'scope, 'env>,
+        f: F,
+    ) -> ffi::Result<ScopedJoinHandle<'scope, T>>
+    where
+        F: FnOnce() -> T + Send + 'scope,
+        T: Send + 'scope,
+    {
+        scope.running.fetch_add(1, Ordering::Relaxed);
+        let finished = scopeguard::guard((), |_| {
+            if scope.running.fetch_sub(1, Ordering::Release) == 1 {
+                scope.done.signal();
+            }
+        });
+
+        let handle = unsafe {
+            // SAFETY: The scope waits for the closure to return before anything it borrows goes
+            // away
+            self.spawn_unchecked(move || {
+                let _finished = finished;
+                f()
+            })?
+        };
+
+        Ok(ScopedJoinHandle {
+            handle: ManuallyDrop::new(handle),
+            detached: &scope.detached,
+        })
+    }
+}
+
+/// Handle to a thread spawned in a [`scope`], handed to the scope to join if dropped.
+pub struct ScopedJoinHandle<'scope, T: Send + 'scope> {
+    handle: ManuallyDrop<JoinHandle<T>>,
+    detached: &'scope DetachedList<'scope>,
+}
+
+impl<T: Send> ScopedJoinHandle<'_, T> {
+    pub fn thread(&self) -> Thread {
+        self.handle.thread()
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.handle.is_finished()
+    }
+
+    pub fn join(self) -> Result<T> {
+        let mut this = ManuallyDrop::new(self);
+        let handle = unsafe { ManuallyDrop::take(&mut this.handle) };
+        handle.join()
+    }
+}
+
+impl<T: Send> Drop for ScopedJoinHandle<'_, T> {
+    fn drop(&mut self) {
+        let handle = unsafe { ManuallyDrop::take(&mut self.handle) };
+        critical_section::with(|cs| self.detached.borrow_ref_mut(cs).push(Box::new(handle)));
+    }
+}
+
+impl<T: Send> Debug for ScopedJoinHandle<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut s = f.debug_struct("ScopedJoinHandle");
+        s.field("tid", &self.thread().id());
+        s.finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,175 @@
+//! Timer wheel multiplexing timeouts over a single kernel alarm.
+//!
+//! While any timer is pending, a kernel alarm ticks the wheel every [`TICK_HSYNC`] horizontal
+//! syncs. Expired timers release the waiting thread with `iReleaseWaitThread`, which makes its
+//! blocking syscall return with an error.
+//!
+//! The horizontal sync rate depends on the video mode, so whoever changes it must report the
+//! new rate through [`set_hsync_hz`].
+
+use alloc::vec::Vec;
+use core::cell::RefCell;
+use core::ffi::c_void;
+use core::ptr;
+use core::sync::atomic::{AtomicU32, Ordering};
+use core::time::Duration;
+use critical_section::Mutex;
+
+use rps2_kernel::os;
+
+use crate::ffi;
+
+/// Horizontal sync frequency of NTSC, assumed until the video mode is set.
+///
+/// PAL consoles boot with a slightly lower rate (15625Hz), so timeouts never expire early.
+pub const DEFAULT_HSYNC_HZ: u32 = 15734;
+
+static HSYNC_HZ: AtomicU32 = AtomicU32::new(DEFAULT_HSYNC_HZ);
+
+/// Set the horizontal sync frequency of the current video mode, the unit of kernel alarms.
+///
+/// Timers already pending keep their length in ticks.
+pub fn set_hsync_hz(hz: u32) {
+    assert!(hz > 0, "invalid horizontal sync frequency");
+    HSYNC_HZ.store(hz, Ordering::Relaxed);
+}
+
+pub fn hsync_hz() -> u32 {
+    HSYNC_HZ.load(Ordering::Relaxed)
+}
+
+/// Length of a wheel tick, in horizontal syncs (about 1ms at NTSC rates).
+pub const TICK_HSYNC: u16 = 16;
+
+const SLOTS: usize = 64;
+
+struct Entry {
+    id: u64,
+    rounds: u32,
+    tid: i32,
+    expired: bool,
+}
+
+struct Wheel {
+    slots: [Vec<Entry>; SLOTS],
+    current: usize,
+    pending: usize,
+    next_id: u64,
+    armed: bool,
+}
+
+const EMPTY_SLOT: Vec<Entry> = Vec::new();
+
+static WHEEL: Mutex<RefCell<Wheel>> = Mutex::new(RefCell::new(Wheel {
+    slots: [EMPTY_SLOT; SLOTS],
+    current: 0,
+    pending: 0,
+    next_id: 0,
+    armed: false,
+}));
+
+extern "C" fn alarm_handler(_alarm_id: i32, _time: u16, _common: *mut c_void) {
+    critical_section::with(|cs| {
+        let mut wheel = WHEEL.borrow_ref_mut(cs);
+        let wheel = &mut *wheel;
+
+        wheel.current = (wheel.current + 1) % SLOTS;
+        for entry in wheel.slots[wheel.current].iter_mut() {
+            if entry.rounds == 0 {
+                entry.expired = true;
+            } else {
+                entry.rounds -= 1;
+            }
+        }
+
+        // The thread might not be waiting yet, in that case the entry stays in the wheel and is
+        // retried on the next tick until the timer gets cancelled. Nothing here can allocate,
+        // as the allocator lock is not usable from an interrupt.
+        for slot in wheel.slots.iter_mut() {
+            let mut i = 0;
+            while i < slot.len() {
+                if slot[i].expired && unsafe { ffi::irq_release_wait_thread(slot[i].tid) }.is_ok() {
+                    slot.swap_remove(i);
+                    wheel.pending -= 1;
+                } else {
+                    i += 1;
+                }
+            }
+        }
+
+        wheel.armed = wheel.pending > 0
+            && unsafe { ffi::irq_set_alarm(TICK_HSYNC, alarm_handler, ptr::null_mut()) }.is_ok();
+    });
+
+    unsafe {
+        os::exit_handler();
+    }
+}
+
+/// Number of ticks covering at least `duration`.
+fn ticks(duration: Duration) -> u32 {
+    let hsync = duration.as_micros() * hsync_hz() as u128 / 1_000_000;
+    // One more tick, as the wheel might be just about to advance
+    let ticks = hsync.div_ceil(TICK_HSYNC as u128) + 1;
+    ticks.min(u32::MAX as u128) as u32
+}
+
+/// A pending timeout for the current thread.
+pub(crate) struct Timeout {
+    id: u64,
+    slot: usize,
+}
+
+impl Timeout {
+    /// Release the current thread from its next wait once `duration` has passed.
+    pub(crate) fn start(duration: Duration) -> Self {
+        let tid = unsafe { ffi::get_thread_id() };
+        let ticks = ticks(duration);
+
+        critical_section::with(|cs| {
+            let mut wheel = WHEEL.borrow_ref_mut(cs);
+
+            let id = wheel.next_id;
+            wheel.next_id += 1;
+
+            let slot = (wheel.current + ticks as usize) % SLOTS;
+            let rounds = (ticks - 1) / SLOTS as u32;
+            wheel.slots[slot].push(Entry {
+                id,
+                rounds,
+                tid,
+                expired: false,
+            });
+            wheel.pending += 1;
+
+            if !wheel.armed {
+                unsafe {
+                    ffi::set_alarm(TICK_HSYNC, alarm_handler, ptr::null_mut())
+                        .expect("Failed to set alarm");
+                }
+                wheel.armed = true;
+            }
+
+            Self { id, slot }
+        })
+    }
+
+    /// Stop the timeout, returns `true` if it already expired.
+    pub(crate) fn cancel(self) -> bool {
+        critical_section::with(|cs| {
+            let mut wheel = WHEEL.borrow_ref_mut(cs);
+            let wheel = &mut *wheel;
+
+            let slot = &mut wheel.slots[self.slot];
+            match slot.iter().position(|entry| entry.id == self.id) {
+                Some(pos) => {
+                    let entry = slot.swap_remove(pos);
+                    wheel.pending -= 1;
+                    entry.expired
+                }
+                // Already removed by the handler
+                None => true,
+            }
+        })
+    }
+}
@@ -0,0 +1,159 @@
+use alloc::vec::Vec;
+use core::cell::RefCell;
+use core::mem;
+use critical_section::Mutex;
+
+use rps2_kernel::interrupt::{HandlerContext, IntcCause, IntcHandler};
+use rps2_pac::gs;
+
+use crate::sema::Sema;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    Even,
+    Odd,
+}
+
+struct State {
+    handler: Option<IntcHandler>,
+    subscribers: Vec<i32>,
+    frame: u64,
+    field: Field,
+}
+
+static STATE: Mutex<RefCell<State>> = Mutex::new(RefCell::new(State {
+    handler: None,
+    subscribers: Vec::new(),
+    frame: 0,
+    field: Field::Even,
+}));
+
+fn vblank_handler(ctx: &HandlerContext) {
+    critical_section::with(|cs| {
+        let mut state = STATE.borrow_ref_mut(cs);
+        state.frame += 1;
+        state.field = if gs::CSR.read().field() {
+            Field::Odd
+        } else {
+            Field::Even
+        };
+
+        for &sid in state.subscribers.iter() {
+            // Fails if the subscriber already has a pending vblank, which is fine
+            ctx.signal_sema(sid);
+        }
+    });
+}
+
+/// A subscription to vertical blank start events.
+///
+/// The interrupt handler is installed with the first subscriber and removed when the last one
+/// is dropped.
+#[derive(Debug)]
+pub struct VBlank {
+    sema: Sema,
+}
+
+impl VBlank {
+    pub fn subscribe() -> Self {
+        let sema = Sema::builder()
+            .init_count(0)
+            .max_count(1)
+            .build()
+            .expect("Failed to build sema");
+
+        if add_subscriber(sema.id()) {
+            // Registering allocates and calls into the kernel, keep it out of the critical
+            // section and discard the handler if another subscriber was faster
+            let handler = IntcHandler::register(IntcCause::VblankStart, vblank_handler)
+                .expect("Failed to register vblank handler");
+            let extra = critical_section::with(|cs| {
+                let mut state = STATE.borrow_ref_mut(cs);
+                match state.handler {
+                    None => {
+                        state.handler = Some(handler);
+                        None
+                    }
+                    Some(_) => Some(handler),
+                }
+            });
+            drop(extra);
+        }
+
+        Self { sema }
+    }
+
+    /// Block until the start of the next vertical blank, returning the frame counter.
+    pub fn wait(&self) -> u64 {
+        // Discard a vblank that happened before this call
+        self.sema.poll();
+        self.sema.wait();
+        frame_count()
+    }
+}
+
+/// Add `sid` to the subscribers, returns `true` if the handler is not installed.
+fn add_subscriber(sid: i32) -> bool {
+    // The list only grows into memory allocated outside of the critical section
+    let mut spare: Option<Vec<i32>> = None;
+    loop {
+        let res = critical_section::with(|cs| {
+            let mut state = STATE.borrow_ref_mut(cs);
+            let len = state.subscribers.len();
+            let mut old = None;
+            if len == state.subscribers.capacity() {
+                match spare.take() {
+                    Some(mut grown) if grown.capacity() > len => {
+                        grown.extend_from_slice(&state.subscribers);
+                        old = Some(mem::replace(&mut state.subscribers, grown));
+                    }
+                    _ => return Err(len),
+                }
+            }
+            state.subscribers.push(sid);
+            Ok((state.handler.is_none(), old))
+        });
+
+        match res {
+            Ok((missing, old)) => {
+                drop(old);
+                return missing;
+            }
+            Err(len) => spare = Some(Vec::with_capacity((len + 1).next_power_of_two().max(4))),
+        }
+    }
+}
+
+impl Drop for VBlank {
+    fn drop(&mut self) {
+        let handler = critical_section::with(|cs| {
+            let mut state = STATE.borrow_ref_mut(cs);
+
+            let sid = self.sema.id();
+            state.subscribers.retain(|&other| other != sid);
+
+            if state.subscribers.is_empty() {
+                state.handler.take()
+            } else {
+                None
+            }
+        });
+        // Removing the handler calls into the kernel
+        drop(handler);
+    }
+}
+
+/// Block until the start of the next vertical blank.
+pub fn wait_vblank() -> u64 {
+    VBlank::subscribe().wait()
+}
+
+/// Number of vertical blanks seen while the handler was installed.
+pub fn frame_count() -> u64 {
+    critical_section::with(|cs| STATE.borrow_ref(cs).frame)
+}
+
+/// Field being displayed, meaningful only in interlaced modes.
+pub fn field() -> Field {
+    critical_section::with(|cs| STATE.borrow_ref(cs).field)
+}
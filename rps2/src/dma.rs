@@ -0,0 +1,368 @@
+//! Blocking DMA transfers with cache coherency handled automatically.
+
+use alloc_crate::alloc::{alloc_zeroed, dealloc, handle_alloc_error, Layout};
+
+use core::fmt::{self, Debug};
+use core::marker::PhantomData;
+use core::mem;
+use core::ops::{Deref, DerefMut};
+use core::ptr::{self, NonNull};
+use core::sync::atomic::{AtomicU32, Ordering};
+
+use rps2_kernel::arch;
+use rps2_kernel::interrupt::{DmacChannel, DmacHandler};
+use rps2_pac::dmac;
+use rps2_thread::sema::Sema;
+
+/// Alignment of DMA buffers, matching the data cache line size.
+pub const BUFFER_ALIGN: usize = 64;
+
+/// Maximum size of a single normal mode transfer, in bytes.
+pub const MAX_TRANSFER_SIZE: usize = 0xffff * 16;
+
+/// An owned, cache line aligned buffer suitable for DMA transfers.
+///
+/// The allocation is padded to a multiple of the cache line size, so cache maintenance on the
+/// buffer never touches unrelated data.
+pub struct DmaBuffer<T: ?Sized> {
+    ptr: NonNull<T>,
+    _marker: PhantomData<T>,
+}
+
+unsafe impl<T: ?Sized + Send> Send for DmaBuffer<T> {}
+unsafe impl<T: ?Sized + Sync> Sync for DmaBuffer<T> {}
+
+impl<T: ?Sized> DmaBuffer<T> {
+    fn layout(size: usize) -> Layout {
+        let size = size.max(1).next_multiple_of(BUFFER_ALIGN);
+        Layout::from_size_align(size, BUFFER_ALIGN).expect("Failed to obtain buffer layout")
+    }
+
+    fn alloc(size: usize) -> NonNull<u8> {
+        let layout = Self::layout(size);
+        // Zero the whole allocation, padding included, as it may get transferred
+        let ptr = unsafe { alloc_zeroed(layout) };
+        NonNull::new(ptr).unwrap_or_else(|| handle_alloc_error(layout))
+    }
+
+    pub fn as_ptr(&self) -> *const T {
+        self.ptr.as_ptr()
+    }
+
+    pub fn as_mut_ptr(&mut self) -> *mut T {
+        self.ptr.as_ptr()
+    }
+
+    /// Size of the buffer contents, in bytes.
+    pub fn size(&self) -> usize {
+        mem::size_of_val(&**self)
+    }
+
+    /// Size of the buffer rounded up to whole quadwords.
+    pub fn qwc(&self) -> usize {
+        self.size().div_ceil(16)
+    }
+}
+
+impl<T> DmaBuffer<T> {
+    pub fn new(val: T) -> Self {
+        let ptr = Self::alloc(mem::size_of::<T>()).cast::<T>();
+        unsafe {
+            ptr.as_ptr().write(val);
+        }
+
+        Self {
+            ptr,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T: Clone> DmaBuffer<[T]> {
+    pub fn from_slice(src: &[T]) -> Self {
+        let data = Self::alloc(mem::size_of_val(src)).cast::<T>();
+        for (i, val) in src.iter().enumerate() {
+            unsafe {
+                data.as_ptr().add(i).write(val.clone());
+            }
+        }
+
+        Self {
+            ptr: NonNull::slice_from_raw_parts(data, src.len()),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T: ?Sized> Deref for DmaBuffer<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { self.ptr.as_ref() }
+    }
+}
+
+impl<T: ?Sized> DerefMut for DmaBuffer<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { self.ptr.as_mut() }
+    }
+}
+
+impl<T: ?Sized> Drop for DmaBuffer<T> {
+    fn drop(&mut self) {
+        let layout = Self::layout(self.size());
+        unsafe {
+            ptr::drop_in_place(self.ptr.as_ptr());
+            dealloc(self.ptr.as_ptr() as *mut u8, layout);
+        }
+    }
+}
+
+impl<T: ?Sized + Debug> Debug for DmaBuffer<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("DmaBuffer").field(&&**self).finish()
+    }
+}
+
+/// DMA channels available for user transfers.
+///
+/// The SIF channels are managed by the kernel and are not exposed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChannelId {
+    Vif0,
+    Vif1,
+    Gif,
+    FromIpu,
+    ToIpu,
+    FromSpr,
+    ToSpr,
+}
+
+impl ChannelId {
+    fn channel(self) -> DmacChannel {
+        match self {
+            Self::Vif0 => DmacChannel::Vif0,
+            Self::Vif1 => DmacChannel::Vif1,
+            Self::Gif => DmacChannel::Gif,
+            Self::FromIpu => DmacChannel::FromIpu,
+            Self::ToIpu => DmacChannel::ToIpu,
+            Self::FromSpr => DmacChannel::FromSpr,
+            Self::ToSpr => DmacChannel::ToSpr,
+        }
+    }
+
+    fn regs(self) -> dmac::Channel {
+        match self {
+            Self::Vif0 => dmac::VIF0,
+            Self::Vif1 => dmac::VIF1,
+            Self::Gif => dmac::GIF,
+            Self::FromIpu => dmac::FROM_IPU,
+            Self::ToIpu => dmac::TO_IPU,
+            Self::FromSpr => dmac::FROM_SPR,
+            Self::ToSpr => dmac::TO_SPR,
+        }
+    }
+}
+
+// Bitmask of the channels currently owned by a `Channel`
+static TAKEN: AtomicU32 = AtomicU32::new(0);
+
+// Ownership of a channel, released after the handler and semaphore are gone
+struct Taken(u32);
+
+impl Drop for Taken {
+    fn drop(&mut self) {
+        TAKEN.fetch_and(!self.0, Ordering::AcqRel);
+    }
+}
+
+/// Exclusive handle to a DMA channel.
+///
+/// Transfers block the calling thread until the DMAC signals completion through its interrupt.
+pub struct Channel {
+    id: ChannelId,
+    regs: dmac::Channel,
+    _handler: DmacHandler,
+    sema: Sema,
+    _taken: Taken,
+}
+
+impl Channel {
+    /// Take ownership of a channel, returns `None` if it is already taken.
+    pub fn take(id: ChannelId) -> Option<Self> {
+        let regs = id.regs();
+        let bit = 1 << regs.id();
+        if TAKEN.fetch_or(bit, Ordering::AcqRel) & bit != 0 {
+            return None;
+        }
+        let taken = Taken(bit);
+
+        let sema = Sema::builder()
+            .init_count(0)
+            .max_count(i32::MAX as u32)
+            .build()
+            .expect("Failed to create semaphore");
+
+        let sid = sema.id();
+        let handler = DmacHandler::register(id.channel(), move |ctx| {
+            ctx.signal_sema(sid);
+        })
+        .expect("Failed to register DMAC handler");
+
+        Some(Self {
+            id,
+            regs,
+            _handler: handler,
+            sema,
+            _taken: taken,
+        })
+    }
+
+    pub fn id(&self) -> ChannelId {
+        self.id
+    }
+
+    /// Transfer a buffer from memory to the channel's peripheral.
+    ///
+    /// Panics if the buffer is larger than [`MAX_TRANSFER_SIZE`].
+    pub fn send<T: ?Sized>(&mut self, buf: DmaBuffer<T>) -> Transfer<'_, T> {
+        let chcr = dmac::Chcr::new()
+            .with_dir(dmac::Direction::FROM_MEMORY)
+            .with_mode(dmac::Mode::NORMAL);
+
+        unsafe { self.start(buf, chcr, false, false) }
+    }
+
+    /// Transfer a source chain starting at the beginning of `buf`.
+    ///
+    /// Only the buffer itself is written back from the data cache, data referenced by `ref`
+    /// style tags outside of it must be flushed by the caller.
+    pub fn send_chain<T: ?Sized>(&mut self, buf: DmaBuffer<T>) -> Transfer<'_, T> {
+        let chcr = dmac::Chcr::new()
+            .with_dir(dmac::Direction::FROM_MEMORY)
+            .with_mode(dmac::Mode::CHAIN);
+
+        unsafe { self.start(buf, chcr, true, false) }
+    }
+
+    /// Transfer data from the channel's peripheral into a buffer.
+    ///
+    /// Panics if the buffer is larger than [`MAX_TRANSFER_SIZE`].
+    pub fn recv<T: ?Sized>(&mut self, buf: DmaBuffer<T>) -> Transfer<'_, T> {
+        let chcr = dmac::Chcr::new()
+            .with_dir(dmac::Direction::TO_MEMORY)
+            .with_mode(dmac::Mode::NORMAL);
+
+        unsafe { self.start(buf, chcr, false, true) }
+    }
+
+    unsafe fn start<T: ?Sized>(
+        &self,
+        buf: DmaBuffer<T>,
+        chcr: dmac::Chcr,
+        chain: bool,
+        invalidate: bool,
+    ) -> Transfer<'_, T> {
+        let ptr = buf.as_ptr() as *const u8;
+        let size = buf.size();
+        assert!(
+            chain || size <= MAX_TRANSFER_SIZE,
+            "buffer too large for a single transfer"
+        );
+
+        // Wait for any previous transfer (e.g. a leaked one) to end
+        while self.regs.is_busy() {}
+
+        // Drain stale completions
+        while self.sema.poll() {}
+
+        // Make the data visible to the DMAC, and make sure no dirty line gets written back over
+        // received data later on
+        arch::cache_dhwbin(ptr as *const (), size);
+
+        let addr = dmac::Madr::from_ptr(ptr);
+        if chain {
+            self.regs.tadr().write(addr);
+            self.regs.qwc().write(dmac::Qwc::new());
+        } else {
+            self.regs.madr().write(addr);
+            self.regs
+                .qwc()
+                .write(dmac::Qwc::new().with_qwc(size.div_ceil(16) as u16));
+        }
+
+        arch::sync();
+        self.regs.chcr().write(chcr.with_str(true));
+
+        Transfer {
+            channel: self,
+            buf: Some(buf),
+            invalidate,
+            done: false,
+        }
+    }
+}
+
+impl Debug for Channel {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Channel")
+            .field("id", &self.id)
+            .finish_non_exhaustive()
+    }
+}
+
+/// An in-flight transfer, borrowing the channel and owning the buffer until it completes.
+///
+/// Dropping the transfer blocks until it completes. Leaking it with [`mem::forget`] leaks the
+/// buffer along with it, so the DMAC never accesses freed memory.
+#[must_use = "dropping a transfer waits for its completion"]
+pub struct Transfer<'a, T: ?Sized> {
+    channel: &'a Channel,
+    // Only taken back once the transfer is done
+    buf: Option<DmaBuffer<T>>,
+    invalidate: bool,
+    done: bool,
+}
+
+impl<T: ?Sized> Transfer<'_, T> {
+    pub fn is_finished(&self) -> bool {
+        self.done || !self.channel.regs.is_busy()
+    }
+
+    /// Block until the transfer completes, giving back the buffer.
+    pub fn wait(mut self) -> DmaBuffer<T> {
+        self.wait_inner();
+        self.buf.take().unwrap()
+    }
+
+    fn wait_inner(&mut self) {
+        if self.done {
+            return;
+        }
+
+        self.channel.sema.wait();
+        self.done = true;
+
+        if let Some(buf) = self.buf.as_ref().filter(|_| self.invalidate) {
+            unsafe {
+                // Drop any line fetched while the transfer was running
+                arch::cache_dhwbin(buf.as_ptr() as *const (), buf.size());
+            }
+        }
+    }
+}
+
+impl<T: ?Sized> Drop for Transfer<'_, T> {
+    fn drop(&mut self) {
+        self.wait_inner();
+    }
+}
+
+impl<T: ?Sized> Debug for Transfer<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Transfer")
+            .field("channel", &self.channel.id)
+            .field("done", &self.done)
+            .finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,403 @@
+//! Filesystem access through the IOP, shaped after `std::fs`.
+//!
+//! Paths start with the name of the IOP device handling them, e.g. `host:`, `mc0:`, `cdrom0:`,
+//! `mass:` or `pfs0:`, and are otherwise passed as-is to the driver.
+
+use alloc_crate::format;
+use alloc_crate::string::String;
+use alloc_crate::vec::Vec;
+
+use rps2_sif::fileio::{self, flags, whence, Stat};
+
+use crate::io::{self, ErrorKind, Read, Seek, SeekFrom, Write};
+
+pub use rps2_sif::fileio::Time;
+
+/// An open file on an IOP device, closed when dropped.
+#[derive(Debug)]
+pub struct File {
+    fd: i32,
+}
+
+impl File {
+    /// Open a file in read-only mode.
+    pub fn open<P: AsRef<str>>(path: P) -> io::Result<File> {
+        OpenOptions::new().read(true).open(path)
+    }
+
+    /// Open a file in write-only mode, creating it or truncating it.
+    pub fn create<P: AsRef<str>>(path: P) -> io::Result<File> {
+        OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)
+    }
+
+    /// Open a file in read-write mode, failing if it already exists.
+    pub fn create_new<P: AsRef<str>>(path: P) -> io::Result<File> {
+        OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .open(path)
+    }
+
+    pub fn options() -> OpenOptions {
+        OpenOptions::new()
+    }
+
+    /// Descriptor of the file on the IOP.
+    pub fn fd(&self) -> i32 {
+        self.fd
+    }
+}
+
+impl Read for File {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        Ok(fileio::read(self.fd, buf)?)
+    }
+}
+
+impl Write for File {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        Ok(fileio::write(self.fd, buf)?)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        // Writes are not buffered on the EE side
+        Ok(())
+    }
+}
+
+impl Seek for File {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        const INVALID: io::Error = io::Error::new(ErrorKind::InvalidInput, "seek offset too big");
+
+        let (offset, whence) = match pos {
+            SeekFrom::Start(offset) => (offset.try_into().map_err(|_| INVALID)?, whence::SET),
+            SeekFrom::End(offset) => (offset.try_into().map_err(|_| INVALID)?, whence::END),
+            SeekFrom::Current(offset) => (offset.try_into().map_err(|_| INVALID)?, whence::CUR),
+        };
+        Ok(fileio::lseek(self.fd, offset, whence)? as u64)
+    }
+}
+
+impl Drop for File {
+    fn drop(&mut self) {
+        // Errors on close can not be reported
+        let _ = fileio::close(self.fd);
+    }
+}
+
+/// Options used to open a file, mirroring `std::fs::OpenOptions`.
+#[derive(Debug, Clone, Default)]
+pub struct OpenOptions {
+    read: bool,
+    write: bool,
+    append: bool,
+    truncate: bool,
+    create: bool,
+    create_new: bool,
+}
+
+impl OpenOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn read(&mut self, read: bool) -> &mut Self {
+        self.read = read;
+        self
+    }
+
+    pub fn write(&mut self, write: bool) -> &mut Self {
+        self.write = write;
+        self
+    }
+
+    pub fn append(&mut self, append: bool) -> &mut Self {
+        self.append = append;
+        self
+    }
+
+    pub fn truncate(&mut self, truncate: bool) -> &mut Self {
+        self.truncate = truncate;
+        self
+    }
+
+    pub fn create(&mut self, create: bool) -> &mut Self {
+        self.create = create;
+        self
+    }
+
+    pub fn create_new(&mut self, create_new: bool) -> &mut Self {
+        self.create_new = create_new;
+        self
+    }
+
+    /// Flags passed to the IOP, validated like `std` does.
+    fn flags(&self) -> io::Result<u32> {
+        const INVALID: io::Error = io::Error::new(ErrorKind::InvalidInput, "invalid open options");
+
+        let writable = self.write || self.append;
+        let access = match (self.read, writable) {
+            (true, false) => flags::RDONLY,
+            (false, true) => flags::WRONLY,
+            (true, true) => flags::RDWR,
+            (false, false) => return Err(INVALID),
+        };
+
+        if !writable && (self.truncate || self.create || self.create_new) {
+            return Err(INVALID);
+        }
+        if self.append && self.truncate && !self.create_new {
+            return Err(INVALID);
+        }
+
+        let creation = if self.create_new {
+            flags::CREAT | flags::EXCL
+        } else {
+            let mut creation = 0;
+            if self.create {
+                creation |= flags::CREAT;
+            }
+            if self.truncate {
+                creation |= flags::TRUNC;
+            }
+            creation
+        };
+
+        let append = if self.append { flags::APPEND } else { 0 };
+        Ok(access | creation | append)
+    }
+
+    pub fn open<P: AsRef<str>>(&self, path: P) -> io::Result<File> {
+        let fd = fileio::open(path.as_ref(), self.flags()?)?;
+        Ok(File { fd })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileType {
+    stat: Stat,
+}
+
+impl FileType {
+    pub fn is_dir(&self) -> bool {
+        self.stat.is_dir()
+    }
+
+    pub fn is_file(&self) -> bool {
+        self.stat.is_file()
+    }
+
+    pub fn is_symlink(&self) -> bool {
+        self.stat.is_symlink()
+    }
+}
+
+/// Metadata of a file, as reported by its IOP driver.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Metadata {
+    stat: Stat,
+}
+
+impl Metadata {
+    pub fn file_type(&self) -> FileType {
+        FileType { stat: self.stat }
+    }
+
+    pub fn is_dir(&self) -> bool {
+        self.stat.is_dir()
+    }
+
+    pub fn is_file(&self) -> bool {
+        self.stat.is_file()
+    }
+
+    pub fn is_symlink(&self) -> bool {
+        self.stat.is_symlink()
+    }
+
+    pub fn len(&self) -> u64 {
+        self.stat.size
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.stat.size == 0
+    }
+
+    pub fn modified(&self) -> Time {
+        self.stat.mtime
+    }
+
+    pub fn accessed(&self) -> Time {
+        self.stat.atime
+    }
+
+    pub fn created(&self) -> Time {
+        self.stat.ctime
+    }
+
+    /// Raw status, including the driver specific mode and attributes.
+    pub fn stat(&self) -> &Stat {
+        &self.stat
+    }
+}
+
+/// Iterator over the entries of a directory, skipping `.` and `..`.
+#[derive(Debug)]
+pub struct ReadDir {
+    fd: i32,
+    path: String,
+}
+
+impl Iterator for ReadDir {
+    type Item = io::Result<DirEntry>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let entry = match fileio::dread(self.fd) {
+                Ok(Some(entry)) => entry,
+                Ok(None) => return None,
+                Err(err) => return Some(Err(err.into())),
+            };
+
+            if entry.name == "." || entry.name == ".." {
+                continue;
+            }
+
+            return Some(Ok(DirEntry {
+                path: join(&self.path, &entry.name),
+                name: entry.name,
+                stat: entry.stat,
+            }));
+        }
+    }
+}
+
+impl Drop for ReadDir {
+    fn drop(&mut self) {
+        let _ = fileio::dclose(self.fd);
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DirEntry {
+    path: String,
+    name: String,
+    stat: Stat,
+}
+
+impl DirEntry {
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn file_name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn metadata(&self) -> io::Result<Metadata> {
+        Ok(Metadata { stat: self.stat })
+    }
+
+    pub fn file_type(&self) -> io::Result<FileType> {
+        Ok(FileType { stat: self.stat })
+    }
+}
+
+/// Join `name` to the directory `path`, which might be just a device.
+fn join(path: &str, name: &str) -> String {
+    if path.ends_with(['/', '\\', ':']) {
+        format!("{path}{name}")
+    } else {
+        format!("{path}/{name}")
+    }
+}
+
+pub fn read_dir<P: AsRef<str>>(path: P) -> io::Result<ReadDir> {
+    let path = path.as_ref();
+    let fd = fileio::dopen(path)?;
+    Ok(ReadDir {
+        fd,
+        path: path.into(),
+    })
+}
+
+pub fn metadata<P: AsRef<str>>(path: P) -> io::Result<Metadata> {
+    let stat = fileio::getstat(path.as_ref())?;
+    Ok(Metadata { stat })
+}
+
+pub fn exists<P: AsRef<str>>(path: P) -> io::Result<bool> {
+    match metadata(path) {
+        Ok(_) => Ok(true),
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(false),
+        Err(err) => Err(err),
+    }
+}
+
+pub fn remove_file<P: AsRef<str>>(path: P) -> io::Result<()> {
+    Ok(fileio::remove(path.as_ref())?)
+}
+
+pub fn create_dir<P: AsRef<str>>(path: P) -> io::Result<()> {
+    Ok(fileio::mkdir(path.as_ref(), 0o777)?)
+}
+
+/// Create a directory and all its missing parents.
+pub fn create_dir_all<P: AsRef<str>>(path: P) -> io::Result<()> {
+    let path = path.as_ref();
+    // Never try to create the device itself
+    let root = path.find(':').map_or(0, |pos| pos + 1);
+
+    let ends = path[root..]
+        .match_indices(['/', '\\'])
+        .map(|(pos, _)| root + pos)
+        .chain([path.len()]);
+
+    for end in ends {
+        let dir = &path[..end];
+        // Skip the device and empty components
+        if end == root || dir.ends_with(['/', '\\']) {
+            continue;
+        }
+
+        match create_dir(dir) {
+            Ok(()) => {}
+            Err(_) if metadata(dir).is_ok_and(|meta| meta.is_dir()) => {}
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(())
+}
+
+pub fn remove_dir<P: AsRef<str>>(path: P) -> io::Result<()> {
+    Ok(fileio::rmdir(path.as_ref())?)
+}
+
+/// Read the whole file at `path`.
+pub fn read<P: AsRef<str>>(path: P) -> io::Result<Vec<u8>> {
+    let path = path.as_ref();
+    let mut file = File::open(path)?;
+
+    // Avoid growing the buffer one round trip at a time
+    let size = metadata(path).map_or(0, |meta| meta.len() as usize);
+    let mut buf = Vec::with_capacity(size);
+    file.read_to_end(&mut buf)?;
+    Ok(buf)
+}
+
+/// Read the whole file at `path`, which must be valid UTF-8.
+pub fn read_to_string<P: AsRef<str>>(path: P) -> io::Result<String> {
+    let mut buf = String::new();
+    File::open(path)?.read_to_string(&mut buf)?;
+    Ok(buf)
+}
+
+/// Write `contents` to the file at `path`, replacing it.
+pub fn write<P: AsRef<str>, C: AsRef<[u8]>>(path: P, contents: C) -> io::Result<()> {
+    File::create(path)?.write_all(contents.as_ref())
+}
@@ -0,0 +1,675 @@
+//! I/O traits, helpers and errors, shaped after `std::io`.
+//!
+//! OS error codes are the errno values returned by IOP drivers.
+
+use alloc_crate::boxed::Box;
+use alloc_crate::string::String;
+use alloc_crate::vec::Vec;
+use core::{cmp, fmt, str};
+
+use rps2_sif::fileio::{errno, Errno};
+
+mod buffered;
+mod cursor;
+mod stdio;
+
+pub use buffered::{BufReader, BufWriter, IntoInnerError};
+pub use cursor::Cursor;
+pub use stdio::{stderr, stdout, Stderr, Stdout};
+
+pub type Result<T> = core::result::Result<T, Error>;
+
+const DEFAULT_BUF_SIZE: usize = 4096;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    NotFound,
+    PermissionDenied,
+    AlreadyExists,
+    WouldBlock,
+    NotADirectory,
+    IsADirectory,
+    DirectoryNotEmpty,
+    ReadOnlyFilesystem,
+    StorageFull,
+    NotSeekable,
+    FileTooLarge,
+    ResourceBusy,
+    Deadlock,
+    CrossesDevices,
+    TooManyLinks,
+    InvalidFilename,
+    ArgumentListTooLong,
+    InvalidInput,
+    InvalidData,
+    TimedOut,
+    WriteZero,
+    Interrupted,
+    Unsupported,
+    UnexpectedEof,
+    OutOfMemory,
+    BrokenPipe,
+    Other,
+}
+
+impl ErrorKind {
+    pub fn as_str(&self) -> &'static str {
+        use ErrorKind::*;
+        match *self {
+            NotFound => "entity not found",
+            PermissionDenied => "permission denied",
+            AlreadyExists => "entity already exists",
+            WouldBlock => "operation would block",
+            NotADirectory => "not a directory",
+            IsADirectory => "is a directory",
+            DirectoryNotEmpty => "directory not empty",
+            ReadOnlyFilesystem => "read-only filesystem or storage medium",
+            StorageFull => "no storage space",
+            NotSeekable => "seek on unseekable file",
+            FileTooLarge => "file too large",
+            ResourceBusy => "resource busy",
+            Deadlock => "deadlock",
+            CrossesDevices => "cross-device link or rename",
+            TooManyLinks => "too many links",
+            InvalidFilename => "invalid filename",
+            ArgumentListTooLong => "argument list too long",
+            InvalidInput => "invalid input parameter",
+            InvalidData => "invalid data",
+            TimedOut => "timed out",
+            WriteZero => "write zero",
+            Interrupted => "operation interrupted",
+            Unsupported => "unsupported",
+            UnexpectedEof => "unexpected end of file",
+            OutOfMemory => "out of memory",
+            BrokenPipe => "broken pipe",
+            Other => "other error",
+        }
+    }
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+fn decode_error_kind(code: i32) -> ErrorKind {
+    use ErrorKind::*;
+    match code {
+        errno::EPERM | errno::EACCES => PermissionDenied,
+        errno::ENOENT | errno::ENXIO | errno::ENODEV => NotFound,
+        errno::EINTR => Interrupted,
+        errno::E2BIG => ArgumentListTooLong,
+        errno::EAGAIN => WouldBlock,
+        errno::ENOMEM => OutOfMemory,
+        errno::EBUSY => ResourceBusy,
+        errno::EEXIST => AlreadyExists,
+        errno::EXDEV => CrossesDevices,
+        errno::ENOTDIR => NotADirectory,
+        errno::EISDIR => IsADirectory,
+        errno::EINVAL | errno::EBADF | errno::EFAULT => InvalidInput,
+        errno::EFBIG => FileTooLarge,
+        errno::ENOSPC => StorageFull,
+        errno::ESPIPE => NotSeekable,
+        errno::EROFS => ReadOnlyFilesystem,
+        errno::EMLINK => TooManyLinks,
+        errno::EPIPE => BrokenPipe,
+        errno::EDEADLK => Deadlock,
+        errno::ENOSYS | errno::ENOTSUP => Unsupported,
+        errno::ENOTEMPTY => DirectoryNotEmpty,
+        errno::ENAMETOOLONG => InvalidFilename,
+        errno::ETIMEDOUT => TimedOut,
+        _ => Other,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Repr {
+    Os(i32),
+    Simple(ErrorKind),
+    Message(ErrorKind, &'static str),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Error {
+    repr: Repr,
+}
+
+impl Error {
+    pub const fn new(kind: ErrorKind, message: &'static str) -> Self {
+        Self {
+            repr: Repr::Message(kind, message),
+        }
+    }
+
+    /// Wrap an IOP errno code.
+    pub const fn from_raw_os_error(code: i32) -> Self {
+        Self {
+            repr: Repr::Os(code),
+        }
+    }
+
+    pub fn raw_os_error(&self) -> Option<i32> {
+        match self.repr {
+            Repr::Os(code) => Some(code),
+            _ => None,
+        }
+    }
+
+    pub fn kind(&self) -> ErrorKind {
+        match self.repr {
+            Repr::Os(code) => decode_error_kind(code),
+            Repr::Simple(kind) | Repr::Message(kind, _) => kind,
+        }
+    }
+}
+
+impl From<ErrorKind> for Error {
+    fn from(kind: ErrorKind) -> Self {
+        Self {
+            repr: Repr::Simple(kind),
+        }
+    }
+}
+
+impl From<Errno> for Error {
+    fn from(errno: Errno) -> Self {
+        Self::from_raw_os_error(errno.0)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.repr {
+            Repr::Os(code) => write!(f, "{} (os error {code})", decode_error_kind(code)),
+            Repr::Simple(kind) => write!(f, "{kind}"),
+            Repr::Message(_, message) => f.write_str(message),
+        }
+    }
+}
+
+impl core::error::Error for Error {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SeekFrom {
+    Start(u64),
+    End(i64),
+    Current(i64),
+}
+
+const UNEXPECTED_EOF: Error = Error::new(ErrorKind::UnexpectedEof, "failed to fill whole buffer");
+const WRITE_ZERO: Error = Error::new(ErrorKind::WriteZero, "failed to write whole buffer");
+const INVALID_UTF8: Error =
+    Error::new(ErrorKind::InvalidData, "stream did not contain valid UTF-8");
+
+pub trait Read {
+    /// Read some bytes into `buf`, returning how many were read. `0` means end of file.
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize>;
+
+    /// Read until end of file, appending to `buf`.
+    fn read_to_end(&mut self, buf: &mut Vec<u8>) -> Result<usize> {
+        let start = buf.len();
+        loop {
+            if buf.len() == buf.capacity() {
+                buf.reserve(32);
+            }
+
+            let len = buf.len();
+            buf.resize(buf.capacity(), 0);
+            let res = self.read(&mut buf[len..]);
+            match res {
+                Ok(0) => {
+                    buf.truncate(len);
+                    return Ok(len - start);
+                }
+                Ok(read) => buf.truncate(len + read),
+                Err(err) if err.kind() == ErrorKind::Interrupted => buf.truncate(len),
+                Err(err) => {
+                    buf.truncate(len);
+                    return Err(err);
+                }
+            }
+        }
+    }
+
+    /// Read until end of file, appending to `buf`. Nothing is appended if the data is not UTF-8.
+    fn read_to_string(&mut self, buf: &mut String) -> Result<usize> {
+        let mut bytes = Vec::new();
+        self.read_to_end(&mut bytes)?;
+        let string = str::from_utf8(&bytes).map_err(|_| INVALID_UTF8)?;
+        buf.push_str(string);
+        Ok(string.len())
+    }
+
+    /// Fill the whole `buf`, failing with [`ErrorKind::UnexpectedEof`] if the data runs out.
+    fn read_exact(&mut self, mut buf: &mut [u8]) -> Result<()> {
+        while !buf.is_empty() {
+            match self.read(buf) {
+                Ok(0) => return Err(UNEXPECTED_EOF),
+                Ok(read) => buf = &mut buf[read..],
+                Err(err) if err.kind() == ErrorKind::Interrupted => {}
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(())
+    }
+
+    fn by_ref(&mut self) -> &mut Self
+    where
+        Self: Sized,
+    {
+        self
+    }
+
+    /// Iterator over the bytes of this reader.
+    fn bytes(self) -> Bytes<Self>
+    where
+        Self: Sized,
+    {
+        Bytes { inner: self }
+    }
+
+    /// Adapter reading at most `limit` bytes.
+    fn take(self, limit: u64) -> Take<Self>
+    where
+        Self: Sized,
+    {
+        Take { inner: self, limit }
+    }
+}
+
+pub trait Write {
+    /// Write some bytes from `buf`, returning how many were written.
+    fn write(&mut self, buf: &[u8]) -> Result<usize>;
+
+    fn flush(&mut self) -> Result<()>;
+
+    /// Write the whole `buf`, failing with [`ErrorKind::WriteZero`] if no progress can be made.
+    fn write_all(&mut self, mut buf: &[u8]) -> Result<()> {
+        while !buf.is_empty() {
+            match self.write(buf) {
+                Ok(0) => return Err(WRITE_ZERO),
+                Ok(written) => buf = &buf[written..],
+                Err(err) if err.kind() == ErrorKind::Interrupted => {}
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(())
+    }
+
+    /// Write formatted text, the target of the `write!` macro.
+    fn write_fmt(&mut self, args: fmt::Arguments) -> Result<()> {
+        struct Adapter<'a, W: ?Sized> {
+            inner: &'a mut W,
+            error: Result<()>,
+        }
+
+        impl<W: Write + ?Sized> fmt::Write for Adapter<'_, W> {
+            fn write_str(&mut self, s: &str) -> fmt::Result {
+                self.inner.write_all(s.as_bytes()).map_err(|err| {
+                    self.error = Err(err);
+                    fmt::Error
+                })
+            }
+        }
+
+        let mut adapter = Adapter {
+            inner: self,
+            error: Ok(()),
+        };
+        match fmt::write(&mut adapter, args) {
+            Ok(()) => Ok(()),
+            // Formatting errors that do not come from the writer are reported as `Other`
+            Err(_) => adapter
+                .error
+                .and(Err(Error::new(ErrorKind::Other, "formatter error"))),
+        }
+    }
+
+    fn by_ref(&mut self) -> &mut Self
+    where
+        Self: Sized,
+    {
+        self
+    }
+}
+
+pub trait Seek {
+    /// Move the cursor to `pos`, returning the new position from the start.
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64>;
+
+    fn rewind(&mut self) -> Result<()> {
+        self.seek(SeekFrom::Start(0))?;
+        Ok(())
+    }
+
+    fn stream_position(&mut self) -> Result<u64> {
+        self.seek(SeekFrom::Current(0))
+    }
+}
+
+/// A reader with an internal buffer, which can be inspected and consumed.
+pub trait BufRead: Read {
+    /// Return the buffered data, filling the buffer if it is empty.
+    fn fill_buf(&mut self) -> Result<&[u8]>;
+
+    /// Mark `amt` bytes of the buffer as read.
+    fn consume(&mut self, amt: usize);
+
+    /// Read until `byte` or end of file, appending to `buf`. The delimiter is included.
+    fn read_until(&mut self, byte: u8, buf: &mut Vec<u8>) -> Result<usize> {
+        let mut read = 0;
+        loop {
+            let available = match self.fill_buf() {
+                Ok(available) => available,
+                Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+                Err(err) => return Err(err),
+            };
+
+            let (done, used) = match available.iter().position(|&b| b == byte) {
+                Some(pos) => (true, pos + 1),
+                None => (available.is_empty(), available.len()),
+            };
+            buf.extend_from_slice(&available[..used]);
+            self.consume(used);
+            read += used;
+
+            if done {
+                return Ok(read);
+            }
+        }
+    }
+
+    /// Read a line, appending it to `buf` including the trailing `\n`.
+    fn read_line(&mut self, buf: &mut String) -> Result<usize> {
+        let mut bytes = Vec::new();
+        let read = self.read_until(b'\n', &mut bytes)?;
+        buf.push_str(str::from_utf8(&bytes).map_err(|_| INVALID_UTF8)?);
+        Ok(read)
+    }
+
+    /// Iterator over the lines of this reader, without the trailing `\n` or `\r\n`.
+    fn lines(self) -> Lines<Self>
+    where
+        Self: Sized,
+    {
+        Lines { inner: self }
+    }
+}
+
+/// Copy all of `reader` into `writer`, returning the number of bytes copied.
+pub fn copy<R, W>(reader: &mut R, writer: &mut W) -> Result<u64>
+where
+    R: Read + ?Sized,
+    W: Write + ?Sized,
+{
+    let mut buf = [0; DEFAULT_BUF_SIZE];
+    let mut copied = 0;
+    loop {
+        let read = match reader.read(&mut buf) {
+            Ok(0) => return Ok(copied),
+            Ok(read) => read,
+            Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err),
+        };
+        writer.write_all(&buf[..read])?;
+        copied += read as u64;
+    }
+}
+
+/// Iterator returned by [`Read::bytes`].
+#[derive(Debug)]
+pub struct Bytes<R> {
+    inner: R,
+}
+
+impl<R: Read> Iterator for Bytes<R> {
+    type Item = Result<u8>;
+
+    fn next(&mut self) -> Option<Result<u8>> {
+        let mut byte = 0;
+        loop {
+            return match self.inner.read(core::slice::from_mut(&mut byte)) {
+                Ok(0) => None,
+                Ok(_) => Some(Ok(byte)),
+                Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+                Err(err) => Some(Err(err)),
+            };
+        }
+    }
+}
+
+/// Reader returned by [`Read::take`].
+#[derive(Debug)]
+pub struct Take<R> {
+    inner: R,
+    limit: u64,
+}
+
+impl<R> Take<R> {
+    /// Number of bytes left to read.
+    pub fn limit(&self) -> u64 {
+        self.limit
+    }
+
+    pub fn set_limit(&mut self, limit: u64) {
+        self.limit = limit;
+    }
+
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: Read> Read for Take<R> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        if self.limit == 0 {
+            return Ok(0);
+        }
+
+        let max = cmp::min(buf.len() as u64, self.limit) as usize;
+        let read = self.inner.read(&mut buf[..max])?;
+        self.limit -= read as u64;
+        Ok(read)
+    }
+}
+
+impl<R: BufRead> BufRead for Take<R> {
+    fn fill_buf(&mut self) -> Result<&[u8]> {
+        if self.limit == 0 {
+            return Ok(&[]);
+        }
+
+        let buf = self.inner.fill_buf()?;
+        let max = cmp::min(buf.len() as u64, self.limit) as usize;
+        Ok(&buf[..max])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        let amt = cmp::min(amt as u64, self.limit) as usize;
+        self.limit -= amt as u64;
+        self.inner.consume(amt);
+    }
+}
+
+/// Iterator returned by [`BufRead::lines`].
+#[derive(Debug)]
+pub struct Lines<B> {
+    inner: B,
+}
+
+impl<B: BufRead> Iterator for Lines<B> {
+    type Item = Result<String>;
+
+    fn next(&mut self) -> Option<Result<String>> {
+        let mut line = String::new();
+        match self.inner.read_line(&mut line) {
+            Ok(0) => None,
+            Ok(_) => {
+                if line.ends_with('\n') {
+                    line.pop();
+                    if line.ends_with('\r') {
+                        line.pop();
+                    }
+                }
+                Some(Ok(line))
+            }
+            Err(err) => Some(Err(err)),
+        }
+    }
+}
+
+impl Read for &[u8] {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        let len = cmp::min(buf.len(), self.len());
+        let (head, tail) = self.split_at(len);
+        buf[..len].copy_from_slice(head);
+        *self = tail;
+        Ok(len)
+    }
+}
+
+impl BufRead for &[u8] {
+    fn fill_buf(&mut self) -> Result<&[u8]> {
+        Ok(*self)
+    }
+
+    fn consume(&mut self, amt: usize) {
+        *self = &self[amt..];
+    }
+}
+
+impl Write for &mut [u8] {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        let len = cmp::min(buf.len(), self.len());
+        let (head, tail) = core::mem::take(self).split_at_mut(len);
+        head.copy_from_slice(&buf[..len]);
+        *self = tail;
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl Write for Vec<u8> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        self.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> Result<()> {
+        self.extend_from_slice(buf);
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl<R: Read + ?Sized> Read for &mut R {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        (**self).read(buf)
+    }
+
+    fn read_to_end(&mut self, buf: &mut Vec<u8>) -> Result<usize> {
+        (**self).read_to_end(buf)
+    }
+
+    fn read_exact(&mut self, buf: &mut [u8]) -> Result<()> {
+        (**self).read_exact(buf)
+    }
+}
+
+impl<R: Read + ?Sized> Read for Box<R> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        (**self).read(buf)
+    }
+
+    fn read_to_end(&mut self, buf: &mut Vec<u8>) -> Result<usize> {
+        (**self).read_to_end(buf)
+    }
+
+    fn read_exact(&mut self, buf: &mut [u8]) -> Result<()> {
+        (**self).read_exact(buf)
+    }
+}
+
+impl<W: Write + ?Sized> Write for &mut W {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        (**self).write(buf)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        (**self).flush()
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> Result<()> {
+        (**self).write_all(buf)
+    }
+
+    fn write_fmt(&mut self, args: fmt::Arguments) -> Result<()> {
+        (**self).write_fmt(args)
+    }
+}
+
+impl<W: Write + ?Sized> Write for Box<W> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        (**self).write(buf)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        (**self).flush()
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> Result<()> {
+        (**self).write_all(buf)
+    }
+
+    fn write_fmt(&mut self, args: fmt::Arguments) -> Result<()> {
+        (**self).write_fmt(args)
+    }
+}
+
+impl<S: Seek + ?Sized> Seek for &mut S {
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64> {
+        (**self).seek(pos)
+    }
+}
+
+impl<S: Seek + ?Sized> Seek for Box<S> {
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64> {
+        (**self).seek(pos)
+    }
+}
+
+impl<B: BufRead + ?Sized> BufRead for &mut B {
+    fn fill_buf(&mut self) -> Result<&[u8]> {
+        (**self).fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        (**self).consume(amt)
+    }
+}
+
+impl<B: BufRead + ?Sized> BufRead for Box<B> {
+    fn fill_buf(&mut self) -> Result<&[u8]> {
+        (**self).fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        (**self).consume(amt)
+    }
+}
@@ -0,0 +1,273 @@
+use alloc_crate::boxed::Box;
+use alloc_crate::vec;
+use alloc_crate::vec::Vec;
+use core::{cmp, fmt};
+
+use super::{BufRead, Error, ErrorKind, Read, Result, Seek, SeekFrom, Write, DEFAULT_BUF_SIZE};
+
+/// Adds buffering to a reader, useful when each read is an IOP round trip.
+pub struct BufReader<R> {
+    inner: R,
+    buf: Box<[u8]>,
+    pos: usize,
+    filled: usize,
+}
+
+impl<R: Read> BufReader<R> {
+    pub fn new(inner: R) -> Self {
+        Self::with_capacity(DEFAULT_BUF_SIZE, inner)
+    }
+
+    pub fn with_capacity(capacity: usize, inner: R) -> Self {
+        Self {
+            inner,
+            buf: vec![0; capacity].into_boxed_slice(),
+            pos: 0,
+            filled: 0,
+        }
+    }
+}
+
+impl<R> BufReader<R> {
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// Mutable access to the reader, reading from it directly skips the buffered data.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    /// Unwrap the reader, the buffered data is lost.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    /// Data buffered but not read yet.
+    pub fn buffer(&self) -> &[u8] {
+        &self.buf[self.pos..self.filled]
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.buf.len()
+    }
+
+    fn discard_buffer(&mut self) {
+        self.pos = 0;
+        self.filled = 0;
+    }
+}
+
+impl<R: Read> Read for BufReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        // Large reads on an empty buffer would only add a copy
+        if self.pos == self.filled && buf.len() >= self.capacity() {
+            self.discard_buffer();
+            return self.inner.read(buf);
+        }
+
+        let mut available = self.fill_buf()?;
+        let read = available.read(buf)?;
+        self.consume(read);
+        Ok(read)
+    }
+}
+
+impl<R: Read> BufRead for BufReader<R> {
+    fn fill_buf(&mut self) -> Result<&[u8]> {
+        if self.pos >= self.filled {
+            self.filled = self.inner.read(&mut self.buf)?;
+            self.pos = 0;
+        }
+        Ok(self.buffer())
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.pos = cmp::min(self.pos + amt, self.filled);
+    }
+}
+
+impl<R: Seek> Seek for BufReader<R> {
+    /// Seek the underlying reader, discarding the buffer.
+    ///
+    /// `SeekFrom::Current` is relative to the position of the data not read yet.
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64> {
+        let res = if let SeekFrom::Current(offset) = pos {
+            let remaining = (self.filled - self.pos) as i64;
+            // Seek twice if the offset would overflow
+            match offset.checked_sub(remaining) {
+                Some(offset) => self.inner.seek(SeekFrom::Current(offset))?,
+                None => {
+                    self.inner.seek(SeekFrom::Current(-remaining))?;
+                    self.discard_buffer();
+                    self.inner.seek(SeekFrom::Current(offset))?
+                }
+            }
+        } else {
+            self.inner.seek(pos)?
+        };
+
+        self.discard_buffer();
+        Ok(res)
+    }
+}
+
+impl<R: fmt::Debug> fmt::Debug for BufReader<R> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("BufReader")
+            .field("reader", &self.inner)
+            .field(
+                "buffer",
+                &format_args!("{}/{}", self.filled - self.pos, self.capacity()),
+            )
+            .finish()
+    }
+}
+
+/// Adds buffering to a writer, flushed when dropped.
+///
+/// Errors on the final flush can not be reported, call [`Write::flush`] before dropping to
+/// handle them.
+pub struct BufWriter<W: Write> {
+    // Only `None` after `into_inner`
+    inner: Option<W>,
+    buf: Vec<u8>,
+}
+
+impl<W: Write> BufWriter<W> {
+    pub fn new(inner: W) -> Self {
+        Self::with_capacity(DEFAULT_BUF_SIZE, inner)
+    }
+
+    pub fn with_capacity(capacity: usize, inner: W) -> Self {
+        Self {
+            inner: Some(inner),
+            buf: Vec::with_capacity(capacity),
+        }
+    }
+
+    pub fn get_ref(&self) -> &W {
+        self.inner.as_ref().unwrap()
+    }
+
+    /// Mutable access to the writer, writing to it directly skips the buffered data.
+    pub fn get_mut(&mut self) -> &mut W {
+        self.inner.as_mut().unwrap()
+    }
+
+    /// Flush the buffer and unwrap the writer.
+    pub fn into_inner(mut self) -> core::result::Result<W, IntoInnerError<Self>> {
+        match self.flush_buf() {
+            Ok(()) => Ok(self.inner.take().unwrap()),
+            Err(err) => Err(IntoInnerError(self, err)),
+        }
+    }
+
+    /// Data written but not flushed yet.
+    pub fn buffer(&self) -> &[u8] {
+        &self.buf
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.buf.capacity()
+    }
+
+    fn flush_buf(&mut self) -> Result<()> {
+        let inner = self.inner.as_mut().unwrap();
+
+        let mut written = 0;
+        let res = loop {
+            if written == self.buf.len() {
+                break Ok(());
+            }
+
+            match inner.write(&self.buf[written..]) {
+                Ok(0) => {
+                    break Err(Error::new(
+                        ErrorKind::WriteZero,
+                        "failed to write the buffered data",
+                    ))
+                }
+                Ok(len) => written += len,
+                Err(err) if err.kind() == ErrorKind::Interrupted => {}
+                Err(err) => break Err(err),
+            }
+        };
+
+        // Keep what could not be written for the next attempt
+        self.buf.drain(..written);
+        res
+    }
+}
+
+impl<W: Write> Write for BufWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        if self.buf.len() + buf.len() > self.capacity() {
+            self.flush_buf()?;
+        }
+
+        if buf.len() >= self.capacity() {
+            self.get_mut().write(buf)
+        } else {
+            self.buf.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.flush_buf()?;
+        self.get_mut().flush()
+    }
+}
+
+impl<W: Write + Seek> Seek for BufWriter<W> {
+    /// Flush the buffer and seek the underlying writer.
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64> {
+        self.flush_buf()?;
+        self.get_mut().seek(pos)
+    }
+}
+
+impl<W: Write> Drop for BufWriter<W> {
+    fn drop(&mut self) {
+        if self.inner.is_some() {
+            let _ = self.flush_buf();
+        }
+    }
+}
+
+impl<W: Write + fmt::Debug> fmt::Debug for BufWriter<W> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("BufWriter")
+            .field("writer", &self.inner)
+            .field(
+                "buffer",
+                &format_args!("{}/{}", self.buf.len(), self.capacity()),
+            )
+            .finish()
+    }
+}
+
+/// Error returned by [`BufWriter::into_inner`], holding the writer which failed to flush.
+#[derive(Debug)]
+pub struct IntoInnerError<W>(W, Error);
+
+impl<W> IntoInnerError<W> {
+    pub fn error(&self) -> &Error {
+        &self.1
+    }
+
+    pub fn into_inner(self) -> W {
+        self.0
+    }
+
+    pub fn into_error(self) -> Error {
+        self.1
+    }
+}
+
+impl<W> fmt::Display for IntoInnerError<W> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.1.fmt(f)
+    }
+}
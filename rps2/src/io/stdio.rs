@@ -0,0 +1,73 @@
+use core::{fmt, str};
+
+use rps2_kernel::debug;
+
+use super::{Result, Write};
+
+/// Handle to the standard output, written through the kernel debug output.
+#[derive(Debug, Clone, Copy)]
+pub struct Stdout(());
+
+/// Handle to the standard error. The PS2 has a single debug output, shared with [`Stdout`].
+#[derive(Debug, Clone, Copy)]
+pub struct Stderr(());
+
+pub fn stdout() -> Stdout {
+    Stdout(())
+}
+
+pub fn stderr() -> Stderr {
+    Stderr(())
+}
+
+/// Print the longest UTF-8 prefix of `buf`, returning the number of bytes consumed.
+///
+/// Invalid bytes are printed as U+FFFD, so multibyte characters must not be split across
+/// writes. `write_fmt` always writes whole characters.
+fn write_lossy(buf: &[u8]) -> usize {
+    match str::from_utf8(buf) {
+        Ok(s) => {
+            debug::print(format_args!("{s}"));
+            buf.len()
+        }
+        Err(err) if err.valid_up_to() > 0 => {
+            let s = unsafe { str::from_utf8_unchecked(&buf[..err.valid_up_to()]) };
+            debug::print(format_args!("{s}"));
+            err.valid_up_to()
+        }
+        Err(err) => {
+            debug::print(format_args!("\u{fffd}"));
+            err.error_len().unwrap_or(buf.len())
+        }
+    }
+}
+
+impl Write for Stdout {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        Ok(write_lossy(buf))
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn write_fmt(&mut self, args: fmt::Arguments) -> Result<()> {
+        debug::print(args);
+        Ok(())
+    }
+}
+
+impl Write for Stderr {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        Ok(write_lossy(buf))
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn write_fmt(&mut self, args: fmt::Arguments) -> Result<()> {
+        debug::print(args);
+        Ok(())
+    }
+}
@@ -3,6 +3,7 @@ extern crate alloc as alloc_crate;
 extern crate rps2_startup;
 
 pub use rps2_kernel::{dbg, interrupt_disable_guard, kprint, kprintln};
+pub use rps2_thread::thread_local;
 
 pub mod prelude {
     pub use crate::boxed::Box;
@@ -15,20 +16,29 @@ pub mod sync {
     pub use alloc_crate::sync::*;
     pub use core::sync::*;
 
+    pub use rps2_thread::condvar::{Condvar, WaitTimeoutResult};
     pub use rps2_thread::lazy_lock::LazyLock;
     pub use rps2_thread::mutex::{IrqMutexGuard, Mutex, MutexGuard};
     pub use rps2_thread::once::Once;
     pub use rps2_thread::once_lock::OnceLock;
+    pub use rps2_thread::rwlock::{
+        RwLock, RwLockReadGuard, RwLockUpgradableReadGuard, RwLockWriteGuard,
+    };
     pub use rps2_thread::sema::{Sema, SemaBuilder};
 
     pub mod mpmc {
         pub use rps2_thread::mpmc::{BoundedQueue, UnboundedQueue};
     }
+
+    pub mod mpsc {
+        pub use rps2_thread::mpsc::*;
+    }
 }
 
 pub mod thread {
     pub use rps2_thread::thread::{
-        current, panicking, rotate_ready_queue, sleep, spawn, Builder, JoinHandle, Result, Thread,
+        current, panicking, rotate_ready_queue, scope, sleep, sleep_for, spawn, AccessError,
+        Builder, JoinHandle, LocalKey, Result, Scope, ScopedJoinHandle, Thread,
     };
 
     pub mod ffi {
@@ -40,6 +50,50 @@ pub mod arch {
     pub use rps2_kernel::arch::*;
 }
 
+pub mod interrupt {
+    pub use rps2_kernel::interrupt::*;
+}
+
+pub mod debug {
+    pub use rps2_kernel::debug::*;
+}
+
+#[cfg(feature = "log")]
+pub mod logger {
+    pub use rps2_kernel::logger::*;
+}
+
+pub mod pac {
+    pub use rps2_pac::*;
+}
+
+pub mod sif {
+    pub use rps2_sif::*;
+}
+
+pub mod pad {
+    pub use rps2_pad::*;
+}
+
+pub mod audio {
+    pub use rps2_audio::*;
+}
+
+pub mod memcard {
+    pub use rps2_sif::memcard::*;
+}
+
+pub mod cdvd {
+    pub use rps2_sif::cdvd::*;
+}
+
+pub mod dma;
+pub mod fs;
+pub mod io;
+pub mod sio;
+pub mod time;
+pub mod video;
+
 pub mod os {
     pub use rps2_kernel::os::*;
 
@@ -70,5 +124,7 @@ pub mod vec {
 
 pub mod panic {
     pub use core::panic::*;
-    pub use rps2_panic::{abort, catch_unwind, panic_any, panicking, set_backtrace_enabled};
+    pub use rps2_panic::{
+        abort, catch_unwind, panic_any, panicking, resume_unwind, set_backtrace_enabled,
+    };
 }
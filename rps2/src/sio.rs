@@ -0,0 +1,188 @@
+//! Driver for the EE serial port, a UART wired to test points on most boards.
+//!
+//! Once initialized, [`sink`] can be passed to [`debug::set_sink`](crate::debug::set_sink) to
+//! send `kprint!` output over the wire on consoles without a DECI2 host.
+
+use core::hint;
+
+use rps2_kernel::interrupt::{IntcCause, IntcHandler};
+
+use crate::pac::sio::{self, Bgr, Fcr, Ier, Isr, Lcr, Lsr, WordLength, FIFO_SIZE};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Parity {
+    #[default]
+    None,
+    Odd,
+    Even,
+}
+
+/// Line settings, 115200 8N1 by default.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Config {
+    pub baud: u32,
+    /// 7 or 8 bits per character.
+    pub seven_bits: bool,
+    pub parity: Parity,
+    pub two_stop_bits: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            baud: 115200,
+            seven_bits: false,
+            parity: Parity::None,
+            two_stop_bits: false,
+        }
+    }
+}
+
+/// Configure the line and reset the FIFOs, disabling the receive interrupt.
+pub fn init(config: Config) {
+    let word_length = if config.seven_bits {
+        WordLength::SEVEN
+    } else {
+        WordLength::EIGHT
+    };
+
+    unsafe {
+        sio::LCR.write(
+            Lcr::new()
+                .with_umode(word_length)
+                .with_usbl(config.two_stop_bits)
+                .with_upen(config.parity != Parity::None)
+                .with_ueps(config.parity == Parity::Even),
+        );
+        sio::LSR.write(
+            Lsr::new()
+                .with_dr(true)
+                .with_oe(true)
+                .with_pe(true)
+                .with_fe(true),
+        );
+        sio::IER.write(Ier::new());
+        sio::ISR.write(
+            Isr::new()
+                .with_rx_data(true)
+                .with_tx_empty(true)
+                .with_rx_error(true),
+        );
+
+        sio::FCR.write(
+            Fcr::new()
+                .with_frste(true)
+                .with_rfrst(true)
+                .with_tfrst(true),
+        );
+        sio::FCR.write(Fcr::new());
+
+        sio::BGR.write(Bgr::for_baud(config.baud));
+    }
+}
+
+/// Queue `byte` for transmission, returns `false` if the FIFO is full.
+pub fn try_put(byte: u8) -> bool {
+    if sio::ISR.read().tx_count() >= FIFO_SIZE {
+        return false;
+    }
+    unsafe { sio::TXFIFO.write(byte) };
+    true
+}
+
+/// Queue `byte` for transmission, spinning while the FIFO is full.
+pub fn put(byte: u8) {
+    while !try_put(byte) {
+        hint::spin_loop();
+    }
+}
+
+pub fn write(bytes: &[u8]) {
+    for &byte in bytes {
+        put(byte);
+    }
+}
+
+/// Wait until every queued byte left the FIFO.
+pub fn flush() {
+    while sio::ISR.read().tx_count() != 0 {
+        hint::spin_loop();
+    }
+}
+
+/// Take a received byte, if any.
+pub fn try_get() -> Option<u8> {
+    if sio::ISR.read().rx_count() == 0 {
+        return None;
+    }
+    Some(sio::RXFIFO.read())
+}
+
+/// Take a received byte, spinning until one arrives.
+pub fn get() -> u8 {
+    loop {
+        if let Some(byte) = try_get() {
+            return byte;
+        }
+        hint::spin_loop();
+    }
+}
+
+/// Read and clear the error flags of the line.
+pub fn take_errors() -> Lsr {
+    let lsr = sio::LSR.read();
+    let errors = Lsr::new()
+        .with_oe(lsr.oe())
+        .with_pe(lsr.pe())
+        .with_fe(lsr.fe());
+    unsafe { sio::LSR.write(errors) };
+    errors
+}
+
+/// A [`Sink`](crate::debug::Sink) writing to the port, turning `\n` into `\r\n` for terminals.
+pub fn sink(s: &str) {
+    for &byte in s.as_bytes() {
+        if byte == b'\n' {
+            put(b'\r');
+        }
+        put(byte);
+    }
+}
+
+/// Guard for a receive interrupt handler, disabling the interrupt when dropped.
+#[derive(Debug)]
+pub struct RxInterrupt {
+    _handler: IntcHandler,
+}
+
+impl RxInterrupt {
+    /// Call `f` with every received byte, from interrupt context.
+    ///
+    /// The port raises its interrupt through the SBUS cause of the INTC, shared with the IOP.
+    /// Returns `None` if the kernel ran out of handler slots.
+    pub fn register<F>(mut f: F) -> Option<Self>
+    where
+        F: FnMut(u8) + Send + 'static,
+    {
+        let handler = IntcHandler::register(IntcCause::Sbus, move |_| {
+            if !sio::ISR.read().rx_data() {
+                return;
+            }
+            while let Some(byte) = try_get() {
+                f(byte);
+            }
+            unsafe {
+                sio::ISR.write(Isr::new().with_rx_data(true).with_rx_error(true));
+            }
+        })?;
+
+        unsafe { sio::IER.modify(|ier| ier.with_erdai(true)) };
+        Some(Self { _handler: handler })
+    }
+}
+
+impl Drop for RxInterrupt {
+    fn drop(&mut self) {
+        unsafe { sio::IER.modify(|ier| ier.with_erdai(false)) };
+    }
+}
@@ -0,0 +1,174 @@
+//! Monotonic clock based on the COP0 cycle counter.
+//!
+//! The 32 bit counter wraps every ~14.5 seconds, it is extended to 64 bits by sampling it at
+//! least once per wrap from the overflow interrupt of timer T1, clocked by HBLNK.
+
+use core::cell::RefCell;
+use core::ops::{Add, AddAssign, Sub, SubAssign};
+use critical_section::Mutex;
+
+use rps2_kernel::arch::cop0;
+use rps2_kernel::interrupt::{IntcCause, IntcHandler};
+use rps2_pac::timer::{self, ClockSource};
+
+pub use core::time::Duration;
+
+/// Frequency of the COP0 cycle counter.
+pub const CPU_CLOCK_HZ: u64 = 294_912_000;
+
+const NANOS_PER_SEC: u128 = 1_000_000_000;
+
+struct Clock {
+    handler: Option<IntcHandler>,
+    epoch: u32,
+    last: u32,
+}
+
+static CLOCK: Mutex<RefCell<Clock>> = Mutex::new(RefCell::new(Clock {
+    handler: None,
+    epoch: 0,
+    last: 0,
+}));
+
+impl Clock {
+    fn sample(&mut self) -> u64 {
+        let count = cop0::get_count();
+        if count < self.last {
+            self.epoch += 1;
+        }
+        self.last = count;
+
+        ((self.epoch as u64) << 32) | count as u64
+    }
+
+    fn start(&mut self) {
+        let handler = IntcHandler::register(IntcCause::Timer1, |_| {
+            // Acknowledge the overflow
+            unsafe {
+                timer::T1
+                    .mode()
+                    .modify(|mode| mode.with_ovff(true).with_equf(false));
+            }
+            critical_section::with(|cs| {
+                CLOCK.borrow_ref_mut(cs).sample();
+            });
+        })
+        .expect("Failed to register T1 handler");
+
+        unsafe {
+            timer::T1.count().write(timer::Count::new());
+            timer::T1.mode().write(
+                timer::Mode::new()
+                    .with_clks(ClockSource::HBLNK)
+                    .with_cue(true)
+                    .with_ovfe(true)
+                    .with_equf(true)
+                    .with_ovff(true),
+            );
+        }
+
+        self.handler = Some(handler);
+    }
+}
+
+fn cycles() -> u64 {
+    critical_section::with(|cs| {
+        let mut clock = CLOCK.borrow_ref_mut(cs);
+        if clock.handler.is_none() {
+            clock.start();
+        }
+        clock.sample()
+    })
+}
+
+fn cycles_to_duration(cycles: u64) -> Duration {
+    let nanos = cycles as u128 * NANOS_PER_SEC / CPU_CLOCK_HZ as u128;
+    Duration::new(
+        (nanos / NANOS_PER_SEC) as u64,
+        (nanos % NANOS_PER_SEC) as u32,
+    )
+}
+
+fn duration_to_cycles(duration: Duration) -> Option<u64> {
+    let cycles = duration.as_nanos() * CPU_CLOCK_HZ as u128 / NANOS_PER_SEC;
+    cycles.try_into().ok()
+}
+
+/// A measurement of the monotonic clock, with a resolution of one CPU cycle.
+///
+/// The clock starts on the first call to [`Instant::now`], which must not happen from an
+/// interrupt handler.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Instant(u64);
+
+impl Instant {
+    pub fn now() -> Self {
+        Self(cycles())
+    }
+
+    pub fn duration_since(&self, earlier: Instant) -> Duration {
+        self.saturating_duration_since(earlier)
+    }
+
+    pub fn checked_duration_since(&self, earlier: Instant) -> Option<Duration> {
+        self.0.checked_sub(earlier.0).map(cycles_to_duration)
+    }
+
+    pub fn saturating_duration_since(&self, earlier: Instant) -> Duration {
+        self.checked_duration_since(earlier).unwrap_or_default()
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        Instant::now().duration_since(*self)
+    }
+
+    pub fn checked_add(&self, duration: Duration) -> Option<Instant> {
+        self.0
+            .checked_add(duration_to_cycles(duration)?)
+            .map(Instant)
+    }
+
+    pub fn checked_sub(&self, duration: Duration) -> Option<Instant> {
+        self.0
+            .checked_sub(duration_to_cycles(duration)?)
+            .map(Instant)
+    }
+}
+
+impl Add<Duration> for Instant {
+    type Output = Instant;
+
+    fn add(self, other: Duration) -> Instant {
+        self.checked_add(other)
+            .expect("overflow when adding duration to instant")
+    }
+}
+
+impl AddAssign<Duration> for Instant {
+    fn add_assign(&mut self, other: Duration) {
+        *self = *self + other;
+    }
+}
+
+impl Sub<Duration> for Instant {
+    type Output = Instant;
+
+    fn sub(self, other: Duration) -> Instant {
+        self.checked_sub(other)
+            .expect("overflow when subtracting duration from instant")
+    }
+}
+
+impl SubAssign<Duration> for Instant {
+    fn sub_assign(&mut self, other: Duration) {
+        *self = *self - other;
+    }
+}
+
+impl Sub<Instant> for Instant {
+    type Output = Duration;
+
+    fn sub(self, other: Instant) -> Duration {
+        self.duration_since(other)
+    }
+}
@@ -0,0 +1,344 @@
+//! Video output configuration and framebuffer management.
+
+use rps2_kernel::{arch, os};
+use rps2_pac::gs::{self, AlphaSelect, Dpms, Psm};
+
+pub use rps2_thread::vblank::{field, frame_count, wait_vblank, Field, VBlank};
+
+pub mod console;
+
+/// Size of GS local memory, in bytes.
+pub const VRAM_SIZE: u32 = 4 * 1024 * 1024;
+
+/// Size of a GS memory page, in bytes.
+pub const PAGE_SIZE: u32 = 8192;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    /// The resolution does not fit the video mode with an integer magnification.
+    InvalidResolution,
+    /// The pixel format cannot be used for display.
+    UnsupportedPsm,
+    /// The framebuffers do not fit in GS memory.
+    OutOfMemory,
+}
+
+pub type Result<T> = core::result::Result<T, Error>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VideoMode {
+    /// NTSC interlaced, reading every other line of the framebuffer on each field.
+    NtscField,
+    /// NTSC interlaced, reading every line of the framebuffer on each field.
+    NtscFrame,
+    /// PAL interlaced, reading every other line of the framebuffer on each field.
+    PalField,
+    /// PAL interlaced, reading every line of the framebuffer on each field.
+    PalFrame,
+    Dtv480p,
+    Dtv720p,
+    Dtv1080iField,
+    Dtv1080iFrame,
+    /// VESA 640x480 at 60Hz.
+    Vga640x480,
+}
+
+// Display area of a mode, in VCK units horizontally and raster lines vertically
+struct Timing {
+    mode: i16,
+    interlaced: bool,
+    ffmd: bool,
+    dx: u16,
+    dy: u16,
+    width: u16,
+    height: u16,
+}
+
+impl VideoMode {
+    fn timing(self) -> Timing {
+        #[rustfmt::skip]
+        let (mode, interlaced, ffmd, dx, dy, width, height) = match self {
+            Self::NtscField => (0x02, true, false, 632, 50, 2560, 448),
+            Self::NtscFrame => (0x02, true, true, 632, 25, 2560, 224),
+            Self::PalField => (0x03, true, false, 652, 72, 2560, 512),
+            Self::PalFrame => (0x03, true, true, 652, 36, 2560, 256),
+            Self::Dtv480p => (0x50, false, false, 232, 35, 1280, 480),
+            Self::Dtv720p => (0x52, false, false, 302, 24, 1280, 720),
+            Self::Dtv1080iField => (0x51, true, false, 238, 40, 1920, 1080),
+            Self::Dtv1080iFrame => (0x51, true, true, 238, 20, 1920, 540),
+            Self::Vga640x480 => (0x1a, false, false, 276, 34, 1280, 480),
+        };
+
+        Timing {
+            mode,
+            interlaced,
+            ffmd,
+            dx,
+            dy,
+            width,
+            height,
+        }
+    }
+
+    /// Resolution shown when the framebuffer is not magnified.
+    pub fn native_resolution(self) -> (u16, u16) {
+        let timing = self.timing();
+        let magh = match self {
+            Self::NtscField | Self::NtscFrame | Self::PalField | Self::PalFrame => 4,
+            Self::Dtv480p | Self::Vga640x480 => 2,
+            Self::Dtv720p | Self::Dtv1080iField | Self::Dtv1080iFrame => 1,
+        };
+
+        (timing.width / magh, timing.height)
+    }
+
+    /// Horizontal sync frequency, in Hz.
+    pub fn hsync_hz(self) -> u32 {
+        match self {
+            Self::NtscField | Self::NtscFrame => 15734,
+            Self::PalField | Self::PalFrame => 15625,
+            Self::Dtv480p | Self::Vga640x480 => 31469,
+            Self::Dtv720p => 45000,
+            Self::Dtv1080iField | Self::Dtv1080iFrame => 33750,
+        }
+    }
+
+    pub fn is_interlaced(self) -> bool {
+        self.timing().interlaced
+    }
+
+    /// Compute the SMODE2 value used by this mode.
+    pub fn smode2(self) -> gs::Smode2 {
+        let timing = self.timing();
+        gs::Smode2::new()
+            .with_int(timing.interlaced)
+            .with_ffmd(timing.ffmd)
+            .with_dpms(Dpms::ON)
+    }
+}
+
+/// A framebuffer in GS memory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Framebuffer {
+    /// Base pointer, in pages.
+    pub fbp: u16,
+    /// Buffer width, in units of 64 pixels.
+    pub fbw: u8,
+    pub psm: Psm,
+}
+
+impl Framebuffer {
+    /// Value of DISPFB to display this buffer.
+    pub fn dispfb(&self) -> gs::Dispfb {
+        gs::Dispfb::new()
+            .with_fbp(self.fbp)
+            .with_fbw(self.fbw)
+            .with_psm(self.psm)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct VideoBuilder {
+    mode: VideoMode,
+    width: u16,
+    height: u16,
+    psm: Psm,
+    double_buffered: bool,
+}
+
+impl VideoBuilder {
+    pub fn new(mode: VideoMode) -> Self {
+        let (width, height) = mode.native_resolution();
+        Self {
+            mode,
+            width,
+            height,
+            psm: Psm::CT32,
+            double_buffered: true,
+        }
+    }
+
+    pub fn resolution(mut self, width: u16, height: u16) -> Self {
+        self.width = width;
+        self.height = height;
+        self
+    }
+
+    pub fn psm(mut self, psm: Psm) -> Self {
+        self.psm = psm;
+        self
+    }
+
+    pub fn double_buffered(mut self, double_buffered: bool) -> Self {
+        self.double_buffered = double_buffered;
+        self
+    }
+
+    /// Compute the DISPLAY value for the configured resolution, centered on screen.
+    pub fn display(&self) -> Result<gs::Display> {
+        let timing = self.mode.timing();
+        if self.width == 0 || self.height == 0 {
+            return Err(Error::InvalidResolution);
+        }
+
+        let magh = timing.width / self.width;
+        let magv = timing.height / self.height;
+        if !(1..=16).contains(&magh) || !(1..=4).contains(&magv) {
+            return Err(Error::InvalidResolution);
+        }
+
+        let dw = self.width * magh;
+        let dh = self.height * magv;
+
+        Ok(gs::Display::new()
+            .with_dx(timing.dx + (timing.width - dw) / 2)
+            .with_dy(timing.dy + (timing.height - dh) / 2)
+            .with_magh(magh as u8 - 1)
+            .with_magv(magv as u8 - 1)
+            .with_dw(dw - 1)
+            .with_dh(dh - 1))
+    }
+
+    /// Compute the layout of the framebuffers in GS memory.
+    ///
+    /// Returns the buffers along with the number of pages they use in total.
+    pub fn framebuffers(&self) -> Result<([Framebuffer; 2], u16)> {
+        if !matches!(self.psm, Psm::CT32 | Psm::CT24 | Psm::CT16 | Psm::CT16S) {
+            return Err(Error::UnsupportedPsm);
+        }
+
+        let fbw = self.width.div_ceil(64);
+        if fbw > 32 {
+            return Err(Error::InvalidResolution);
+        }
+
+        // A page holds 64x32 pixels in 32 bit formats, and 64x64 in 16 bit ones
+        let page_height = if self.psm.bits_per_pixel() == 32 {
+            32
+        } else {
+            64
+        };
+        let pages = fbw as u32 * (self.height as u32).div_ceil(page_height);
+
+        let count = if self.double_buffered { 2 } else { 1 };
+        if pages * count * PAGE_SIZE > VRAM_SIZE {
+            return Err(Error::OutOfMemory);
+        }
+
+        let buffer = |fbp| Framebuffer {
+            fbp,
+            fbw: fbw as u8,
+            psm: self.psm,
+        };
+
+        if self.double_buffered {
+            Ok(([buffer(0), buffer(pages as u16)], (pages * 2) as u16))
+        } else {
+            Ok(([buffer(0), buffer(0)], pages as u16))
+        }
+    }
+
+    /// Reset the GS and switch to the configured mode.
+    pub fn build(self) -> Result<Video> {
+        let display = self.display()?;
+        let (buffers, used_pages) = self.framebuffers()?;
+        let timing = self.mode.timing();
+
+        unsafe {
+            gs::CSR.write(gs::Csr::new().with_reset(true));
+            arch::syncp();
+            gs::CSR.write(gs::Csr::new());
+
+            os::gs_put_imr(gs::Imr::all_masked().bits());
+            os::set_gs_crt(timing.interlaced as i16, timing.mode, timing.ffmd as i16);
+            // Kernel alarms count horizontal syncs
+            rps2_thread::timer::set_hsync_hz(self.mode.hsync_hz());
+
+            rps2_kernel::interrupt_disable_guard!();
+
+            gs::PMODE.write(
+                gs::Pmode::new()
+                    .with_en1(true)
+                    .with_crtmd(1)
+                    .with_mmod(AlphaSelect::FIXED)
+                    .with_alp(0xff),
+            );
+            gs::SMODE2.write(self.mode.smode2());
+            gs::DISPFB1.write(buffers[0].dispfb());
+            gs::DISPLAY1.write(display);
+            gs::BGCOLOR.write(gs::Bgcolor::new());
+        }
+
+        Ok(Video {
+            mode: self.mode,
+            width: self.width,
+            height: self.height,
+            buffers,
+            used_pages,
+            shown: 0,
+            vblank: VBlank::subscribe(),
+        })
+    }
+}
+
+/// The configured video output.
+#[derive(Debug)]
+pub struct Video {
+    mode: VideoMode,
+    width: u16,
+    height: u16,
+    buffers: [Framebuffer; 2],
+    used_pages: u16,
+    shown: usize,
+    vblank: VBlank,
+}
+
+impl Video {
+    pub fn builder(mode: VideoMode) -> VideoBuilder {
+        VideoBuilder::new(mode)
+    }
+
+    pub fn mode(&self) -> VideoMode {
+        self.mode
+    }
+
+    pub fn width(&self) -> u16 {
+        self.width
+    }
+
+    pub fn height(&self) -> u16 {
+        self.height
+    }
+
+    /// The buffer currently being displayed.
+    pub fn display_buffer(&self) -> Framebuffer {
+        self.buffers[self.shown]
+    }
+
+    /// The buffer to draw the next frame into.
+    ///
+    /// This is the same as the display buffer when not double buffered.
+    pub fn draw_buffer(&self) -> Framebuffer {
+        self.buffers[self.shown ^ 1]
+    }
+
+    /// First page of GS memory not used by the framebuffers.
+    pub fn free_page(&self) -> u16 {
+        self.used_pages
+    }
+
+    /// Wait for the start of the next vertical blank, returning the frame counter.
+    pub fn wait_vblank(&self) -> u64 {
+        self.vblank.wait()
+    }
+
+    /// Wait for vertical blank and display the draw buffer.
+    pub fn swap(&mut self) {
+        self.wait_vblank();
+
+        self.shown ^= 1;
+        unsafe {
+            gs::DISPFB1.write(self.buffers[self.shown].dispfb());
+        }
+    }
+}
@@ -0,0 +1,285 @@
+//! A text console drawn into a framebuffer, e.g. to show `kprint!` output on retail consoles.
+//!
+//! Text written with [`sink`] is kept in a grid, which [`draw`] uploads to GS memory.
+//!
+//! ```ignore
+//! debug::set_sink(console::sink);
+//! kprintln!("hello");
+//! console::draw(&mut packet, &video.draw_buffer(), [0xff; 3], [0; 3])?;
+//! ```
+
+use alloc_crate::string::String;
+use alloc_crate::vec::Vec;
+use core::cell::RefCell;
+use critical_section::Mutex;
+
+use rps2_pac::gif::{ImageUpload, Packet};
+use rps2_pac::gs::Psm;
+
+use super::{Error, Framebuffer, Result};
+
+pub const MAX_COLS: usize = 80;
+pub const MAX_ROWS: usize = 64;
+
+/// Size of a character cell, in pixels.
+pub const GLYPH_SIZE: usize = 8;
+
+const TAB_WIDTH: usize = 4;
+
+struct Grid {
+    cells: [[u8; MAX_COLS]; MAX_ROWS],
+    cols: usize,
+    rows: usize,
+    col: usize,
+    row: usize,
+}
+
+impl Grid {
+    fn clear(&mut self) {
+        self.cells = [[b' '; MAX_COLS]; MAX_ROWS];
+        self.col = 0;
+        self.row = 0;
+    }
+
+    fn new_line(&mut self) {
+        self.col = 0;
+        if self.row + 1 < self.rows {
+            self.row += 1;
+        } else {
+            self.cells.copy_within(1..self.rows, 0);
+            self.cells[self.rows - 1] = [b' '; MAX_COLS];
+        }
+    }
+
+    fn put(&mut self, byte: u8) {
+        if self.col == self.cols {
+            self.new_line();
+        }
+        self.cells[self.row][self.col] = byte;
+        self.col += 1;
+    }
+}
+
+static GRID: Mutex<RefCell<Grid>> = Mutex::new(RefCell::new(Grid {
+    cells: [[b' '; MAX_COLS]; MAX_ROWS],
+    cols: MAX_COLS,
+    rows: 30,
+    col: 0,
+    row: 0,
+}));
+
+/// Set the size of the console in characters, clamped to [`MAX_COLS`] and [`MAX_ROWS`].
+///
+/// Clears the console, the default is 80 by 30, filling 640x240.
+pub fn resize(cols: usize, rows: usize) {
+    critical_section::with(|cs| {
+        let mut grid = GRID.borrow_ref_mut(cs);
+        grid.cols = cols.clamp(1, MAX_COLS);
+        grid.rows = rows.clamp(1, MAX_ROWS);
+        grid.clear();
+    })
+}
+
+/// Size of the console in characters.
+pub fn size() -> (usize, usize) {
+    critical_section::with(|cs| {
+        let grid = GRID.borrow_ref(cs);
+        (grid.cols, grid.rows)
+    })
+}
+
+pub fn clear() {
+    critical_section::with(|cs| GRID.borrow_ref_mut(cs).clear())
+}
+
+/// A [`Sink`](crate::debug::Sink) appending to the console, scrolling when it is full.
+///
+/// Characters outside of printable ASCII are shown as `?`.
+pub fn sink(s: &str) {
+    critical_section::with(|cs| {
+        let mut grid = GRID.borrow_ref_mut(cs);
+        for c in s.chars() {
+            match c {
+                '\n' => grid.new_line(),
+                '\r' => grid.col = 0,
+                '\t' => {
+                    let stop = (grid.col / TAB_WIDTH + 1) * TAB_WIDTH;
+                    while grid.col < stop.min(grid.cols) {
+                        grid.put(b' ');
+                    }
+                }
+                ' '..='~' => grid.put(c as u8),
+                _ => grid.put(b'?'),
+            }
+        }
+    })
+}
+
+/// Text of `row`, without trailing spaces.
+pub fn line(row: usize) -> String {
+    critical_section::with(|cs| {
+        let grid = GRID.borrow_ref(cs);
+        let cells = &grid.cells[row.min(MAX_ROWS - 1)][..grid.cols];
+        let text = cells.iter().map(|&b| b as char).collect::<String>();
+        text.trim_end().into()
+    })
+}
+
+/// Append an upload of the whole console to the top left corner of `fb`.
+///
+/// `fb` must be a 32, 24 or 16 bit color buffer. The upload is `cols * 8` by `rows * 8` pixels,
+/// cut to the width of the buffer.
+pub fn draw(packet: &mut Packet, fb: &Framebuffer, fg: [u8; 3], bg: [u8; 3]) -> Result<()> {
+    let bytes_per_pixel = match fb.psm {
+        Psm::CT32 => 4,
+        Psm::CT24 => 3,
+        Psm::CT16 | Psm::CT16S => 2,
+        _ => return Err(Error::UnsupportedPsm),
+    };
+    let encode = |[r, g, b]: [u8; 3]| -> ([u8; 4], usize) {
+        if bytes_per_pixel == 2 {
+            let pixel = (r as u16 >> 3) | (g as u16 >> 3) << 5 | (b as u16 >> 3) << 10 | 0x8000;
+            let [lo, hi] = pixel.to_le_bytes();
+            ([lo, hi, 0, 0], 2)
+        } else {
+            ([r, g, b, 0x80], bytes_per_pixel)
+        }
+    };
+    let (fg, _) = encode(fg);
+    let (bg, _) = encode(bg);
+
+    // Copy the grid out to render outside of the critical section
+    let (cells, cols, rows) = critical_section::with(|cs| {
+        let grid = GRID.borrow_ref(cs);
+        let cols = grid.cols.min(fb.fbw as usize * 64 / GLYPH_SIZE);
+        let cells: Vec<[u8; MAX_COLS]> = grid.cells[..grid.rows].to_vec();
+        (cells, cols, grid.rows)
+    });
+
+    let width = cols * GLYPH_SIZE;
+    let mut data = Vec::with_capacity(width * rows * GLYPH_SIZE * bytes_per_pixel);
+    for line in &cells {
+        for y in 0..GLYPH_SIZE {
+            for &c in &line[..cols] {
+                let bits = FONT[(c - b' ') as usize][y];
+                for x in 0..GLYPH_SIZE {
+                    let pixel = if bits & (0x80 >> x) != 0 { &fg } else { &bg };
+                    data.extend_from_slice(&pixel[..bytes_per_pixel]);
+                }
+            }
+        }
+    }
+
+    let upload = ImageUpload {
+        // Pages are 32 blocks of 64 words
+        dbp: fb.fbp * 32,
+        dbw: fb.fbw,
+        psm: fb.psm,
+        x: 0,
+        y: 0,
+        width: width as u16,
+        height: (rows * GLYPH_SIZE) as u16,
+    };
+    packet.upload_image(&upload, &data);
+    Ok(())
+}
+
+/// 5x7 glyphs of printable ASCII, one byte per row with the leftmost pixel in the top bit.
+#[rustfmt::skip]
+static FONT: [[u8; GLYPH_SIZE]; 95] = [
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // ' '
+    [0x10, 0x10, 0x10, 0x10, 0x10, 0x00, 0x10, 0x00], // '!'
+    [0x28, 0x28, 0x28, 0x00, 0x00, 0x00, 0x00, 0x00], // '"'
+    [0x28, 0x28, 0x7c, 0x28, 0x7c, 0x28, 0x28, 0x00], // '#'
+    [0x10, 0x3c, 0x50, 0x38, 0x14, 0x78, 0x10, 0x00], // '$'
+    [0x60, 0x64, 0x08, 0x10, 0x20, 0x4c, 0x0c, 0x00], // '%'
+    [0x30, 0x48, 0x50, 0x20, 0x54, 0x48, 0x34, 0x00], // '&'
+    [0x10, 0x10, 0x20, 0x00, 0x00, 0x00, 0x00, 0x00], // "'"
+    [0x08, 0x10, 0x20, 0x20, 0x20, 0x10, 0x08, 0x00], // '('
+    [0x20, 0x10, 0x08, 0x08, 0x08, 0x10, 0x20, 0x00], // ')'
+    [0x00, 0x10, 0x54, 0x38, 0x54, 0x10, 0x00, 0x00], // '*'
+    [0x00, 0x10, 0x10, 0x7c, 0x10, 0x10, 0x00, 0x00], // '+'
+    [0x00, 0x00, 0x00, 0x00, 0x30, 0x10, 0x20, 0x00], // ','
+    [0x00, 0x00, 0x00, 0x7c, 0x00, 0x00, 0x00, 0x00], // '-'
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x30, 0x30, 0x00], // '.'
+    [0x00, 0x04, 0x08, 0x10, 0x20, 0x40, 0x00, 0x00], // '/'
+    [0x38, 0x44, 0x4c, 0x54, 0x64, 0x44, 0x38, 0x00], // '0'
+    [0x10, 0x30, 0x10, 0x10, 0x10, 0x10, 0x38, 0x00], // '1'
+    [0x38, 0x44, 0x04, 0x08, 0x10, 0x20, 0x7c, 0x00], // '2'
+    [0x7c, 0x08, 0x10, 0x08, 0x04, 0x44, 0x38, 0x00], // '3'
+    [0x08, 0x18, 0x28, 0x48, 0x7c, 0x08, 0x08, 0x00], // '4'
+    [0x7c, 0x40, 0x78, 0x04, 0x04, 0x44, 0x38, 0x00], // '5'
+    [0x18, 0x20, 0x40, 0x78, 0x44, 0x44, 0x38, 0x00], // '6'
+    [0x7c, 0x04, 0x08, 0x10, 0x20, 0x20, 0x20, 0x00], // '7'
+    [0x38, 0x44, 0x44, 0x38, 0x44, 0x44, 0x38, 0x00], // '8'
+    [0x38, 0x44, 0x44, 0x3c, 0x04, 0x08, 0x30, 0x00], // '9'
+    [0x00, 0x30, 0x30, 0x00, 0x30, 0x30, 0x00, 0x00], // ':'
+    [0x00, 0x30, 0x30, 0x00, 0x30, 0x10, 0x20, 0x00], // ';'
+    [0x08, 0x10, 0x20, 0x40, 0x20, 0x10, 0x08, 0x00], // '<'
+    [0x00, 0x00, 0x7c, 0x00, 0x7c, 0x00, 0x00, 0x00], // '='
+    [0x20, 0x10, 0x08, 0x04, 0x08, 0x10, 0x20, 0x00], // '>'
+    [0x38, 0x44, 0x04, 0x08, 0x10, 0x00, 0x10, 0x00], // '?'
+    [0x38, 0x44, 0x04, 0x34, 0x54, 0x54, 0x38, 0x00], // '@'
+    [0x38, 0x44, 0x44, 0x7c, 0x44, 0x44, 0x44, 0x00], // 'A'
+    [0x78, 0x44, 0x44, 0x78, 0x44, 0x44, 0x78, 0x00], // 'B'
+    [0x38, 0x44, 0x40, 0x40, 0x40, 0x44, 0x38, 0x00], // 'C'
+    [0x70, 0x48, 0x44, 0x44, 0x44, 0x48, 0x70, 0x00], // 'D'
+    [0x7c, 0x40, 0x40, 0x78, 0x40, 0x40, 0x7c, 0x00], // 'E'
+    [0x7c, 0x40, 0x40, 0x78, 0x40, 0x40, 0x40, 0x00], // 'F'
+    [0x38, 0x44, 0x40, 0x5c, 0x44, 0x44, 0x3c, 0x00], // 'G'
+    [0x44, 0x44, 0x44, 0x7c, 0x44, 0x44, 0x44, 0x00], // 'H'
+    [0x38, 0x10, 0x10, 0x10, 0x10, 0x10, 0x38, 0x00], // 'I'
+    [0x1c, 0x08, 0x08, 0x08, 0x08, 0x48, 0x30, 0x00], // 'J'
+    [0x44, 0x48, 0x50, 0x60, 0x50, 0x48, 0x44, 0x00], // 'K'
+    [0x40, 0x40, 0x40, 0x40, 0x40, 0x40, 0x7c, 0x00], // 'L'
+    [0x44, 0x6c, 0x54, 0x54, 0x44, 0x44, 0x44, 0x00], // 'M'
+    [0x44, 0x44, 0x64, 0x54, 0x4c, 0x44, 0x44, 0x00], // 'N'
+    [0x38, 0x44, 0x44, 0x44, 0x44, 0x44, 0x38, 0x00], // 'O'
+    [0x78, 0x44, 0x44, 0x78, 0x40, 0x40, 0x40, 0x00], // 'P'
+    [0x38, 0x44, 0x44, 0x44, 0x54, 0x48, 0x34, 0x00], // 'Q'
+    [0x78, 0x44, 0x44, 0x78, 0x50, 0x48, 0x44, 0x00], // 'R'
+    [0x3c, 0x40, 0x40, 0x38, 0x04, 0x04, 0x78, 0x00], // 'S'
+    [0x7c, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x00], // 'T'
+    [0x44, 0x44, 0x44, 0x44, 0x44, 0x44, 0x38, 0x00], // 'U'
+    [0x44, 0x44, 0x44, 0x44, 0x44, 0x28, 0x10, 0x00], // 'V'
+    [0x44, 0x44, 0x44, 0x54, 0x54, 0x54, 0x28, 0x00], // 'W'
+    [0x44, 0x44, 0x28, 0x10, 0x28, 0x44, 0x44, 0x00], // 'X'
+    [0x44, 0x44, 0x28, 0x10, 0x10, 0x10, 0x10, 0x00], // 'Y'
+    [0x7c, 0x04, 0x08, 0x10, 0x20, 0x40, 0x7c, 0x00], // 'Z'
+    [0x38, 0x20, 0x20, 0x20, 0x20, 0x20, 0x38, 0x00], // '['
+    [0x00, 0x40, 0x20, 0x10, 0x08, 0x04, 0x00, 0x00], // '\\'
+    [0x38, 0x08, 0x08, 0x08, 0x08, 0x08, 0x38, 0x00], // ']'
+    [0x10, 0x28, 0x44, 0x00, 0x00, 0x00, 0x00, 0x00], // '^'
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x7c, 0x00], // '_'
+    [0x20, 0x10, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00], // '`'
+    [0x00, 0x00, 0x38, 0x04, 0x3c, 0x44, 0x3c, 0x00], // 'a'
+    [0x40, 0x40, 0x58, 0x64, 0x44, 0x44, 0x78, 0x00], // 'b'
+    [0x00, 0x00, 0x38, 0x40, 0x40, 0x44, 0x38, 0x00], // 'c'
+    [0x04, 0x04, 0x34, 0x4c, 0x44, 0x44, 0x3c, 0x00], // 'd'
+    [0x00, 0x00, 0x38, 0x44, 0x7c, 0x40, 0x38, 0x00], // 'e'
+    [0x18, 0x24, 0x20, 0x70, 0x20, 0x20, 0x20, 0x00], // 'f'
+    [0x00, 0x3c, 0x44, 0x44, 0x3c, 0x04, 0x38, 0x00], // 'g'
+    [0x40, 0x40, 0x58, 0x64, 0x44, 0x44, 0x44, 0x00], // 'h'
+    [0x10, 0x00, 0x30, 0x10, 0x10, 0x10, 0x38, 0x00], // 'i'
+    [0x08, 0x00, 0x18, 0x08, 0x08, 0x48, 0x30, 0x00], // 'j'
+    [0x40, 0x40, 0x48, 0x50, 0x60, 0x50, 0x48, 0x00], // 'k'
+    [0x30, 0x10, 0x10, 0x10, 0x10, 0x10, 0x38, 0x00], // 'l'
+    [0x00, 0x00, 0x68, 0x54, 0x54, 0x44, 0x44, 0x00], // 'm'
+    [0x00, 0x00, 0x58, 0x64, 0x44, 0x44, 0x44, 0x00], // 'n'
+    [0x00, 0x00, 0x38, 0x44, 0x44, 0x44, 0x38, 0x00], // 'o'
+    [0x00, 0x00, 0x78, 0x44, 0x78, 0x40, 0x40, 0x00], // 'p'
+    [0x00, 0x00, 0x34, 0x4c, 0x3c, 0x04, 0x04, 0x00], // 'q'
+    [0x00, 0x00, 0x58, 0x64, 0x40, 0x40, 0x40, 0x00], // 'r'
+    [0x00, 0x00, 0x38, 0x40, 0x38, 0x04, 0x78, 0x00], // 's'
+    [0x20, 0x20, 0x70, 0x20, 0x20, 0x24, 0x18, 0x00], // 't'
+    [0x00, 0x00, 0x44, 0x44, 0x44, 0x4c, 0x34, 0x00], // 'u'
+    [0x00, 0x00, 0x44, 0x44, 0x44, 0x28, 0x10, 0x00], // 'v'
+    [0x00, 0x00, 0x44, 0x44, 0x54, 0x54, 0x28, 0x00], // 'w'
+    [0x00, 0x00, 0x44, 0x28, 0x10, 0x28, 0x44, 0x00], // 'x'
+    [0x00, 0x00, 0x44, 0x44, 0x3c, 0x04, 0x38, 0x00], // 'y'
+    [0x00, 0x00, 0x7c, 0x08, 0x10, 0x20, 0x7c, 0x00], // 'z'
+    [0x08, 0x10, 0x10, 0x20, 0x10, 0x10, 0x08, 0x00], // '{'
+    [0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x00], // '|'
+    [0x20, 0x10, 0x10, 0x08, 0x10, 0x10, 0x20, 0x00], // '}'
+    [0x00, 0x00, 0x20, 0x54, 0x08, 0x00, 0x00, 0x00], // '~'
+];
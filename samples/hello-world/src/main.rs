@@ -19,52 +19,42 @@ fn slope(i: u32) -> u32 {
 }
 
 fn funny_colors() {
-    const GS_PMODE: *mut u64 = 0x12000000 as _;
-    const GS_DISPFB1: *mut u64 = 0x12000070 as _;
-    const GS_DISPLAY1: *mut u64 = 0x12000080 as _;
-    const GS_DISPFB2: *mut u64 = 0x12000090 as _;
-    const GS_DISPLAY2: *mut u64 = 0x120000a0 as _;
-    const GS_BGCOLOR: *mut u64 = 0x120000e0 as _;
-    const GS_CSR: *mut u64 = 0x12001000 as _;
+    use rps2::pac::gs::{self, AlphaSelect, Psm};
+    use rps2::video::{Video, VideoMode};
 
-    unsafe {
-        GS_CSR.write_volatile(1 << 9);
-        rps2::arch::syncp();
-        GS_CSR.write_volatile(0);
-    }
+    let video = Video::builder(VideoMode::PalField)
+        .psm(Psm::CT24)
+        .double_buffered(false)
+        .build()
+        .unwrap();
 
+    // Only show the background color
     unsafe {
-        rps2::os::gs_put_imr(0x00007f00);
-        rps2::os::set_gs_crt(0x00, 0x03, 0x00);
+        gs::PMODE.write(
+            gs::Pmode::new()
+                .with_en1(true)
+                .with_crtmd(1)
+                .with_mmod(AlphaSelect::FIXED)
+                .with_slbg(true),
+        );
     }
 
-    // Step 5: Disable all color outputs from the GS
-    unsafe {
-        rps2::interrupt_disable_guard!();
-
-        GS_PMODE.write_volatile(0x000000a5);
-        GS_DISPFB1.write_volatile(0x00009400);
-        GS_DISPFB2.write_volatile(0x00009400);
-        GS_DISPLAY1.write_volatile(0xfff9ff38014338);
-        GS_DISPLAY2.write_volatile(0xfff9ff38014338);
-    }
-
-    // Step 6: Show different colors on the screen
+    // Show different colors on the screen
     for i in 0.. {
         let h = i;
         let r = slope(h + 240);
         let g = slope(h + 120);
         let b = slope(h);
 
-        let bgcolor = r | (g << 8) | (b << 16);
         unsafe {
-            GS_BGCOLOR.write_volatile(bgcolor as _);
+            gs::BGCOLOR.write(
+                gs::Bgcolor::new()
+                    .with_r(r as _)
+                    .with_g(g as _)
+                    .with_b(b as _),
+            );
         }
 
-        for _ in 0..200000 {
-            unsafe {
-                rps2::arch::sync();
-            }
-        }
+        video.wait_vblank();
     }
 }